@@ -0,0 +1,170 @@
+use crate::authors::{AuthorLines, Authorship};
+use crate::churn::Churn;
+use crate::report::{FileContent, Report, TreeNode};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Secret mixed into every pseudonym and content hash, so that they cannot
+/// be reversed by hashing likely names such as `src` or `package.json`, or
+/// matched against the hashes of known files.
+#[derive(Clone)]
+pub struct Key(Vec<u8>);
+
+impl Key {
+    /// The key given by a user, giving the same pseudonyms every time it is
+    /// used.
+    pub fn new(secret: &str) -> Self {
+        Key(secret.as_bytes().to_vec())
+    }
+
+    /// A random key, giving pseudonyms that differ from run to run.
+    pub fn random() -> io::Result<Self> {
+        let mut key = vec![0; 32];
+        getrandom::fill(&mut key).map_err(io::Error::other)?;
+        Ok(Key(key))
+    }
+
+    /// The HMAC-SHA256 of `data` under this key, in hex.
+    fn digest(&self, data: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(data);
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+}
+
+/// Replaces the project name and every directory and file name with a
+/// pseudonym derived from its path and `key`, keeping file extensions, and
+/// withholds all file contents. Authors are replaced with pseudonyms of
+/// their names, and content hashes with hashes of them under `key`, which
+/// still tell identical files apart. Sizes and line counts are left as they
+/// are; the list of excluded paths, whose rules name paths too, is dropped.
+///
+/// Pseudonyms are only stable across runs with the same key, so anonymized
+/// reports of the same project can be compared with each other if it is
+/// kept.
+pub fn anonymize(report: &mut Report, key: &Key) {
+    report.project_name = pseudonym(key, Path::new(&report.project_name), false);
+    report.root.name = report.project_name.clone();
+
+    report.excluded.clear();
+    for project in &mut report.projects {
+        let mut path = PathBuf::new();
+        for prefix in project
+            .path
+            .ancestors()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .skip(1)
+        {
+            path.push(pseudonym(key, prefix, false));
+        }
+        project.path = path;
+    }
+
+    let mut renamed = HashMap::new();
+    rename_children(&mut report.root, key, &mut renamed);
+    let churn = report.churn.take();
+    let mut renamed_churn = churn.as_ref().map(|_| Churn::default());
+    let authors = report.authors.take();
+    let mut renamed_authors = authors.as_ref().map(|_| Authorship::default());
+    for file in &mut report.files {
+        let changes = churn.as_ref().and_then(|churn| churn.get(&file.path));
+        let file_authors: Vec<_> = authors
+            .iter()
+            .flat_map(|authors| authors.get(&file.path))
+            .map(|author| AuthorLines {
+                name: pseudonym(key, Path::new(&author.name), false),
+                lines: author.lines,
+            })
+            .collect();
+        if let Some(path) = renamed.remove(&file.path) {
+            file.path = path;
+        }
+        if let (Some(renamed_churn), Some(changes)) = (&mut renamed_churn, changes) {
+            renamed_churn.insert(file.path.clone(), changes);
+        }
+        if let Some(renamed_authors) = &mut renamed_authors {
+            renamed_authors.insert(file.path.clone(), file_authors);
+        }
+        if let FileContent::Text(text) = &mut file.content {
+            text.spooled = None;
+            text.sha256 = key.digest(text.sha256.as_bytes());
+        }
+    }
+    report.churn = renamed_churn;
+    report.authors = renamed_authors;
+}
+
+/// Renames the entries below `dir`, whose own path is already anonymized,
+/// recording the new path of every file in `renamed`.
+fn rename_children(dir: &mut TreeNode, key: &Key, renamed: &mut HashMap<PathBuf, PathBuf>) {
+    for node in &mut dir.children {
+        node.name = pseudonym(key, &node.path, !node.is_dir);
+        if let Some(target) = &mut node.link {
+            *target = PathBuf::from(pseudonym(key, target, true));
+        }
+        let path = dir.path.join(&node.name);
+        if node.is_dir {
+            node.path = path;
+            rename_children(node, key, renamed);
+        } else {
+            renamed.insert(std::mem::replace(&mut node.path, path.clone()), path);
+        }
+    }
+}
+
+/// How `path`, found outside the project tree, is written in a report of
+/// the project in `root`, so that reports do not reveal where the project
+/// was checked out or whose machine scanned it: relative to `root` if it is
+/// inside it, otherwise with the home directory written as `~`. Relative
+/// paths are kept as they are.
+pub fn report_path(path: &Path, root: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return relative.to_path_buf();
+    }
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    match home
+        .as_deref()
+        .and_then(|home| path.strip_prefix(home).ok())
+    {
+        Some(rest) if path.is_absolute() => Path::new("~").join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The first 12 hex digits of the HMAC-SHA256 of `path` under `key`,
+/// followed by its extension if `keep_extension` is set.
+fn pseudonym(key: &Key, path: &Path, keep_extension: bool) -> String {
+    let hash = key.digest(path.to_string_lossy().as_bytes());
+    match path.extension().filter(|_| keep_extension) {
+        Some(extension) => format!("{}.{}", &hash[..12], extension.to_string_lossy()),
+        None => hash[..12].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pseudonyms_depend_on_the_key() {
+        let path = Path::new("src/main.rs");
+        let (first, second) = (Key::new("first"), Key::new("second"));
+        assert_eq!(pseudonym(&first, path, true), pseudonym(&first, path, true));
+        assert_ne!(
+            pseudonym(&first, path, true),
+            pseudonym(&second, path, true)
+        );
+        assert_ne!(
+            pseudonym(&Key::random().unwrap(), path, true),
+            pseudonym(&Key::random().unwrap(), path, true)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Who wrote the lines of a project, as attributed by `git blame`, to show
+//! how ownership of the code is spread and where knowledge is concentrated.
+
+use crate::ignores::TrackedFiles;
+use crate::report::{FileContent, FileEntry};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Lines of a file last changed by one author.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuthorLines {
+    /// The author's name, after `.mailmap` is applied.
+    pub name: String,
+    pub lines: usize,
+}
+
+/// The authors of every file in a report, by path relative to the project
+/// root.
+#[derive(Clone, Default)]
+pub struct Authorship {
+    files: HashMap<PathBuf, Vec<AuthorLines>>,
+}
+
+impl Authorship {
+    /// Runs `git blame` on each of `files` in `root` that git tracks and
+    /// that has text, which must be inside a git work tree. Lines changed
+    /// since the last commit are not attributed to anyone.
+    pub fn blame(root: &Path, files: &[FileEntry]) -> io::Result<Self> {
+        let tracked = TrackedFiles::load(root)?;
+        let paths: Vec<_> = files
+            .iter()
+            .filter(|file| matches!(file.content, FileContent::Text(_)))
+            .map(|file| file.path.as_path())
+            .filter(|path| tracked.contains(path, false))
+            .collect();
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(paths.len().max(1));
+        let next = AtomicUsize::new(0);
+        let authorship = Mutex::new(Authorship::default());
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else {
+                        break;
+                    };
+                    // A file git cannot blame, such as one added but never
+                    // committed, has no authors yet.
+                    if let Ok(authors) = blame_file(root, path) {
+                        authorship
+                            .lock()
+                            .unwrap()
+                            .insert(path.to_path_buf(), authors);
+                    }
+                });
+            }
+        });
+        Ok(authorship.into_inner().unwrap())
+    }
+
+    /// The authors of the file at `path`, relative to the project root, most
+    /// lines first; empty if none are known.
+    pub fn get(&self, path: &Path) -> &[AuthorLines] {
+        self.files.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Records the authors of the file at `path`, as read back from a report.
+    pub fn insert(&mut self, path: PathBuf, authors: Vec<AuthorLines>) {
+        if !authors.is_empty() {
+            self.files.insert(path, authors);
+        }
+    }
+
+    /// Every file with its authors, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &[AuthorLines])> {
+        self.files
+            .iter()
+            .map(|(path, authors)| (path.as_path(), authors.as_slice()))
+    }
+}
+
+/// Counts the lines of `path` per author, most lines first and then by name.
+fn blame_file(root: &Path, path: &Path) -> io::Result<Vec<AuthorLines>> {
+    let output = Command::new("git")
+        .args(["blame", "--line-porcelain", "-w", "--"])
+        .arg(path)
+        .current_dir(root)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git blame failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let mut lines: HashMap<String, usize> = HashMap::new();
+    let mut author = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(name) = line.strip_prefix("author ") {
+            author = Some(name.to_string());
+        } else if line == "author-mail <not.committed.yet>" {
+            author = None;
+        } else if line.starts_with('\t') {
+            if let Some(name) = author.take() {
+                *lines.entry(name).or_default() += 1;
+            }
+        }
+    }
+    let mut authors: Vec<_> = lines
+        .into_iter()
+        .map(|(name, lines)| AuthorLines { name, lines })
+        .collect();
+    authors.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.name.cmp(&b.name)));
+    Ok(authors)
+}
@@ -0,0 +1,30 @@
+use crate::events::Logger;
+use crate::scan::{scan_project, ScanOptions};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Scans `start_dir` `runs` times without writing a report and logs throughput.
+pub fn run(start_dir: &Path, runs: u32, options: &ScanOptions, log: &Logger) -> io::Result<()> {
+    let runs = runs.max(1);
+    let silent = Logger::silent();
+    let mut min = Duration::MAX;
+    let mut total = Duration::ZERO;
+    let mut files = 0;
+    let mut bytes = 0;
+
+    log.info(&format!("Benchmarking {}...", start_dir.display()));
+    for _ in 0..runs {
+        let started = Instant::now();
+        let report = scan_project(start_dir, options, &silent)?;
+        let elapsed = started.elapsed();
+
+        min = min.min(elapsed);
+        total += elapsed;
+        files = report.files.len();
+        bytes = report.total_bytes();
+    }
+
+    log.bench(runs, files, bytes, min, total / runs);
+    Ok(())
+}
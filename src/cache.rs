@@ -0,0 +1,443 @@
+//! Measurements remembered between runs, so files that have not changed
+//! since the last scan need not be measured, or even opened, again.
+
+use crate::comments::LineCounts;
+use crate::language::{self, language};
+use crate::output::format_size;
+use crate::report::Text;
+use crate::sfc::Section;
+use clap::ValueEnum;
+use encoding_rs::Encoding;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Directory holding the cache inside the analyzed project, unless another
+/// is configured. Scans always skip it.
+pub const DIR: &str = ".codetree";
+const FILE_NAME: &str = "cache.json";
+
+/// Bumped whenever the meaning of cached measurements changes, so caches
+/// written by older versions are discarded.
+pub const VERSION: u32 = 6;
+
+#[derive(Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    /// Whether code blocks in documents were split off into sections.
+    count_doc_blocks: bool,
+    /// Whether secrets were redacted before measuring.
+    redact: bool,
+    files: HashMap<PathBuf, Entry>,
+}
+
+/// What was measured from one file, and the size and modification time it
+/// had when it was read.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    /// Nanoseconds since the Unix epoch.
+    modified: u128,
+    /// Language the file was measured as, which language overrides can
+    /// change without touching the file.
+    language: String,
+    lines: usize,
+    counts: LineCounts,
+    tokens: usize,
+    /// Language named by a `#!` line, see [`Text::script`].
+    script: Option<String>,
+    encoding: String,
+    redactions: usize,
+    sha256: String,
+    sections: Vec<CachedSection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSection {
+    language: String,
+    lines: usize,
+    bytes: u64,
+}
+
+/// Measurements of an unchanged file, as taken by an earlier scan.
+pub struct Measurements {
+    pub lines: usize,
+    pub counts: LineCounts,
+    pub tokens: usize,
+    pub script: Option<&'static str>,
+    pub encoding: &'static str,
+    pub redactions: usize,
+    pub sha256: String,
+    pub sections: Vec<Section>,
+}
+
+impl Cache {
+    pub fn new(count_doc_blocks: bool, redact: bool) -> Self {
+        Cache {
+            version: VERSION,
+            count_doc_blocks,
+            redact,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Path of the cache kept in `dir`.
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(FILE_NAME)
+    }
+
+    /// Reads the cache at `path`. A missing or unreadable cache, or one
+    /// written by another version or with other settings, is treated as
+    /// empty: the files are simply measured again.
+    pub fn load(path: &Path, count_doc_blocks: bool, redact: bool) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Cache>(&data).ok())
+            .filter(|cache| {
+                cache.version == VERSION
+                    && cache.count_doc_blocks == count_doc_blocks
+                    && cache.redact == redact
+            })
+            .unwrap_or_else(|| Cache::new(count_doc_blocks, redact))
+    }
+
+    /// Writes the cache to `path`, creating its directory if needed. Past
+    /// `max_size` bytes, the most recently modified files, the likeliest to
+    /// change before the next scan, are left out until it fits; a cache
+    /// that cannot fit at all is not written, and removes any earlier one.
+    pub fn save(&mut self, path: &Path, max_size: Option<u64>) -> io::Result<()> {
+        let mut data = serde_json::to_vec(self)?;
+        if let Some(max_size) = max_size {
+            if data.len() as u64 > max_size {
+                self.evict(data.len() as u64 - max_size)?;
+                data = serde_json::to_vec(self)?;
+            }
+            if data.len() as u64 > max_size {
+                clear(path)?;
+                return Ok(());
+            }
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, data)
+    }
+
+    /// Removes entries, most recently modified first, until at least
+    /// `excess` bytes of the serialized cache are gone.
+    fn evict(&mut self, excess: u64) -> io::Result<()> {
+        let mut paths: Vec<_> = self
+            .files
+            .iter()
+            .map(|(path, entry)| (entry.modified, path.clone()))
+            .collect();
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+        let mut freed = 0;
+        for (_, path) in paths {
+            if freed >= excess {
+                break;
+            }
+            if let Some(entry) = self.files.remove(&path) {
+                // The key, a colon, the entry and a comma.
+                freed += (serde_json::to_vec(&path)?.len() + serde_json::to_vec(&entry)?.len() + 2)
+                    as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// The measurements of the file at `path`, relative to the project
+    /// root, if its size and modification time are unchanged.
+    pub fn get(&self, path: &Path, metadata: &Metadata) -> Option<Measurements> {
+        let entry = self.files.get(path)?;
+        if entry.size != metadata.len()
+            || Some(entry.modified) != modified(metadata)
+            || entry.language != language(path).unwrap_or("other")
+        {
+            return None;
+        }
+        let script = match &entry.script {
+            Some(script) => Some(language::by_name(script)?),
+            None => None,
+        };
+        let sections = entry
+            .sections
+            .iter()
+            .map(|section| {
+                Some(Section {
+                    language: language::by_name(&section.language)?,
+                    lines: section.lines,
+                    bytes: section.bytes,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Measurements {
+            lines: entry.lines,
+            counts: entry.counts,
+            tokens: entry.tokens,
+            script,
+            encoding: Encoding::for_label(entry.encoding.as_bytes())?.name(),
+            redactions: entry.redactions,
+            sha256: entry.sha256.clone(),
+            sections,
+        })
+    }
+
+    /// Remembers the measurements of the file at `path` as it was when
+    /// `metadata` was taken.
+    pub fn insert(&mut self, path: &Path, metadata: &Metadata, text: &Text) {
+        let Some(modified) = modified(metadata) else {
+            return;
+        };
+        self.files.insert(
+            path.to_path_buf(),
+            Entry {
+                size: metadata.len(),
+                modified,
+                language: language(path).unwrap_or("other").to_string(),
+                lines: text.lines,
+                counts: text.counts,
+                tokens: text.tokens,
+                script: text.script.map(str::to_string),
+                encoding: text.encoding.to_string(),
+                redactions: text.redactions,
+                sha256: text.sha256.clone(),
+                sections: text
+                    .sections
+                    .iter()
+                    .map(|section| CachedSection {
+                        language: section.language.to_string(),
+                        lines: section.lines,
+                        bytes: section.bytes,
+                    })
+                    .collect(),
+            },
+        );
+    }
+}
+
+/// What `codetree cache` does with a project's cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Action {
+    /// Print where the cache is, its size and how many files it covers
+    Status,
+    /// Remove the cache, so the next scan measures every file again
+    Clear,
+    /// Print the path of the cache file
+    Path,
+}
+
+/// Carries out `action` on the cache kept in `dir`, printing the outcome.
+pub fn run(action: Action, dir: &Path) -> io::Result<()> {
+    let path = Cache::path(dir);
+    match action {
+        Action::Path => println!("{}", path.display()),
+        Action::Clear if clear(&path)? => println!("Removed {}", path.display()),
+        Action::Clear => println!("No cache at {}", path.display()),
+        Action::Status => match inspect(&path) {
+            Ok(None) => println!("No cache at {}", path.display()),
+            Ok(Some(summary)) => {
+                println!("Cache: {}", path.display());
+                println!("Size: {}", format_size(summary.bytes));
+                println!("Files: {}", summary.files);
+                if summary.version != Some(VERSION) {
+                    println!("Written by another version of codetree; the next scan rebuilds it");
+                }
+            }
+            Err(err) => println!(
+                "Cache: {}\nUnreadable ({}); the next scan rebuilds it",
+                path.display(),
+                err
+            ),
+        },
+    }
+    Ok(())
+}
+
+/// Directory of the cache of the project in `root`: `configured`, relative
+/// to `root`, or else [`DIR`].
+pub fn dir(root: &Path, configured: Option<&Path>) -> PathBuf {
+    root.join(configured.unwrap_or(Path::new(DIR)))
+}
+
+/// What a cache file holds, without the measurements themselves.
+pub struct Summary {
+    pub bytes: u64,
+    /// Version of codetree's cache format it was written in, if any.
+    pub version: Option<u32>,
+    pub files: usize,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: Option<u32>,
+    #[serde(default)]
+    files: HashMap<PathBuf, IgnoredAny>,
+}
+
+/// Reads the cache at `path` far enough to summarize it, or returns `None`
+/// if there is none. A file that is not a cache is an error.
+pub fn inspect(path: &Path) -> io::Result<Option<Summary>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let header: Header = serde_json::from_slice(&data)?;
+    Ok(Some(Summary {
+        bytes: data.len() as u64,
+        version: header.version,
+        files: header.files.len(),
+    }))
+}
+
+/// Removes the cache at `path`, and its directory if nothing else is left
+/// in it. Returns whether there was a cache to remove.
+pub fn clear(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    }
+    if let Some(dir) = path.parent() {
+        // Fails, as it should, if the directory holds anything else.
+        let _ = fs::remove_dir(dir);
+    }
+    Ok(true)
+}
+
+fn modified(metadata: &Metadata) -> Option<u128> {
+    let modified = metadata.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Measured;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    /// Writes `content` to `name` in `dir`, modified `age` seconds ago, and
+    /// returns its metadata and measurements.
+    fn file(dir: &Path, name: &str, content: &str, age: u64) -> (Metadata, Text) {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let text = Measured::new(Path::new(name), content.to_string(), false).omit();
+        (fs::metadata(&path).unwrap(), text)
+    }
+
+    #[test]
+    fn entries_are_reused_while_the_file_is_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let (metadata, text) = file(dir.path(), "a.rs", "fn main() {}\n", 100);
+        let mut cache = Cache::new(false, true);
+        cache.insert(Path::new("a.rs"), &metadata, &text);
+        let known = cache.get(Path::new("a.rs"), &metadata).unwrap();
+        assert_eq!(known.lines, 1);
+        assert_eq!(known.sha256, text.sha256);
+        assert!(cache.get(Path::new("b.rs"), &metadata).is_none());
+    }
+
+    #[test]
+    fn size_change_invalidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (metadata, text) = file(dir.path(), "a.rs", "fn main() {}\n", 100);
+        let mut cache = Cache::new(false, true);
+        cache.insert(Path::new("a.rs"), &metadata, &text);
+        let (metadata, _) = file(dir.path(), "a.rs", "fn main() { run() }\n", 100);
+        assert!(cache.get(Path::new("a.rs"), &metadata).is_none());
+    }
+
+    #[test]
+    fn modification_time_change_invalidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (metadata, text) = file(dir.path(), "a.rs", "fn main() {}\n", 100);
+        let mut cache = Cache::new(false, true);
+        cache.insert(Path::new("a.rs"), &metadata, &text);
+        // Same length, so only the time tells them apart.
+        let (metadata, _) = file(dir.path(), "a.rs", "fn mian() {}\n", 50);
+        assert!(cache.get(Path::new("a.rs"), &metadata).is_none());
+    }
+
+    #[test]
+    fn language_change_invalidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (metadata, text) = file(dir.path(), "a.rs", "fn main() {}\n", 100);
+        let mut cache = Cache::new(false, true);
+        cache.insert(Path::new("a.rs"), &metadata, &text);
+        // As if a language override had mapped the file to another language
+        // when it was cached.
+        cache.files.get_mut(Path::new("a.rs")).unwrap().language = "python".to_string();
+        assert!(cache.get(Path::new("a.rs"), &metadata).is_none());
+    }
+
+    #[test]
+    fn load_discards_caches_with_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Cache::path(&dir.path().join(DIR));
+        let (metadata, text) = file(dir.path(), "a.rs", "fn main() {}\n", 100);
+        let mut cache = Cache::new(false, true);
+        cache.insert(Path::new("a.rs"), &metadata, &text);
+        cache.save(&path, None).unwrap();
+
+        let reused = |count_doc_blocks, redact| {
+            Cache::load(&path, count_doc_blocks, redact)
+                .get(Path::new("a.rs"), &metadata)
+                .is_some()
+        };
+        assert!(reused(false, true));
+        assert!(!reused(true, true));
+        assert!(!reused(false, false));
+
+        cache.version = VERSION - 1;
+        cache.save(&path, None).unwrap();
+        assert!(!reused(false, true));
+    }
+
+    #[test]
+    fn save_leaves_out_the_newest_files_past_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Cache::path(&dir.path().join(DIR));
+        let mut cache = Cache::new(false, true);
+        let mut files = Vec::new();
+        for (name, age) in [("old.rs", 300), ("mid.rs", 200), ("new.rs", 100)] {
+            let (metadata, text) = file(dir.path(), name, "fn main() {}\n", age);
+            cache.insert(Path::new(name), &metadata, &text);
+            files.push((name, metadata));
+        }
+        let full = serde_json::to_vec(&cache).unwrap().len() as u64;
+
+        cache.save(&path, Some(full - 1)).unwrap();
+        let size = fs::metadata(&path).unwrap().len();
+        assert!(size < full);
+        let saved = Cache::load(&path, false, true);
+        let kept: Vec<_> = files
+            .iter()
+            .map(|(name, metadata)| saved.get(Path::new(name), metadata).is_some())
+            .collect();
+        assert_eq!(kept, [true, true, false]);
+    }
+
+    #[test]
+    fn save_removes_a_cache_that_cannot_fit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Cache::path(&dir.path().join(DIR));
+        let mut cache = Cache::new(false, true);
+        cache.save(&path, None).unwrap();
+        assert!(path.is_file());
+        cache.save(&path, Some(1)).unwrap();
+        assert!(!path.exists());
+    }
+}
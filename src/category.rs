@@ -0,0 +1,124 @@
+use crate::language::language;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Component, Path};
+
+/// What a file is for, a coarser view of a project than its languages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Code,
+    Tests,
+    Docs,
+    Config,
+    Data,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Code,
+        Category::Tests,
+        Category::Docs,
+        Category::Config,
+        Category::Data,
+    ];
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Code => "code",
+            Category::Tests => "tests",
+            Category::Docs => "docs",
+            Category::Config => "config",
+            Category::Data => "data",
+        })
+    }
+}
+
+const TEST_DIRS: [&str; 6] = ["test", "tests", "__tests__", "spec", "specs", "e2e"];
+const DOC_DIRS: [&str; 2] = ["doc", "docs"];
+const DOC_EXTENSIONS: [&str; 5] = ["md", "mdx", "rst", "adoc", "txt"];
+const DOC_NAMES: [&str; 6] = [
+    "readme",
+    "license",
+    "changelog",
+    "contributing",
+    "authors",
+    "notice",
+];
+const CONFIG_EXTENSIONS: [&str; 9] = [
+    "toml",
+    "yaml",
+    "yml",
+    "ini",
+    "cfg",
+    "conf",
+    "env",
+    "properties",
+    "lock",
+];
+const CONFIG_NAMES: [&str; 5] = [
+    "dockerfile",
+    "makefile",
+    "package.json",
+    "composer.json",
+    "jenkinsfile",
+];
+
+/// Classifies `path` (relative to the project root) by its directories,
+/// name and extension. Tests win over everything else, so a fixture under
+/// `tests/` counts as tests; files that are neither code nor one of the
+/// other categories count as data.
+pub fn category(path: &Path) -> Category {
+    classify(path, language(path))
+}
+
+/// Classifies `path` as [`category`] does, for a file known to be written
+/// in `language`, such as a script recognized by its `#!` line.
+pub fn classify(path: &Path, language: Option<&str>) -> Category {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let in_dir = |dirs: &[&str]| {
+        path.parent().is_some_and(|parent| {
+            parent.components().any(|component| match component {
+                Component::Normal(dir) => dirs.contains(&dir.to_string_lossy().as_ref()),
+                _ => false,
+            })
+        })
+    };
+
+    let is_test_name = name.contains(".test.")
+        || name.contains(".spec.")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem.starts_with("test_");
+    if in_dir(&TEST_DIRS) || is_test_name {
+        return Category::Tests;
+    }
+    if DOC_NAMES.contains(&stem)
+        || DOC_EXTENSIONS.contains(&extension.as_str())
+        || (in_dir(&DOC_DIRS) && language.is_none())
+    {
+        return Category::Docs;
+    }
+    let is_config_name = CONFIG_NAMES.contains(&name.as_str())
+        || name.starts_with('.')
+        || name.contains(".config.")
+        || (extension == "json" && (name.contains("config") || name.starts_with("tsconfig")));
+    if is_config_name || CONFIG_EXTENSIONS.contains(&extension.as_str()) {
+        return Category::Config;
+    }
+    match language {
+        Some("json" | "xml" | "sql") | None => Category::Data,
+        Some(_) => Category::Code,
+    }
+}
@@ -0,0 +1,92 @@
+//! How often files change, from the history of the git repository holding
+//! the project, to point at the files most worth a maintainer's attention.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Commits within this many days count as recent changes.
+pub const RECENT_DAYS: u64 = 90;
+
+/// How often one file changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FileChurn {
+    /// Non-merge commits touching the file.
+    pub commits: usize,
+    /// Those of the commits made in the last [`RECENT_DAYS`] days.
+    pub recent_commits: usize,
+}
+
+/// The churn of every file in the history of a project, by path relative
+/// to the project root.
+#[derive(Clone, Default)]
+pub struct Churn {
+    files: HashMap<PathBuf, FileChurn>,
+}
+
+impl Churn {
+    /// Asks git for the history of `root`, which must be inside a git work
+    /// tree. Files are counted under their current names; renames start a
+    /// new count.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let output = Command::new("git")
+            .args([
+                "-c",
+                "core.quotePath=false",
+                "log",
+                "--no-merges",
+                "--relative",
+                "--name-only",
+                "--format=%x00%ct",
+                "--",
+                ".",
+            ])
+            .current_dir(root)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git log failed in {}: {}",
+                root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let recent = SystemTime::now()
+            .checked_sub(Duration::from_secs(RECENT_DAYS * 24 * 60 * 60))
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_secs());
+        let mut churn = Churn::default();
+        let mut is_recent = false;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(time) = line.strip_prefix('\0') {
+                is_recent = time.parse::<u64>().is_ok_and(|time| time >= recent);
+            } else if !line.is_empty() {
+                let entry = churn.files.entry(PathBuf::from(line)).or_default();
+                entry.commits += 1;
+                entry.recent_commits += usize::from(is_recent);
+            }
+        }
+        Ok(churn)
+    }
+
+    /// The churn of the file at `path`, relative to the project root, if it
+    /// has any history.
+    pub fn get(&self, path: &Path) -> Option<FileChurn> {
+        self.files.get(path).copied()
+    }
+
+    /// Records the churn of the file at `path`, as read back from a report.
+    pub fn insert(&mut self, path: PathBuf, churn: FileChurn) {
+        self.files.insert(path, churn);
+    }
+
+    /// Every file with its churn, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, FileChurn)> {
+        self.files
+            .iter()
+            .map(|(path, churn)| (path.as_path(), *churn))
+    }
+}
@@ -0,0 +1,135 @@
+//! Telling code, comment and blank lines apart.
+
+use crate::language;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How many lines of a file hold code, only comments, or only whitespace.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LineCounts {
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+}
+
+impl LineCounts {
+    pub fn add(&mut self, other: LineCounts) {
+        self.code += other.code;
+        self.comments += other.comments;
+        self.blanks += other.blanks;
+    }
+}
+
+/// Classifies every line of `content`, written in `language`. A line with
+/// any code outside a comment counts as code. Comment markers inside string
+/// literals are not recognized as such, so counts are close estimates
+/// rather than exact figures, as with other line counters.
+pub fn count(language: &str, content: &str) -> LineCounts {
+    let syntax = language::comment_syntax(language);
+    let mut counts = LineCounts::default();
+    // The delimiter closing the block comment that is open, if any.
+    let mut open: Option<&str> = None;
+
+    for line in content.lines() {
+        let mut rest = line.trim();
+        if rest.is_empty() {
+            counts.blanks += 1;
+            continue;
+        }
+        let mut code = false;
+        loop {
+            if let Some(end) = open {
+                match rest.find(end) {
+                    Some(i) => {
+                        rest = rest[i + end.len()..].trim_start();
+                        open = None;
+                    }
+                    None => break,
+                }
+            }
+            if rest.is_empty() {
+                break;
+            }
+            // Block starts are checked first, as `--[[` begins with `--`.
+            if let Some((start, end)) = syntax.block.iter().find(|(s, _)| rest.starts_with(s)) {
+                rest = &rest[start.len()..];
+                open = Some(end);
+                continue;
+            }
+            if syntax.line.iter().any(|prefix| rest.starts_with(prefix)) {
+                break;
+            }
+            // Code up to the next comment marker, if a block opens there.
+            code = true;
+            let next = syntax
+                .block
+                .iter()
+                .map(|(start, _)| start)
+                .chain(syntax.line)
+                .filter_map(|marker| rest.find(marker).map(|i| (i, marker)))
+                .min_by_key(|(i, marker)| (*i, std::cmp::Reverse(marker.len())));
+            match next {
+                Some((i, marker)) if !syntax.line.contains(marker) => rest = &rest[i..],
+                _ => break,
+            }
+        }
+        if code {
+            counts.code += 1;
+        } else {
+            counts.comments += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(code: usize, comments: usize, blanks: usize) -> LineCounts {
+        LineCounts {
+            code,
+            comments,
+            blanks,
+        }
+    }
+
+    #[test]
+    fn c_style_lines_and_blocks() {
+        let rust = "// line\n\nfn main() {} // trailing\n/* one\n   two */\nlet x = 1; /* inline */\n  \t\n";
+        assert_eq!(count("rust", rust), counts(2, 3, 2));
+    }
+
+    #[test]
+    fn code_after_a_closing_block_is_code() {
+        assert_eq!(count("rust", "/* a\n*/ let x = 1;\n"), counts(1, 1, 0));
+        assert_eq!(count("rust", "let x = /* a */ 1;\n"), counts(1, 0, 0));
+    }
+
+    #[test]
+    fn hash_comments() {
+        let python = "#!/usr/bin/env python3\n# comment\n\nprint(1)  # trailing\n";
+        assert_eq!(count("python", python), counts(1, 2, 1));
+    }
+
+    #[test]
+    fn block_starts_take_precedence_over_line_markers() {
+        let lua = "--[[ block\nstill comment\n]]\n-- line\nprint(1)\n";
+        assert_eq!(count("lua", lua), counts(1, 4, 0));
+    }
+
+    #[test]
+    fn markup_comments() {
+        let html = "<!-- header -->\n<p>text</p>\n<!--\nnote\n-->\n";
+        assert_eq!(count("html", html), counts(1, 4, 0));
+    }
+
+    #[test]
+    fn languages_without_comments_have_only_code_and_blanks() {
+        assert_eq!(
+            count("json", "{\n\n  \"a\": \"// no\"\n}\n"),
+            counts(3, 0, 1)
+        );
+        assert_eq!(count("unknown", "# not a comment\n"), counts(1, 0, 0));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::language::LanguageOverride;
+use crate::output::text::{TreeGlyphs, TreeStyle};
+use crate::output::{self, OutputFormat};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the project configuration file, looked up in the analyzed
+/// directory.
+pub const FILE_NAME: &str = ".codetree.toml";
+
+/// Persistent settings from the user's `~/.config/codetree/config.toml` and
+/// the project's `.codetree.toml`. Command-line flags and `CODETREE_*`
+/// variables take precedence over both.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub format: Option<OutputFormat>,
+    /// Where to write the report, relative to the analyzed directory.
+    pub output: Option<PathBuf>,
+    pub max_depth: Option<usize>,
+    /// Where `--cache` keeps its file, relative to the analyzed directory.
+    pub cache_dir: Option<PathBuf>,
+    /// Largest size in bytes the `--cache` file may take.
+    #[serde(deserialize_with = "size")]
+    pub cache_max_size: Option<u64>,
+    /// Number of entries in ranked sections, as `--top`.
+    pub top: Option<usize>,
+    pub tree_style: Option<TreeStyle>,
+    /// Glyphs as `BRANCH,LAST,VERTICAL`, overriding `tree-style`.
+    pub tree_glyphs: Option<TreeGlyphs>,
+    /// `PATTERN=LANGUAGE` pairs, checked after those given on the command
+    /// line.
+    pub language_overrides: Vec<LanguageOverride>,
+    /// Whether the built-in lists of excluded directories and files apply.
+    pub default_excludes: Option<bool>,
+    /// Directory names excluded in addition to the built-in list.
+    pub exclude_dirs: Vec<String>,
+    /// File names excluded in addition to the built-in list.
+    pub exclude_files: Vec<String>,
+    /// Which files are treated as holding secrets.
+    pub sensitive: SensitivePolicy,
+}
+
+/// The `[sensitive]` section: files never read because they may hold
+/// secrets, on top of the built-in list, and exceptions to it.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensitivePolicy {
+    /// Globs, matched like `--exclude` patterns.
+    pub patterns: Vec<String>,
+    /// Regular expressions, matched against paths relative to the project
+    /// root with `/` separators.
+    pub regex: Vec<String>,
+    /// Globs of files to read even though they match a sensitive pattern.
+    pub allow: Vec<String>,
+}
+
+impl SensitivePolicy {
+    /// Adds the entries of `other` to these.
+    pub fn extend(&mut self, other: SensitivePolicy) {
+        self.patterns.extend(other.patterns);
+        self.regex.extend(other.regex);
+        self.allow.extend(other.allow);
+    }
+}
+
+impl Config {
+    /// Loads the user's configuration, then the project's in `root`. Values
+    /// set in the project win; exclusion lists are combined, and language
+    /// overrides are checked in the project's first.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let mut config = match user_path() {
+            Some(path) if path.is_file() => read(&path)?,
+            _ => Config::default(),
+        };
+        let project = root.join(FILE_NAME);
+        if project.is_file() {
+            let project = read(&project)?;
+            config.format = project.format.or(config.format);
+            config.output = project.output.or(config.output);
+            config.max_depth = project.max_depth.or(config.max_depth);
+            config.cache_dir = project.cache_dir.or(config.cache_dir);
+            config.cache_max_size = project.cache_max_size.or(config.cache_max_size);
+            config.top = project.top.or(config.top);
+            if project.tree_style.is_some() || project.tree_glyphs.is_some() {
+                config.tree_style = project.tree_style;
+                config.tree_glyphs = project.tree_glyphs;
+            }
+            config.language_overrides =
+                [project.language_overrides, config.language_overrides].concat();
+            config.default_excludes = project.default_excludes.or(config.default_excludes);
+            config.exclude_dirs.extend(project.exclude_dirs);
+            config.exclude_files.extend(project.exclude_files);
+            config.sensitive.extend(project.sensitive);
+        }
+        Ok(config)
+    }
+
+    /// Where a scan of `start_dir` writes its report in `format`: `output`,
+    /// or the format's default name in `output_dir`, or the configured
+    /// `output` relative to `start_dir`, or else the default name there.
+    pub fn report_path(
+        &self,
+        start_dir: &Path,
+        format: OutputFormat,
+        output: Option<&Path>,
+        output_dir: Option<&Path>,
+    ) -> PathBuf {
+        match (output, output_dir, &self.output) {
+            (Some(path), _, _) => path.to_path_buf(),
+            (None, Some(dir), _) => dir.join(format.output_name()),
+            (None, None, Some(path)) => start_dir.join(path),
+            (None, None, None) => start_dir.join(format.output_name()),
+        }
+    }
+}
+
+/// Reads a size such as `"50MB"`, as [`output::parse_size`] parses it.
+fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    output::parse_size(&text)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn read(path: &Path) -> io::Result<Config> {
+    toml::from_str(&fs::read_to_string(path)?).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
+
+/// `$XDG_CONFIG_HOME/codetree/config.toml`, falling back to `~/.config`.
+pub fn user_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("codetree").join("config.toml"))
+}
+
+/// Writes a commented `.codetree.toml` into `dir`, refusing to overwrite an
+/// existing one, and returns its path.
+pub fn init(dir: &Path) -> io::Result<PathBuf> {
+    let path = dir.join(FILE_NAME);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            ),
+            _ => err,
+        })?;
+    file.write_all(template().as_bytes())?;
+    Ok(path)
+}
+
+/// The commented `.codetree.toml` written by [`init`].
+fn template() -> String {
+    let formats: Vec<_> = OutputFormat::value_variants()
+        .iter()
+        .filter_map(|format| format.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    format!(
+        r#"# codetree settings for this project. Command-line flags and CODETREE_*
+# environment variables override these values.
+
+# Report format, one of:
+# {}
+# format = "text"
+
+# Where to write the report, relative to this directory.
+# output = "codetree.txt"
+
+# Only descend this many directory levels below this directory.
+# max-depth = 4
+
+# Where --cache keeps its file, relative to this directory.
+# cache-dir = ".codetree"
+
+# Largest size the cache may grow to; past it, the most recently changed
+# files are left out of it.
+# cache-max-size = "50MB"
+
+# Number of entries in ranked sections: hotspots, authors and extensions in
+# the text report, top languages and files in the others (defaults depend on
+# the format).
+# top = 10
+
+# Characters used to draw the file tree: unicode, ascii or indent, or custom
+# glyphs as BRANCH,LAST,VERTICAL, which take precedence.
+# tree-style = "ascii"
+# tree-glyphs = "+-- ,+-- ,|   "
+
+# Treat files matching a pattern as a language, checked after any
+# --language-override given on the command line.
+# language-overrides = ["*.tpl=gotemplate", "Jenkinsfile=groovy"]
+
+# Directory and file names to skip in addition to the built-in lists.
+# exclude-dirs = ["generated"]
+# exclude-files = ["schema.graphql"]
+
+# Set to false to analyze the directories and files skipped by default, such
+# as assets, public, README.md and lock files.
+# default-excludes = true
+
+# Files that may hold secrets are never read. Add globs or regular
+# expressions (matched against paths relative to this directory) to the
+# built-in list of keys and .env files, and allow files that match it but
+# hold no secrets.
+# [sensitive]
+# patterns = ["*.secret"]
+# regex = ["^deploy/.*\\.json$"]
+# allow = ["test/fixtures/*.pem"]
+"#,
+        wrap(&formats.join(", "), "# ")
+    )
+}
+
+/// `text` broken into lines of at most 76 characters, each after `prefix`.
+fn wrap(text: &str, prefix: &str) -> String {
+    let mut lines = vec![String::new()];
+    for word in text.split(' ') {
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && line.len() + 1 + word.len() > 76 {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines.join(&format!("\n{}", prefix))
+}
@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Evidence that a directory holds a certain kind of project.
+enum Marker {
+    /// A file or directory at this path relative to the root. A final
+    /// component starting with `*` matches any name with that suffix.
+    Path(&'static str),
+    /// A file at this path whose contents include the given text.
+    Contains(&'static str, &'static str),
+}
+
+/// A kind of project recognised from marker files, and the build and
+/// dependency directories it produces that never belong in a report.
+pub struct Ecosystem {
+    pub name: &'static str,
+    /// Heading the ecosystem is grouped under in reports, such as `Mobile`.
+    /// Reports list categories in the order they first appear here.
+    pub category: &'static str,
+    /// Every group must have at least one marker present.
+    requires: &'static [&'static [Marker]],
+    /// Gitignore-syntax patterns, anchored at the project root.
+    pub excluded: &'static [&'static str],
+    /// Frameworks or tools reported alongside the ecosystem when their
+    /// marker is present.
+    hints: &'static [(&'static str, Marker)],
+}
+
+/// An ecosystem found in a project, with the framework hints that matched.
+#[derive(Clone)]
+pub struct Detection {
+    pub ecosystem: &'static Ecosystem,
+    pub frameworks: Vec<&'static str>,
+}
+
+const ECOSYSTEMS: [Ecosystem; 13] = [
+    Ecosystem {
+        name: "Android",
+        category: "Mobile",
+        requires: &[
+            &[
+                Marker::Path("build.gradle"),
+                Marker::Path("build.gradle.kts"),
+                Marker::Path("settings.gradle"),
+                Marker::Path("settings.gradle.kts"),
+            ],
+            &[
+                Marker::Path("AndroidManifest.xml"),
+                Marker::Path("app/src/main/AndroidManifest.xml"),
+            ],
+        ],
+        excluded: &[".gradle/", ".cxx/", ".externalNativeBuild/", "/app/build/"],
+        hints: &[],
+    },
+    Ecosystem {
+        name: "iOS",
+        category: "Mobile",
+        requires: &[&[
+            Marker::Path("*.xcodeproj"),
+            Marker::Path("*.xcworkspace"),
+            Marker::Path("Podfile"),
+        ]],
+        excluded: &["Pods/", "DerivedData/", "xcuserdata/"],
+        hints: &[("CocoaPods", Marker::Path("Podfile"))],
+    },
+    Ecosystem {
+        name: "Flutter",
+        category: "Mobile",
+        requires: &[&[Marker::Contains("pubspec.yaml", "flutter")]],
+        excluded: &[
+            ".dart_tool/",
+            ".flutter-plugins",
+            ".flutter-plugins-dependencies",
+            "/ios/Pods/",
+            "/ios/build/",
+            "/android/.gradle/",
+        ],
+        hints: &[],
+    },
+    Ecosystem {
+        name: "React Native",
+        category: "Mobile",
+        requires: &[&[Marker::Contains("package.json", "\"react-native\"")]],
+        excluded: &[
+            "/ios/Pods/",
+            "/ios/build/",
+            "/android/.gradle/",
+            "/android/app/build/",
+        ],
+        hints: &[("Expo", Marker::Contains("package.json", "\"expo\""))],
+    },
+    Ecosystem {
+        name: "Elixir",
+        category: "Backend",
+        requires: &[&[Marker::Path("mix.exs")]],
+        excluded: &["/_build/", "/deps/", ".elixir_ls/", "/cover/"],
+        hints: &[
+            ("Phoenix", Marker::Contains("mix.exs", ":phoenix")),
+            ("Nerves", Marker::Contains("mix.exs", ":nerves")),
+        ],
+    },
+    Ecosystem {
+        name: "Haskell",
+        category: "Toolchain",
+        requires: &[&[
+            Marker::Path("stack.yaml"),
+            Marker::Path("cabal.project"),
+            Marker::Path("*.cabal"),
+        ]],
+        excluded: &[".stack-work/", "dist-newstyle/"],
+        hints: &[
+            ("Stack", Marker::Path("stack.yaml")),
+            ("Cabal", Marker::Path("cabal.project")),
+        ],
+    },
+    Ecosystem {
+        name: "Zig",
+        category: "Toolchain",
+        requires: &[&[Marker::Path("build.zig")]],
+        excluded: &["zig-out/", "zig-cache/", ".zig-cache/"],
+        hints: &[("Zig package manager", Marker::Path("build.zig.zon"))],
+    },
+    Ecosystem {
+        name: "Nim",
+        category: "Toolchain",
+        requires: &[&[
+            Marker::Path("*.nimble"),
+            Marker::Path("nim.cfg"),
+            Marker::Path("config.nims"),
+        ]],
+        excluded: &["nimcache/", "nimbledeps/"],
+        hints: &[("Nimble", Marker::Path("*.nimble"))],
+    },
+    Ecosystem {
+        name: "Kotlin Multiplatform",
+        category: "Mobile",
+        requires: &[&[
+            Marker::Contains("build.gradle.kts", "multiplatform"),
+            Marker::Contains("shared/build.gradle.kts", "multiplatform"),
+            Marker::Contains("composeApp/build.gradle.kts", "multiplatform"),
+        ]],
+        excluded: &[
+            ".gradle/",
+            ".kotlin/",
+            "/shared/build/",
+            "/composeApp/build/",
+        ],
+        hints: &[
+            (
+                "Compose Multiplatform",
+                Marker::Contains("composeApp/build.gradle.kts", "compose"),
+            ),
+            ("Kotlin/Native", Marker::Path("iosApp")),
+        ],
+    },
+    Ecosystem {
+        name: "Deno",
+        category: "Web",
+        requires: &[&[Marker::Path("deno.json"), Marker::Path("deno.jsonc")]],
+        excluded: &[".deno/", "/_fresh/"],
+        hints: &[
+            ("Fresh", Marker::Contains("deno.json", "$fresh")),
+            ("Fresh", Marker::Contains("deno.jsonc", "$fresh")),
+        ],
+    },
+    Ecosystem {
+        name: "Terraform",
+        category: "Infrastructure",
+        requires: &[&[Marker::Path("*.tf")]],
+        excluded: &[".terraform/", "*.tfstate", "*.tfstate.backup"],
+        hints: &[("Terragrunt", Marker::Path("terragrunt.hcl"))],
+    },
+    Ecosystem {
+        name: "Helm",
+        category: "Infrastructure",
+        requires: &[&[Marker::Path("Chart.yaml")]],
+        excluded: &["/charts/*.tgz"],
+        hints: &[],
+    },
+    Ecosystem {
+        name: "dbt",
+        category: "Data",
+        requires: &[&[Marker::Path("dbt_project.yml")]],
+        excluded: &["/dbt_packages/", "/logs/"],
+        hints: &[],
+    },
+];
+
+/// Groups `detections` by their ecosystem's category, keeping the order in
+/// which categories first appear.
+pub fn by_category(detections: &[Detection]) -> Vec<(&'static str, Vec<&Detection>)> {
+    let mut groups: Vec<(&'static str, Vec<&Detection>)> = Vec::new();
+    for detection in detections {
+        let category = detection.ecosystem.category;
+        match groups.iter_mut().find(|(name, _)| *name == category) {
+            Some((_, members)) => members.push(detection),
+            None => groups.push((category, vec![detection])),
+        }
+    }
+    groups
+}
+
+/// The files of a project that is not a directory on disk, such as the
+/// entries of an archive, as far as detection needs them.
+#[derive(Default)]
+pub struct Listing {
+    /// Every file and directory, relative to the project root.
+    pub paths: HashSet<PathBuf>,
+    /// The contents of those of [`marker_files`] that exist.
+    pub contents: HashMap<PathBuf, String>,
+}
+
+/// Returns the ecosystems whose markers are all present in `root`, with
+/// their matching framework hints.
+pub fn detect(root: &Path) -> Vec<Detection> {
+    detect_with(|marker| present(root, marker))
+}
+
+/// Like [`detect`], for a project known by its [`Listing`].
+pub fn detect_listing(listing: &Listing) -> Vec<Detection> {
+    detect_with(|marker| listed(listing, marker))
+}
+
+/// Paths, relative to the project root, of the files whose contents some
+/// marker looks for, to be kept in a [`Listing`].
+pub fn marker_files() -> impl Iterator<Item = &'static str> {
+    ECOSYSTEMS
+        .iter()
+        .flat_map(|ecosystem| {
+            let required = ecosystem.requires.iter().flat_map(|group| group.iter());
+            required.chain(ecosystem.hints.iter().map(|(_, marker)| marker))
+        })
+        .filter_map(|marker| match marker {
+            Marker::Contains(path, _) => Some(*path),
+            Marker::Path(_) => None,
+        })
+}
+
+fn detect_with(present: impl Fn(&Marker) -> bool) -> Vec<Detection> {
+    ECOSYSTEMS
+        .iter()
+        .filter(|ecosystem| {
+            ecosystem
+                .requires
+                .iter()
+                .all(|group| group.iter().any(&present))
+        })
+        .map(|ecosystem| {
+            let mut frameworks: Vec<_> = ecosystem
+                .hints
+                .iter()
+                .filter(|(_, marker)| present(marker))
+                .map(|(framework, _)| *framework)
+                .collect();
+            frameworks.dedup();
+            Detection {
+                ecosystem,
+                frameworks,
+            }
+        })
+        .collect()
+}
+
+/// The detection of the ecosystem called `name` with `frameworks`, as
+/// named in a report. Unknown ecosystems and frameworks are left out.
+pub fn by_name(name: &str, frameworks: &[String]) -> Option<Detection> {
+    let ecosystem = ECOSYSTEMS.iter().find(|ecosystem| ecosystem.name == name)?;
+    Some(Detection {
+        ecosystem,
+        frameworks: frameworks
+            .iter()
+            .filter_map(|framework| {
+                ecosystem
+                    .hints
+                    .iter()
+                    .map(|(hint, _)| *hint)
+                    .find(|hint| hint == framework)
+            })
+            .collect(),
+    })
+}
+
+impl std::fmt::Display for Detection {
+    /// The ecosystem name followed by any frameworks, e.g.
+    /// `Elixir (Phoenix)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.ecosystem.name)?;
+        if !self.frameworks.is_empty() {
+            write!(f, " ({})", self.frameworks.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn present(root: &Path, marker: &Marker) -> bool {
+    match marker {
+        Marker::Path(path) => {
+            let path = Path::new(path);
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let Some(suffix) = name.strip_prefix('*') else {
+                return root.join(path).exists();
+            };
+            let dir = root.join(path.parent().unwrap_or(Path::new("")));
+            fs::read_dir(dir).is_ok_and(|entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.file_name().to_string_lossy().ends_with(suffix))
+            })
+        }
+        Marker::Contains(path, text) => {
+            fs::read_to_string(root.join(path)).is_ok_and(|content| content.contains(text))
+        }
+    }
+}
+
+fn listed(listing: &Listing, marker: &Marker) -> bool {
+    match marker {
+        Marker::Path(path) => {
+            let path = Path::new(path);
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let Some(suffix) = name.strip_prefix('*') else {
+                return listing.paths.contains(path);
+            };
+            let dir = path.parent().unwrap_or(Path::new(""));
+            listing.paths.iter().any(|listed| {
+                listed.parent() == Some(dir)
+                    && listed
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().ends_with(suffix))
+            })
+        }
+        Marker::Contains(path, text) => listing
+            .contents
+            .get(Path::new(path))
+            .is_some_and(|content| content.contains(text)),
+    }
+}
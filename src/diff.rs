@@ -0,0 +1,497 @@
+use crate::language::language;
+use crate::output::json::{Snapshot, SnapshotFile};
+use crate::output::{csv_field, format_size};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+    /// GitHub-flavored Markdown table
+    Markdown,
+    /// Comma-separated values with raw numbers
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    /// `-`, `+` and `~` lines followed by the totals
+    Text,
+    /// Markdown lists and tables, for pull requests and wikis
+    Markdown,
+    /// A JSON document for further processing
+    Json,
+}
+
+/// Files, lines and bytes of a set of files.
+#[derive(Clone, Copy, Default, Serialize)]
+struct Counts {
+    files: u64,
+    lines: u64,
+    bytes: u64,
+}
+
+impl Counts {
+    fn add(&mut self, file: &SnapshotFile) {
+        self.files += 1;
+        self.lines += file.lines as u64;
+        self.bytes += file.size;
+    }
+}
+
+/// Counts before and after.
+#[derive(Default, Serialize)]
+struct Change {
+    old: Counts,
+    new: Counts,
+}
+
+#[derive(Serialize)]
+struct ChangedFile<'a> {
+    path: &'a Path,
+    old_size: u64,
+    new_size: u64,
+    old_lines: usize,
+    new_lines: usize,
+}
+
+/// Everything that differs between two reports.
+#[derive(Serialize)]
+struct Delta<'a> {
+    removed: Vec<&'a SnapshotFile>,
+    added: Vec<&'a SnapshotFile>,
+    changed: Vec<ChangedFile<'a>>,
+    /// Keyed by lowercase extension, or `(none)`.
+    extensions: BTreeMap<String, Change>,
+    total: Change,
+}
+
+/// Prints the files added, removed or changed between two JSON reports,
+/// with the change in files, lines and size per extension and overall.
+pub fn files(old_path: &Path, new_path: &Path, format: DiffFormat) -> io::Result<()> {
+    let old = Snapshot::load(old_path)?;
+    let new = Snapshot::load(new_path)?;
+    let delta = delta(&old, &new);
+    let output = match format {
+        DiffFormat::Text => render_text(&delta),
+        DiffFormat::Markdown => render_markdown(&delta),
+        DiffFormat::Json => serde_json::to_string_pretty(&delta)? + "\n",
+    };
+    print(&output)
+}
+
+fn delta<'a>(old: &'a Snapshot, new: &'a Snapshot) -> Delta<'a> {
+    let old_files: HashMap<&Path, &SnapshotFile> = old
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file))
+        .collect();
+    let new_files: HashMap<&Path, &SnapshotFile> = new
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file))
+        .collect();
+
+    let mut delta = Delta {
+        removed: Vec::new(),
+        added: Vec::new(),
+        changed: Vec::new(),
+        extensions: BTreeMap::new(),
+        total: Change::default(),
+    };
+    for file in &old.files {
+        if !new_files.contains_key(file.path.as_path()) {
+            delta.removed.push(file);
+        }
+        delta
+            .extensions
+            .entry(extension(&file.path))
+            .or_default()
+            .old
+            .add(file);
+        delta.total.old.add(file);
+    }
+    for file in &new.files {
+        match old_files.get(file.path.as_path()) {
+            None => delta.added.push(file),
+            Some(old) if old.differs_from(file) => delta.changed.push(ChangedFile {
+                path: &file.path,
+                old_size: old.size,
+                new_size: file.size,
+                old_lines: old.lines,
+                new_lines: file.lines,
+            }),
+            Some(_) => {}
+        }
+        delta
+            .extensions
+            .entry(extension(&file.path))
+            .or_default()
+            .new
+            .add(file);
+        delta.total.new.add(file);
+    }
+    delta
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "(none)".to_string())
+}
+
+fn render_text(delta: &Delta) -> String {
+    let mut output = String::new();
+    for file in &delta.removed {
+        let _ = writeln!(output, "- {}", file.path.display());
+    }
+    for file in &delta.added {
+        let _ = writeln!(output, "+ {}", file.path.display());
+    }
+    for file in &delta.changed {
+        let _ = writeln!(
+            output,
+            "~ {} ({} -> {}, {} -> {} lines)",
+            file.path.display(),
+            format_size(file.old_size),
+            format_size(file.new_size),
+            file.old_lines,
+            file.new_lines
+        );
+    }
+
+    let _ = writeln!(output, "\nBy extension:");
+    for (extension, change) in &delta.extensions {
+        let _ = writeln!(output, "  {:<10} {}", extension, counts_change(change));
+    }
+    let _ = writeln!(output, "  {:<10} {}", "total", counts_change(&delta.total));
+    output
+}
+
+fn render_markdown(delta: &Delta) -> String {
+    let mut output = String::new();
+    let mut list = |title: &str, paths: Vec<String>| {
+        if !paths.is_empty() {
+            let _ = writeln!(output, "### {} ({})\n", title, paths.len());
+            for path in paths {
+                let _ = writeln!(output, "- {}", path);
+            }
+            output.push('\n');
+        }
+    };
+    list(
+        "Removed files",
+        delta
+            .removed
+            .iter()
+            .map(|file| format!("`{}`", file.path.display()))
+            .collect(),
+    );
+    list(
+        "Added files",
+        delta
+            .added
+            .iter()
+            .map(|file| format!("`{}`", file.path.display()))
+            .collect(),
+    );
+    list(
+        "Changed files",
+        delta
+            .changed
+            .iter()
+            .map(|file| {
+                format!(
+                    "`{}`: {} -> {}, {} -> {} lines",
+                    file.path.display(),
+                    format_size(file.old_size),
+                    format_size(file.new_size),
+                    file.old_lines,
+                    file.new_lines
+                )
+            })
+            .collect(),
+    );
+
+    let _ = writeln!(output, "### Growth\n");
+    let _ = writeln!(output, "| Extension | Files | Lines | Size |");
+    let _ = writeln!(output, "|---|---:|---:|---:|");
+    let rows = delta
+        .extensions
+        .iter()
+        .map(|(extension, change)| (format!("`{}`", extension), change))
+        .chain([("**Total**".to_string(), &delta.total)]);
+    for (extension, change) in rows {
+        let [files, lines, size] = cells(change);
+        let _ = writeln!(
+            output,
+            "| {} | {} | {} | {} |",
+            extension, files, lines, size
+        );
+    }
+    output
+}
+
+/// `old -> new (change)` for the files, lines and size of `change`.
+fn cells(change: &Change) -> [String; 3] {
+    let cell = |metric: &str, old: u64, new: u64, is_size: bool| {
+        let row = Row {
+            metric: metric.to_string(),
+            values: vec![old, new],
+            is_size,
+        };
+        let (old, new) = if is_size {
+            (format_size(old), format_size(new))
+        } else {
+            (old.to_string(), new.to_string())
+        };
+        format!("{} -> {}, {}", old, new, self::change(&row))
+    };
+    let (old, new) = (change.old, change.new);
+    [
+        cell("Files", old.files, new.files, false),
+        cell("Lines", old.lines, new.lines, false),
+        cell("Size", old.bytes, new.bytes, true),
+    ]
+}
+
+fn counts_change(change: &Change) -> String {
+    let [files, lines, size] = cells(change);
+    format!("files {}  lines {}  size {}", files, lines, size)
+}
+
+/// Prints the headline metrics of several JSON reports side by side, one
+/// column per report in the order given, followed by the change from the
+/// first to the last.
+pub fn table(paths: &[PathBuf], format: TableFormat) -> io::Result<()> {
+    let snapshots = paths
+        .iter()
+        .map(|path| Snapshot::load(path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let headers: Vec<String> = paths
+        .iter()
+        .map(|path| {
+            path.file_stem()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    print(&render_table(&headers, &rows(&snapshots), format))
+}
+
+/// Writes `output` to stdout, failing rather than panicking when it has
+/// been closed, as by `head`.
+fn print(output: &str) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    out.write_all(output.as_bytes())?;
+    out.flush()
+}
+
+/// A metric and its value in each snapshot; sizes are rendered in
+/// human-readable units in Markdown.
+struct Row {
+    metric: String,
+    values: Vec<u64>,
+    is_size: bool,
+}
+
+fn rows(snapshots: &[Snapshot]) -> Vec<Row> {
+    let row = |metric: &str, is_size: bool, value: &dyn Fn(&Snapshot) -> u64| Row {
+        metric: metric.to_string(),
+        values: snapshots.iter().map(value).collect(),
+        is_size,
+    };
+    let mut rows = vec![
+        row("Files", false, &|s| s.statistics.total_files as u64),
+        row("Lines", false, &|s| s.statistics.total_lines as u64),
+        row("Size", true, &|s| s.statistics.total_bytes),
+        row("Tokens", false, &|s| s.statistics.total_tokens as u64),
+        row("Identical copies", true, &|s| {
+            s.statistics.duplicate_wasted_bytes
+        }),
+    ];
+
+    let per_language: Vec<BTreeMap<&str, u64>> = snapshots
+        .iter()
+        .map(|snapshot| {
+            let mut lines = BTreeMap::new();
+            for file in &snapshot.files {
+                if let Some(language) = language(&file.path) {
+                    *lines.entry(language).or_default() += file.lines as u64;
+                }
+            }
+            lines
+        })
+        .collect();
+    let mut languages: Vec<&str> = per_language
+        .iter()
+        .flat_map(|lines| lines.keys().copied())
+        .collect();
+    languages.sort_unstable();
+    languages.dedup();
+    // Largest in the latest snapshot first.
+    let latest = per_language.last();
+    languages.sort_by_key(|language| {
+        std::cmp::Reverse(latest.and_then(|lines| lines.get(language)).copied())
+    });
+    for language in languages {
+        rows.push(Row {
+            metric: format!("{} lines", language),
+            values: per_language
+                .iter()
+                .map(|lines| lines.get(language).copied().unwrap_or(0))
+                .collect(),
+            is_size: false,
+        });
+    }
+    rows
+}
+
+fn render_table(headers: &[String], rows: &[Row], format: TableFormat) -> String {
+    let mut output = String::new();
+    match format {
+        TableFormat::Markdown => {
+            let _ = writeln!(output, "| Metric | {} | Change |", headers.join(" | "));
+            let _ = writeln!(output, "|---|{}---:|", "---:|".repeat(headers.len()));
+            for row in rows {
+                let cells: Vec<String> = row
+                    .values
+                    .iter()
+                    .map(|&value| {
+                        if row.is_size {
+                            format_size(value)
+                        } else {
+                            value.to_string()
+                        }
+                    })
+                    .collect();
+                let _ = writeln!(
+                    output,
+                    "| {} | {} | {} |",
+                    row.metric,
+                    cells.join(" | "),
+                    change(row)
+                );
+            }
+        }
+        TableFormat::Csv => {
+            let headers: Vec<String> = headers.iter().map(|header| csv_field(header)).collect();
+            let _ = writeln!(output, "metric,{}", headers.join(","));
+            for row in rows {
+                let values: Vec<String> = row.values.iter().map(u64::to_string).collect();
+                let _ = writeln!(output, "{},{}", csv_field(&row.metric), values.join(","));
+            }
+        }
+    }
+    output
+}
+
+/// Signed difference between the first and last value, with the relative
+/// change when the first value is non-zero.
+fn change(row: &Row) -> String {
+    let (Some(&first), Some(&last)) = (row.values.first(), row.values.last()) else {
+        return String::new();
+    };
+    let (sign, delta) = if last >= first {
+        ("+", last - first)
+    } else {
+        ("-", first - last)
+    };
+    let delta = if row.is_size {
+        format_size(delta)
+    } else {
+        delta.to_string()
+    };
+    if first == 0 {
+        format!("{}{}", sign, delta)
+    } else {
+        let percent = (last as f64 - first as f64) / first as f64 * 100.0;
+        format!("{}{} ({:+.1}%)", sign, delta, percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::json::SnapshotStatistics;
+
+    fn file(path: &str, size: u64, lines: usize, sha256: Option<&str>) -> SnapshotFile {
+        SnapshotFile {
+            path: PathBuf::from(path),
+            size,
+            lines,
+            sha256: sha256.map(str::to_string),
+        }
+    }
+
+    fn snapshot(files: Vec<SnapshotFile>) -> Snapshot {
+        Snapshot {
+            statistics: SnapshotStatistics {
+                total_files: files.len(),
+                total_lines: files.iter().map(|file| file.lines).sum(),
+                total_bytes: files.iter().map(|file| file.size).sum(),
+                duplicate_wasted_bytes: 0,
+                total_tokens: 0,
+            },
+            files,
+        }
+    }
+
+    fn paths<'a>(files: impl IntoIterator<Item = &'a Path>) -> Vec<&'a str> {
+        files
+            .into_iter()
+            .map(|path| path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn lists_added_removed_and_changed_files() {
+        let old = snapshot(vec![
+            file("kept.rs", 10, 1, Some("a")),
+            file("gone.rs", 10, 1, Some("b")),
+            file("grown.rs", 10, 1, Some("c")),
+        ]);
+        let new = snapshot(vec![
+            file("kept.rs", 10, 1, Some("a")),
+            file("grown.rs", 30, 3, Some("d")),
+            file("new.rs", 5, 1, Some("e")),
+        ]);
+        let delta = delta(&old, &new);
+        assert_eq!(
+            paths(delta.removed.iter().map(|f| f.path.as_path())),
+            ["gone.rs"]
+        );
+        assert_eq!(
+            paths(delta.added.iter().map(|f| f.path.as_path())),
+            ["new.rs"]
+        );
+        assert_eq!(paths(delta.changed.iter().map(|f| f.path)), ["grown.rs"]);
+        assert_eq!(delta.total.old.lines, 3);
+        assert_eq!(delta.total.new.lines, 5);
+        assert_eq!(delta.extensions["rs"].new.bytes, 45);
+    }
+
+    #[test]
+    fn same_length_edits_are_changes() {
+        let old = snapshot(vec![file("main.rs", 11, 1, Some("a"))]);
+        let new = snapshot(vec![file("main.rs", 11, 1, Some("b"))]);
+        assert_eq!(delta(&old, &new).changed.len(), 1);
+    }
+
+    #[test]
+    fn falls_back_to_size_and_lines_without_hashes() {
+        let old = snapshot(vec![file("a.bin", 11, 0, None), file("b.bin", 11, 0, None)]);
+        let new = snapshot(vec![
+            file("a.bin", 11, 0, Some("a")),
+            file("b.bin", 12, 0, None),
+        ]);
+        assert_eq!(
+            paths(delta(&old, &new).changed.iter().map(|f| f.path)),
+            ["b.bin"]
+        );
+    }
+}
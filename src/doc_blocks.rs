@@ -0,0 +1,86 @@
+use crate::language::by_name;
+use crate::sfc::{add_line, Section};
+
+/// Attributes the code embedded in a documentation file to the languages it
+/// declares: fenced blocks with an info string in Markdown and MDX, and
+/// `<script>`, `<style>` and `<code class="language-...">` elements in
+/// HTML. Blocks without a known language are left out.
+///
+/// Returns `None` for other languages. Lines outside the blocks, and the
+/// lines holding fences or tags, stay with the document's own language.
+pub fn sections(language: &str, content: &str) -> Option<Vec<Section>> {
+    match language {
+        "markdown" | "mdx" => Some(markdown(content)),
+        "html" => Some(html(content)),
+        _ => None,
+    }
+}
+
+fn markdown(content: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    // The opening fence and the language of the block it starts.
+    let mut open: Option<(&str, Option<&'static str>)> = None;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        match open {
+            Some((fence, language)) => {
+                let closes = trimmed.starts_with(fence)
+                    && trimmed.trim_end().chars().all(|c| fence.starts_with(c));
+                if closes {
+                    open = None;
+                } else if let Some(language) = language {
+                    add_line(&mut sections, language, line);
+                }
+            }
+            None => {
+                let fence_char = match trimmed.chars().next() {
+                    Some(c @ ('`' | '~')) => c,
+                    _ => continue,
+                };
+                let length = trimmed.chars().take_while(|&c| c == fence_char).count();
+                if length < 3 {
+                    continue;
+                }
+                let info = trimmed[length..].split_whitespace().next().unwrap_or("");
+                open = Some((&trimmed[..length], by_name(info.trim_matches(['{', '}']))));
+            }
+        }
+    }
+    sections
+}
+
+fn html(content: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    // The closing tag and the language of the open element.
+    let mut open: Option<(&str, &'static str)> = None;
+    for line in content.lines() {
+        let lower = line.to_ascii_lowercase();
+        match open {
+            Some((closing, language)) => {
+                if lower.contains(closing) {
+                    open = None;
+                } else {
+                    add_line(&mut sections, language, line);
+                }
+            }
+            None => {
+                open = if lower.contains("<script") {
+                    Some(("</script>", "javascript"))
+                } else if lower.contains("<style") {
+                    Some(("</style>", "css"))
+                } else {
+                    lower
+                        .split_once("class=\"language-")
+                        .and_then(|(_, rest)| rest.split(['"', ' ']).next())
+                        .and_then(by_name)
+                        .map(|language| ("</code>", language))
+                };
+                // Elements opened and closed on the same line hold no full lines.
+                if open.is_some_and(|(closing, _)| lower.contains(closing)) {
+                    open = None;
+                }
+            }
+        }
+    }
+    sections
+}
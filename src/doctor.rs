@@ -0,0 +1,277 @@
+use crate::cache::{self, Cache};
+use crate::config::{self, Config};
+use crate::output::{format_size, OutputFormat};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    hint: Option<&'static str>,
+}
+
+/// Where the report of a scan is to be written, as set by `--output`,
+/// `--output-dir` and `--stdout`.
+pub enum Target {
+    Stdout,
+    File {
+        output: Option<PathBuf>,
+        output_dir: Option<PathBuf>,
+    },
+}
+
+/// Runs environment checks against `dir` and prints a diagnostic for each.
+/// The report is expected in `format`, or else the configured one, at
+/// `target`.
+///
+/// Returns `false` if any check failed outright.
+pub fn run(dir: &Path, format: Option<OutputFormat>, target: Target) -> io::Result<bool> {
+    println!("Checking environment for {}...\n", dir.display());
+
+    let mut checks = vec![check_directory(dir), check_git()];
+    if dir.is_dir() {
+        let (check, config) = check_config(dir);
+        checks.push(check);
+        let format = format.or(config.format).unwrap_or(OutputFormat::Text);
+        checks.push(check_writable(match target {
+            Target::Stdout => None,
+            Target::File { output, output_dir } => {
+                Some(config.report_path(dir, format, output.as_deref(), output_dir.as_deref()))
+            }
+        }));
+        checks.push(check_cache(dir, &config));
+        checks.push(check_symlinks(dir));
+        checks.push(check_long_paths(dir));
+    }
+
+    let mut healthy = true;
+    for check in &checks {
+        let label = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => {
+                healthy = false;
+                "fail"
+            }
+        };
+        println!("[{:<4}] {}: {}", label, check.name, check.detail);
+        if let (Some(hint), false) = (check.hint, matches!(check.status, Status::Ok)) {
+            println!("       hint: {}", hint);
+        }
+    }
+
+    println!(
+        "\n{}",
+        if healthy {
+            "No problems found."
+        } else {
+            "Some checks failed; see the hints above."
+        }
+    );
+    Ok(healthy)
+}
+
+fn check_directory(dir: &Path) -> Check {
+    let (status, detail) = match fs::read_dir(dir) {
+        Ok(_) => (Status::Ok, "readable".to_string()),
+        Err(err) => (Status::Fail, err.to_string()),
+    };
+    Check {
+        name: "project directory",
+        status,
+        detail,
+        hint: Some("pass an existing, readable directory as the first argument"),
+    }
+}
+
+fn check_git() -> Check {
+    let (status, detail) = match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => (
+            Status::Ok,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        Ok(output) => (
+            Status::Warn,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ),
+        Err(err) => (Status::Warn, format!("not found ({})", err)),
+    };
+    Check {
+        name: "git",
+        status,
+        detail,
+        hint: Some("install git and make sure it is on PATH"),
+    }
+}
+
+/// Loads the user's and the project's configuration, returning it for the
+/// later checks, or the defaults if it is invalid.
+fn check_config(dir: &Path) -> (Check, Config) {
+    let files: Vec<_> = config::user_path()
+        .into_iter()
+        .chain([dir.join(config::FILE_NAME)])
+        .filter(|path| path.is_file())
+        .collect();
+    let (status, detail, config) = match Config::load(dir) {
+        Ok(config) if files.is_empty() => (Status::Ok, "none found".to_string(), config),
+        Ok(config) => {
+            let names: Vec<_> = files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            (Status::Ok, format!("{} valid", names.join(", ")), config)
+        }
+        Err(err) => (Status::Fail, err.to_string(), Config::default()),
+    };
+    let check = Check {
+        name: "configuration",
+        status,
+        detail,
+        hint: Some(
+            "fix or remove the setting named above; `codetree init` writes a commented example",
+        ),
+    };
+    (check, config)
+}
+
+/// Writes a file next to where the report goes, or into the closest of its
+/// parent directories that exists, as a scan creates the others. With
+/// `path` as `None`, the report goes to stdout.
+fn check_writable(path: Option<PathBuf>) -> Check {
+    let (status, detail) = match path {
+        None => (Status::Ok, "the report goes to stdout".to_string()),
+        Some(path) if path.is_dir() => (Status::Fail, format!("{} is a directory", path.display())),
+        Some(path) => {
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let existing = parent
+                .ancestors()
+                .find(|dir| dir.is_dir())
+                .unwrap_or(Path::new("."));
+            let probe = existing.join(format!(".codetree-doctor-{}", std::process::id()));
+            match fs::write(&probe, b"") {
+                Ok(()) => {
+                    let _ = fs::remove_file(&probe);
+                    (Status::Ok, format!("{} can be written", path.display()))
+                }
+                Err(err) => (
+                    Status::Fail,
+                    format!("{} cannot be written: {}", path.display(), err),
+                ),
+            }
+        }
+    };
+    Check {
+        name: "output location",
+        status,
+        detail,
+        hint: Some(
+            "check the permissions of the report's directory, or write it elsewhere with \
+             --output, --output-dir or --stdout",
+        ),
+    }
+}
+
+/// Reads the cache left by `--cache`, if any. A cache that cannot be used is
+/// only a warning, as scans measure every file again instead.
+fn check_cache(dir: &Path, config: &Config) -> Check {
+    let path = Cache::path(&cache::dir(dir, config.cache_dir.as_deref()));
+    let (status, detail) = match cache::inspect(&path) {
+        Ok(None) => (Status::Ok, "none yet".to_string()),
+        Ok(Some(summary)) if summary.version == Some(cache::VERSION) => (
+            Status::Ok,
+            format!(
+                "{}, {} files, {}",
+                path.display(),
+                summary.files,
+                format_size(summary.bytes)
+            ),
+        ),
+        Ok(Some(_)) => (
+            Status::Warn,
+            format!(
+                "{} was written by another version of codetree and will be rebuilt",
+                path.display()
+            ),
+        ),
+        Err(err) => (Status::Warn, format!("{}: {}", path.display(), err)),
+    };
+    Check {
+        name: "cache",
+        status,
+        detail,
+        hint: Some("run `codetree cache clear`; the next scan with --cache rebuilds it"),
+    }
+}
+
+fn check_symlinks(dir: &Path) -> Check {
+    let (status, detail) = match with_scratch_dir(dir, |scratch| {
+        let target = scratch.join("target");
+        let link = scratch.join("link");
+        fs::write(&target, b"")?;
+        create_symlink(&target, &link)?;
+        fs::read_link(&link).map(|_| ())
+    }) {
+        Ok(()) => (Status::Ok, "supported".to_string()),
+        Err(err) => (Status::Warn, format!("unsupported ({})", err)),
+    };
+    Check {
+        name: "symlinks",
+        status,
+        detail,
+        hint: Some("on Windows, enable Developer Mode or run with symlink privileges"),
+    }
+}
+
+fn check_long_paths(dir: &Path) -> Check {
+    let (status, detail) = match with_scratch_dir(dir, |scratch| {
+        let mut path = scratch.to_path_buf();
+        while path.as_os_str().len() < 300 {
+            path.push("a".repeat(50));
+        }
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("file.txt"), b"")
+    }) {
+        Ok(()) => (Status::Ok, "paths over 260 characters work".to_string()),
+        Err(err) => (Status::Warn, format!("unsupported ({})", err)),
+    };
+    Check {
+        name: "long paths",
+        status,
+        detail,
+        hint: Some("on Windows, enable LongPathsEnabled in the registry or group policy"),
+    }
+}
+
+/// Runs `f` inside a temporary directory under `dir`, removing it afterwards.
+fn with_scratch_dir<F>(dir: &Path, f: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let scratch = dir.join(format!(".codetree-doctor-{}.d", std::process::id()));
+    fs::create_dir(&scratch)?;
+    let result = f(&scratch);
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
@@ -0,0 +1,245 @@
+use crate::ignores::Rule;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable progress on stdout
+    Text,
+    /// One JSON event per line on stderr
+    Json,
+}
+
+/// Reports progress and warnings either as plain text or as JSON events.
+pub struct Logger {
+    format: Option<LogFormat>,
+    /// Format of progress events, which may differ from `format`.
+    progress: Option<LogFormat>,
+    show_ignores: bool,
+    /// Print text messages on stderr instead of stdout, which then carries
+    /// nothing but the report.
+    stderr: bool,
+}
+
+impl Logger {
+    pub fn new(format: LogFormat) -> Self {
+        Logger {
+            format: Some(format),
+            progress: Some(format),
+            show_ignores: false,
+            stderr: false,
+        }
+    }
+
+    /// A logger that discards everything, for repeated internal runs.
+    pub fn silent() -> Self {
+        Logger {
+            format: None,
+            progress: None,
+            show_ignores: false,
+            stderr: false,
+        }
+    }
+
+    /// Report progress in `format` instead of the format of other messages,
+    /// such as JSON events for an editor while the log stays readable.
+    pub fn progress_format(mut self, format: LogFormat) -> Self {
+        self.progress = Some(format);
+        self
+    }
+
+    /// Also print every skipped path and its rule in text mode.
+    pub fn show_ignores(mut self, enabled: bool) -> Self {
+        self.show_ignores = enabled;
+        self
+    }
+
+    /// Print text messages on stderr, keeping stdout free for the report.
+    pub fn to_stderr(mut self, enabled: bool) -> Self {
+        self.stderr = enabled;
+        self
+    }
+
+    pub fn info(&self, message: &str) {
+        match self.format {
+            Some(LogFormat::Text) => self.print(&format!("{}\n", message)),
+            Some(LogFormat::Json) => self.emit(json!({ "event": "info", "message": message })),
+            None => {}
+        }
+    }
+
+    pub fn warn(&self, message: &str) {
+        match self.format {
+            Some(LogFormat::Text) => eprintln!("Warning: {}", message),
+            Some(LogFormat::Json) => self.emit(json!({ "event": "warning", "message": message })),
+            None => {}
+        }
+    }
+
+    /// Reports that `done` of `total` items of `phase` are finished, the
+    /// last being `path`. The total is unknown while the tree is walked, so
+    /// text progress is only shown once files are read.
+    pub fn progress(
+        &self,
+        phase: &str,
+        done: usize,
+        total: Option<usize>,
+        path: &Path,
+    ) -> io::Result<()> {
+        let percent = total.map(|total| done as f64 / total.max(1) as f64 * 100.0);
+        match (self.progress, percent) {
+            (Some(LogFormat::Text), Some(percent)) => {
+                self.print(&format!("\rProcessing Files: {}% Complete", percent as u32));
+                Ok(())
+            }
+            (Some(LogFormat::Json), _) => {
+                self.emit(json!({
+                    "event": "progress",
+                    "phase": phase,
+                    "done": done,
+                    "total": total,
+                    "percent": percent,
+                    "path": path.display().to_string(),
+                }));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn progress_done(&self) {
+        if self.progress == Some(LogFormat::Text) {
+            self.print("\n");
+        }
+    }
+
+    pub fn skipped(&self, path: &Path, rule: &Rule) {
+        match self.format {
+            Some(LogFormat::Text) if self.show_ignores => {
+                self.print(&format!("Ignored {} ({})\n", path.display(), rule))
+            }
+            Some(LogFormat::Json) => self.emit(json!({
+                "event": match rule {
+                    Rule::Codetree => "file_skipped",
+                    _ => "rule_hit",
+                },
+                "path": path.display().to_string(),
+                "rule": rule.to_string(),
+            })),
+            _ => {}
+        }
+    }
+
+    pub fn decode_error(&self, path: &Path, error: &io::Error) {
+        match self.format {
+            Some(LogFormat::Text) => {
+                self.warn(&format!("could not decode {}: {}", path.display(), error))
+            }
+            Some(LogFormat::Json) => self.emit(json!({
+                "event": "decode_error",
+                "path": path.display().to_string(),
+                "error": error.to_string(),
+            })),
+            None => {}
+        }
+    }
+
+    pub fn timings(&self, phases: &[(&str, Duration)]) {
+        let total: Duration = phases.iter().map(|(_, elapsed)| *elapsed).sum();
+        match self.format {
+            Some(LogFormat::Text) => {
+                self.print("\nTimings:\n");
+                for (phase, elapsed) in phases {
+                    let share = if total.is_zero() {
+                        0.0
+                    } else {
+                        elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+                    };
+                    self.print(&format!(
+                        "  {:<10} {:>10.2} ms  ({:>5.1}%)\n",
+                        phase,
+                        elapsed.as_secs_f64() * 1000.0,
+                        share
+                    ));
+                }
+                self.print(&format!(
+                    "  {:<10} {:>10.2} ms\n",
+                    "total",
+                    total.as_secs_f64() * 1000.0
+                ));
+            }
+            Some(LogFormat::Json) => {
+                let phases: Vec<Value> = phases
+                    .iter()
+                    .map(|(phase, elapsed)| {
+                        json!({ "phase": phase, "ms": elapsed.as_secs_f64() * 1000.0 })
+                    })
+                    .collect();
+                self.emit(json!({
+                    "event": "timings",
+                    "phases": phases,
+                    "total_ms": total.as_secs_f64() * 1000.0,
+                }));
+            }
+            None => {}
+        }
+    }
+
+    pub fn bench(&self, runs: u32, files: usize, bytes: u64, min: Duration, avg: Duration) {
+        let secs = avg.as_secs_f64();
+        let files_per_sec = if secs > 0.0 { files as f64 / secs } else { 0.0 };
+        let mb_per_sec = if secs > 0.0 {
+            bytes as f64 / (1024.0 * 1024.0) / secs
+        } else {
+            0.0
+        };
+        match self.format {
+            Some(LogFormat::Text) => {
+                self.print(&format!(
+                    "Benchmark: {} runs over {} files ({:.2} MB)\n",
+                    runs,
+                    files,
+                    bytes as f64 / (1024.0 * 1024.0)
+                ));
+                self.print(&format!(
+                    "  min:       {:>10.2} ms\n",
+                    min.as_secs_f64() * 1000.0
+                ));
+                self.print(&format!("  avg:       {:>10.2} ms\n", secs * 1000.0));
+                self.print(&format!("  files/sec: {:>10.0}\n", files_per_sec));
+                self.print(&format!("  MB/sec:    {:>10.2}\n", mb_per_sec));
+            }
+            Some(LogFormat::Json) => self.emit(json!({
+                "event": "bench",
+                "runs": runs,
+                "files": files,
+                "bytes": bytes,
+                "min_ms": min.as_secs_f64() * 1000.0,
+                "avg_ms": secs * 1000.0,
+                "files_per_sec": files_per_sec,
+                "mb_per_sec": mb_per_sec,
+            })),
+            None => {}
+        }
+    }
+
+    /// Writes text output to stdout, or to stderr if so configured, at once
+    /// so progress updates show up without a newline.
+    fn print(&self, text: &str) {
+        let _ = if self.stderr {
+            io::stderr().write_all(text.as_bytes())
+        } else {
+            let mut stdout = io::stdout().lock();
+            stdout
+                .write_all(text.as_bytes())
+                .and_then(|_| stdout.flush())
+        };
+    }
+
+    fn emit(&self, event: Value) {
+        eprintln!("{}", event);
+    }
+}
@@ -0,0 +1,662 @@
+use crate::anonymize::report_path;
+use crate::config::SensitivePolicy;
+use crate::detect::Detection;
+use crate::events::Logger;
+use globset::GlobMatcher;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Match;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The rule that caused a path to be left out of the report.
+pub enum Rule<'a> {
+    /// Directory name is in the built-in `EXCLUDED_DIRS` list.
+    BuiltinDir,
+    /// File name is in the built-in `EXCLUDED_FILES` list.
+    BuiltinFile,
+    /// Name is in `exclude-dirs` or `exclude-files` of a configuration file.
+    Config,
+    /// The file is codetree's own output or executable.
+    Codetree,
+    /// A line of a `.gitignore` found during the walk.
+    Gitignore(&'a Glob),
+    /// A line of a file passed with `--ignore-file`.
+    IgnoreFile(&'a Glob),
+    /// A line of the repository's `.git/info/exclude`.
+    GitExclude(&'a Glob),
+    /// A line of the global git excludes file.
+    GlobalGitignore(&'a Glob),
+    /// A build or dependency directory of a detected project ecosystem.
+    Ecosystem(&'static str, &'a Glob),
+    /// A pattern passed with `--exclude`.
+    Exclude(&'a str),
+    /// A file matching none of the patterns passed with `--include`.
+    NotIncluded,
+    /// A path git does not track, with `--git-tracked`.
+    Untracked,
+    /// A directory reached again through a symlink, with
+    /// `--follow-symlinks`.
+    Revisited,
+    /// A dotfile or dot-directory, with `--no-hidden`.
+    Hidden,
+    /// A pattern of [`SENSITIVE_FILES`] or of the sensitive file policy.
+    Sensitive(&'a str),
+}
+
+impl fmt::Display for Rule<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(f, None)
+    }
+}
+
+impl Rule<'_> {
+    /// The rule as written in a report of the project in `root`, naming
+    /// ignore files as [`report_path`] does rather than by absolute path.
+    pub fn in_report(&self, root: &Path) -> String {
+        struct InReport<'r, 'a>(&'r Rule<'a>, &'r Path);
+        impl fmt::Display for InReport<'_, '_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.describe(f, Some(self.1))
+            }
+        }
+        InReport(self, root).to_string()
+    }
+
+    /// Writes the rule, naming ignore files relative to `root` if given.
+    fn describe(&self, f: &mut fmt::Formatter<'_>, root: Option<&Path>) -> fmt::Result {
+        let (source, glob) = match self {
+            Rule::BuiltinDir => return f.write_str("built-in excluded directory list"),
+            Rule::BuiltinFile => return f.write_str("built-in excluded file list"),
+            Rule::Config => return f.write_str("configured exclusion list"),
+            Rule::Codetree => return f.write_str("codetree output or executable"),
+            Rule::Gitignore(glob) => ("gitignore", glob),
+            Rule::IgnoreFile(glob) => ("ignore file", glob),
+            Rule::GitExclude(glob) => ("git info/exclude", glob),
+            Rule::GlobalGitignore(glob) => ("global gitignore", glob),
+            Rule::Ecosystem(name, glob) => {
+                return write!(f, "{} project exclusions: {}", name, glob.original())
+            }
+            Rule::Exclude(pattern) => return write!(f, "--exclude {}", pattern),
+            Rule::NotIncluded => return f.write_str("no --include pattern matched"),
+            Rule::Untracked => return f.write_str("not tracked by git"),
+            Rule::Revisited => return f.write_str("directory already walked through a symlink"),
+            Rule::Hidden => return f.write_str("hidden file or directory"),
+            Rule::Sensitive(pattern) => return write!(f, "sensitive file pattern {}", pattern),
+        };
+        match glob.from() {
+            Some(from) => {
+                let from = match root {
+                    Some(root) => report_path(from, root),
+                    None => from.to_path_buf(),
+                };
+                write!(f, "{} {}: {}", source, from.display(), glob.original())
+            }
+            None => write!(f, "{}: {}", source, glob.original()),
+        }
+    }
+}
+
+/// Gitignore-syntax patterns from user-supplied ignore files, the
+/// `.gitignore` files above the scanned directory up to the root of its
+/// repository, the repository's `.git/info/exclude`, the user's global git
+/// excludes file and detected project ecosystems. The `.gitignore` files of
+/// the scanned directory and below are loaded separately during the walk
+/// with [`Ignores::load_gitignore`].
+pub struct Ignores {
+    /// The scanned directory, as the paths to match start with it.
+    root: PathBuf,
+    ignore_files: Gitignore,
+    /// Whether git's own ignore files are honored at all.
+    respect_gitignore: bool,
+    /// The `.gitignore` files of the directories above `root` within its
+    /// repository, outermost first, each with the path from its directory
+    /// down to `root`.
+    ancestors: Vec<(PathBuf, Gitignore)>,
+    /// `.git/info/exclude`, with the path from the repository root down to
+    /// `root`.
+    git_exclude: (PathBuf, Gitignore),
+    global: Gitignore,
+    ecosystems: Vec<(&'static str, Gitignore)>,
+}
+
+impl Ignores {
+    /// Loads every file in `ignore_files` and the exclusions of `ecosystems`,
+    /// anchoring their patterns at `root`. With `respect_gitignore`, also
+    /// loads the `.gitignore` files above `root` and `.git/info/exclude` of
+    /// the repository holding it, if any, and git's `core.excludesFile` (or
+    /// `~/.config/git/ignore`).
+    pub fn new(
+        root: &Path,
+        ignore_files: &[PathBuf],
+        respect_gitignore: bool,
+        ecosystems: &[Detection],
+        log: &Logger,
+    ) -> io::Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        for file in ignore_files {
+            if !file.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("ignore file {} not found", file.display()),
+                ));
+            }
+            if let Some(err) = builder.add(file) {
+                return Err(io::Error::other(err));
+            }
+        }
+        let ignore_files = builder.build().map_err(io::Error::other)?;
+
+        let mut ancestors = Vec::new();
+        let mut git_exclude = (PathBuf::new(), Gitignore::empty());
+        let mut global = Gitignore::empty();
+        if respect_gitignore {
+            if let Some((repository, below)) = repository(root) {
+                // From the repository root down to the directory above
+                // `root`, whose own `.gitignore` the walk loads.
+                let mut dir = repository.clone();
+                for (i, component) in below.components().enumerate() {
+                    let path = dir.join(".gitignore");
+                    if path.is_file() {
+                        let (gitignore, err) = Gitignore::new(&path);
+                        if let Some(err) = err {
+                            log.warn(&format!("Could not fully read {}: {}", path.display(), err));
+                        }
+                        ancestors.push((below.components().skip(i).collect(), gitignore));
+                    }
+                    dir.push(component);
+                }
+
+                let mut builder = GitignoreBuilder::new(&repository);
+                if let Some(exclude) = git_dir(&repository)
+                    .map(|git_dir| git_dir.join("info").join("exclude"))
+                    .filter(|exclude| exclude.is_file())
+                {
+                    if let Some(err) = builder.add(&exclude) {
+                        log.warn(&format!(
+                            "Could not fully read {}: {}",
+                            exclude.display(),
+                            err
+                        ));
+                    }
+                }
+                git_exclude = (below, builder.build().map_err(io::Error::other)?);
+            }
+
+            let (built, err) = GitignoreBuilder::new(root).build_global();
+            if let Some(err) = err {
+                log.warn(&format!("Could not fully read global gitignore: {}", err));
+            }
+            global = built;
+        }
+
+        let mut detected = Vec::with_capacity(ecosystems.len());
+        for Detection { ecosystem, .. } in ecosystems {
+            let mut builder = GitignoreBuilder::new(root);
+            for pattern in ecosystem.excluded {
+                builder.add_line(None, pattern).map_err(io::Error::other)?;
+            }
+            detected.push((ecosystem.name, builder.build().map_err(io::Error::other)?));
+        }
+
+        Ok(Ignores {
+            root: root.to_path_buf(),
+            ignore_files,
+            respect_gitignore,
+            ancestors,
+            git_exclude,
+            global,
+            ecosystems: detected,
+        })
+    }
+
+    /// Returns the pattern that excludes `path`, if any.
+    ///
+    /// `nested` holds the `.gitignore` matchers from the walk root down to the
+    /// directory containing `path`. As in git, the deepest `.gitignore` wins,
+    /// including those above the walk root, then `--ignore-file` patterns,
+    /// then `.git/info/exclude`, then the global excludes file; a `!`
+    /// negation in a higher-precedence source re-includes the path.
+    /// Exclusions of detected ecosystems apply last.
+    pub fn matched<'a>(
+        &'a self,
+        path: &Path,
+        is_dir: bool,
+        nested: &'a [Gitignore],
+    ) -> Option<Rule<'a>> {
+        for gitignore in nested.iter().rev() {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(glob) => return Some(Rule::Gitignore(glob)),
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+        // Matchers anchored above the walk root take paths relative to their
+        // own directory.
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        for (below, gitignore) in self.ancestors.iter().rev() {
+            match gitignore.matched(below.join(relative), is_dir) {
+                Match::Ignore(glob) => return Some(Rule::Gitignore(glob)),
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+        match self.ignore_files.matched(path, is_dir) {
+            Match::Ignore(glob) => return Some(Rule::IgnoreFile(glob)),
+            Match::Whitelist(_) => return None,
+            Match::None => {}
+        }
+        let (below, git_exclude) = &self.git_exclude;
+        match git_exclude.matched(below.join(relative), is_dir) {
+            Match::Ignore(glob) => return Some(Rule::GitExclude(glob)),
+            Match::Whitelist(_) => return None,
+            Match::None => {}
+        }
+        match self.global.matched(path, is_dir) {
+            Match::Ignore(glob) => return Some(Rule::GlobalGitignore(glob)),
+            Match::Whitelist(_) => return None,
+            Match::None => {}
+        }
+        self.ecosystems
+            .iter()
+            .find_map(|(name, gitignore)| match gitignore.matched(path, is_dir) {
+                Match::Ignore(glob) => Some(Rule::Ecosystem(name, glob)),
+                _ => None,
+            })
+    }
+
+    /// Loads `dir/.gitignore`, if present and git's ignore files are
+    /// honored, with its patterns anchored at `dir`.
+    pub fn load_gitignore(&self, dir: &Path, log: &Logger) -> Option<Gitignore> {
+        let path = dir.join(".gitignore");
+        if !self.respect_gitignore || !path.is_file() {
+            return None;
+        }
+        let (gitignore, err) = Gitignore::new(&path);
+        if let Some(err) = err {
+            log.warn(&format!("Could not fully read {}: {}", path.display(), err));
+        }
+        Some(gitignore)
+    }
+
+    /// Like [`Ignores::load_gitignore`], for a `.gitignore` in `dir` whose
+    /// `content` was read from an archive rather than the file system.
+    pub fn parse_gitignore(&self, dir: &Path, content: &str, log: &Logger) -> Option<Gitignore> {
+        if !self.respect_gitignore {
+            return None;
+        }
+        let path = dir.join(".gitignore");
+        let mut builder = GitignoreBuilder::new(dir);
+        for line in content.lines() {
+            if let Err(err) = builder.add_line(Some(path.clone()), line) {
+                log.warn(&format!("Could not fully read {}: {}", path.display(), err));
+            }
+        }
+        match builder.build() {
+            Ok(gitignore) => Some(gitignore),
+            Err(err) => {
+                log.warn(&format!("Could not read {}: {}", path.display(), err));
+                None
+            }
+        }
+    }
+}
+
+/// The root of the git work tree holding the directory `dir`, with the path
+/// from it down to `dir`, or `None` outside of one.
+fn repository(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    if !dir.is_dir() {
+        return None;
+    }
+    let dir = dir.canonicalize().ok()?;
+    let repository = dir
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())?;
+    let below = dir.strip_prefix(repository).ok()?.to_path_buf();
+    Some((repository.to_path_buf(), below))
+}
+
+/// The git directory of the work tree at `repository`: its `.git`
+/// directory, or the one a `.git` file points to, as in worktrees and
+/// submodules, where `info/exclude` is shared from the common directory.
+fn git_dir(repository: &Path) -> Option<PathBuf> {
+    let dot_git = repository.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = std::fs::read_to_string(&dot_git).ok()?;
+    let git_dir = repository.join(content.trim().strip_prefix("gitdir:")?.trim());
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => Some(git_dir.join(common.trim())),
+        Err(_) => Some(git_dir),
+    }
+}
+
+/// Glob patterns passed with `--exclude` and `--include`, matched against
+/// paths relative to the project root. Patterns without a `/` match names in
+/// any directory.
+pub struct PathGlobs {
+    /// Each pattern as given, its matcher and whether it only applies to
+    /// directories.
+    exclude: Vec<(String, GlobMatcher, bool)>,
+    include: Vec<GlobMatcher>,
+}
+
+impl PathGlobs {
+    pub fn new(exclude: &[String], include: &[String]) -> io::Result<Self> {
+        let mut excluded = Vec::with_capacity(exclude.len());
+        for pattern in exclude {
+            excluded.push((pattern.clone(), matcher(pattern)?, false));
+            // `dir/**` only matches what is inside `dir`; also match the
+            // directory itself so it is not walked at all.
+            if let Some(dir) = pattern.strip_suffix("/**") {
+                excluded.push((pattern.clone(), matcher(dir)?, true));
+            }
+        }
+        Ok(PathGlobs {
+            exclude: excluded,
+            include: include
+                .iter()
+                .map(|pattern| matcher(pattern))
+                .collect::<io::Result<_>>()?,
+        })
+    }
+
+    /// Whether only files matching an `--include` pattern are kept.
+    pub fn has_includes(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// Returns the rule that leaves out `path`, if any. Directories are only
+    /// subject to `--exclude`.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<Rule<'_>> {
+        if let Some((pattern, _, _)) = self
+            .exclude
+            .iter()
+            .find(|(_, glob, dir_only)| (is_dir || !dir_only) && glob.is_match(path))
+        {
+            return Some(Rule::Exclude(pattern));
+        }
+        let included =
+            is_dir || !self.has_includes() || self.include.iter().any(|glob| glob.is_match(path));
+        (!included).then_some(Rule::NotIncluded)
+    }
+}
+
+/// Patterns of files that typically hold secrets, such as private keys and
+/// local environment files. Matching files are always skipped, even with
+/// `--no-default-excludes`.
+pub const SENSITIVE_FILES: [&str; 13] = [
+    ".env",
+    ".env.local",
+    ".env.*.local",
+    ".netrc",
+    ".pgpass",
+    "*.key",
+    "*.p12",
+    "*.pem",
+    "*.pfx",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "id_rsa",
+];
+
+/// [`SENSITIVE_FILES`] and the patterns of a [`SensitivePolicy`], with its
+/// exceptions.
+pub struct SensitiveFiles {
+    /// Globs, matched like `--exclude` patterns, each as given.
+    patterns: Vec<(String, GlobMatcher)>,
+    regexes: Vec<Regex>,
+    allow: Vec<GlobMatcher>,
+}
+
+impl SensitiveFiles {
+    /// The built-in patterns, followed by those of `policy`.
+    pub fn new(policy: &SensitivePolicy) -> io::Result<Self> {
+        let patterns = SENSITIVE_FILES
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(policy.patterns.iter().cloned())
+            .map(|pattern| matcher(&pattern).map(|glob| (pattern, glob)))
+            .collect::<io::Result<_>>()?;
+        let regexes = policy
+            .regex
+            .iter()
+            .map(|regex| {
+                Regex::new(regex).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid sensitive regex {}: {}", regex, err),
+                    )
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(SensitiveFiles {
+            patterns,
+            regexes,
+            allow: policy
+                .allow
+                .iter()
+                .map(|pattern| matcher(pattern))
+                .collect::<io::Result<_>>()?,
+        })
+    }
+
+    /// Returns the rule that leaves out the file at `path`, relative to the
+    /// project root, if any.
+    pub fn matched(&self, path: &Path) -> Option<Rule<'_>> {
+        if self.allow.iter().any(|glob| glob.is_match(path)) {
+            return None;
+        }
+        self.pattern(path)
+    }
+
+    /// Whether the file at `path` matches a sensitive pattern, even one
+    /// read anyway for being allowed.
+    pub fn is_sensitive(&self, path: &Path) -> bool {
+        self.pattern(path).is_some()
+    }
+
+    fn pattern(&self, path: &Path) -> Option<Rule<'_>> {
+        if let Some((pattern, _)) = self.patterns.iter().find(|(_, glob)| glob.is_match(path)) {
+            return Some(Rule::Sensitive(pattern));
+        }
+        let slashed = path
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        self.regexes
+            .iter()
+            .find(|regex| regex.is_match(&slashed))
+            .map(|regex| Rule::Sensitive(regex.as_str()))
+    }
+}
+
+fn matcher(pattern: &str) -> io::Result<GlobMatcher> {
+    let normalized = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    globset::Glob::new(&normalized)
+        .map(|glob| glob.compile_matcher())
+        .map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid pattern {}: {}", pattern, err),
+            )
+        })
+}
+
+/// The files git tracks below a directory, as listed by `git ls-files`.
+pub struct TrackedFiles {
+    /// Paths relative to the directory.
+    files: HashSet<PathBuf>,
+    /// Every directory holding a tracked file, at any depth.
+    dirs: HashSet<PathBuf>,
+}
+
+impl TrackedFiles {
+    /// Asks git for the files tracked below `root`, which must be inside a
+    /// git work tree.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let output = Command::new("git")
+            .args(["ls-files", "-z"])
+            .current_dir(root)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git ls-files failed in {}: {}",
+                root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let files: HashSet<_> = output
+            .stdout
+            .split(|&byte| byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| PathBuf::from(String::from_utf8_lossy(path).as_ref()))
+            .collect();
+        let dirs = files
+            .iter()
+            .flat_map(|file| file.ancestors().skip(1))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(TrackedFiles { files, dirs })
+    }
+
+    /// Whether `path`, relative to the root, is a tracked file or a
+    /// directory holding one.
+    pub fn contains(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir {
+            self.dirs.contains(path)
+        } else {
+            self.files.contains(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A git repository holding `files`, given as path and content pairs.
+    fn repository(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git").join("info")).unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    /// Whether `path`, relative to `root`, is excluded when walking `root`:
+    /// either it or a directory on the way down to it is matched, with the
+    /// `.gitignore` files the walk would load until then.
+    fn excluded(root: &Path, path: &str) -> bool {
+        let log = Logger::silent();
+        let ignores = Ignores::new(root, &[], true, &[], &log).unwrap();
+        let mut nested: Vec<_> = ignores.load_gitignore(root, &log).into_iter().collect();
+        let mut current = root.to_path_buf();
+        let mut components = Path::new(path).components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component);
+            let is_dir = components.peek().is_some();
+            if ignores.matched(&current, is_dir, &nested).is_some() {
+                return true;
+            }
+            if is_dir {
+                nested.extend(ignores.load_gitignore(&current, &log));
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn deeper_gitignore_negates_a_parent_rule() {
+        let dir = repository(&[
+            (".gitignore", "*.log\n"),
+            ("src/.gitignore", "!keep.log\n"),
+            ("src/keep.log", ""),
+            ("src/drop.log", ""),
+        ]);
+        assert!(!excluded(dir.path(), "src/keep.log"));
+        assert!(excluded(dir.path(), "src/drop.log"));
+    }
+
+    #[test]
+    fn nested_gitignore_is_anchored_at_its_directory() {
+        let dir = repository(&[
+            ("src/.gitignore", "/gen\n"),
+            ("src/gen/a.rs", ""),
+            ("gen/a.rs", ""),
+        ]);
+        assert!(excluded(dir.path(), "src/gen/a.rs"));
+        assert!(!excluded(dir.path(), "gen/a.rs"));
+    }
+
+    #[test]
+    fn gitignore_above_the_scanned_directory_applies() {
+        let dir = repository(&[
+            (".gitignore", "gen/\n/sub/out.txt\n"),
+            ("sub/gen/g.js", ""),
+            ("sub/out.txt", ""),
+            ("sub/main.js", ""),
+        ]);
+        let sub = dir.path().join("sub");
+        assert!(excluded(&sub, "gen/g.js"));
+        assert!(excluded(&sub, "out.txt"));
+        assert!(!excluded(&sub, "main.js"));
+    }
+
+    #[test]
+    fn scanned_gitignore_negates_an_ancestor_rule() {
+        let dir = repository(&[
+            (".gitignore", "*.log\n"),
+            ("sub/.gitignore", "!keep.log\n"),
+            ("sub/keep.log", ""),
+        ]);
+        assert!(!excluded(&dir.path().join("sub"), "keep.log"));
+    }
+
+    #[test]
+    fn info_exclude_is_read_from_the_repository_root() {
+        let dir = repository(&[
+            (".git/info/exclude", "secret.txt\n/sub/local.txt\n"),
+            ("sub/secret.txt", ""),
+            ("sub/local.txt", ""),
+            ("sub/.gitignore", "!local.txt\n"),
+        ]);
+        let sub = dir.path().join("sub");
+        assert!(excluded(&sub, "secret.txt"));
+        assert!(!excluded(&sub, "local.txt"));
+    }
+
+    #[test]
+    fn ignore_file_overrides_info_exclude() {
+        let dir = repository(&[
+            (".git/info/exclude", "*.txt\n"),
+            ("ignore", "!notes.txt\n"),
+            ("notes.txt", ""),
+            ("todo.txt", ""),
+        ]);
+        let log = Logger::silent();
+        let ignores =
+            Ignores::new(dir.path(), &[dir.path().join("ignore")], true, &[], &log).unwrap();
+        assert!(ignores
+            .matched(&dir.path().join("notes.txt"), false, &[])
+            .is_none());
+        assert!(ignores
+            .matched(&dir.path().join("todo.txt"), false, &[])
+            .is_some());
+    }
+}
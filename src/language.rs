@@ -0,0 +1,362 @@
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How a language writes comments: prefixes running to the end of the
+/// line, and delimiters of blocks that may span lines.
+#[derive(Clone, Copy, Debug)]
+pub struct CommentSyntax {
+    pub line: &'static [&'static str],
+    pub block: &'static [(&'static str, &'static str)],
+}
+
+const C_STYLE: CommentSyntax = comments(&["//"], &[("/*", "*/")]);
+const HASH: CommentSyntax = comments(&["#"], &[]);
+const MARKUP: CommentSyntax = comments(&[], &[("<!--", "-->")]);
+const NO_COMMENTS: CommentSyntax = comments(&[], &[]);
+
+const fn comments(
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+) -> CommentSyntax {
+    CommentSyntax { line, block }
+}
+
+/// A language codetree recognizes, and how to recognize it.
+#[derive(Debug)]
+pub struct Language {
+    /// Identifier used throughout reports, which is also the name common
+    /// Markdown code-fence highlighters know the language by.
+    pub id: &'static str,
+    /// Name shown to people, spelled as cloc spells it.
+    pub name: &'static str,
+    /// Lowercase file extensions, without the dot.
+    pub extensions: &'static [&'static str],
+    /// Exact names of files in the language that have no telling
+    /// extension, such as `Makefile`.
+    pub filenames: &'static [&'static str],
+    /// Interpreters that run scripts in the language from a `#!` line.
+    pub interpreters: &'static [&'static str],
+    pub comments: CommentSyntax,
+}
+
+const fn lang(
+    id: &'static str,
+    name: &'static str,
+    extensions: &'static [&'static str],
+    comments: CommentSyntax,
+) -> Language {
+    Language {
+        id,
+        name,
+        extensions,
+        filenames: &[],
+        interpreters: &[],
+        comments,
+    }
+}
+
+impl Language {
+    const fn filenames(mut self, filenames: &'static [&'static str]) -> Self {
+        self.filenames = filenames;
+        self
+    }
+
+    const fn interpreters(mut self, interpreters: &'static [&'static str]) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+}
+
+/// Every language codetree recognizes, in alphabetical order of identifier.
+pub const LANGUAGES: &[Language] = &[
+    lang(
+        "assembly",
+        "Assembly",
+        &["asm", "s"],
+        comments(&[";", "#"], &[]),
+    ),
+    lang("awk", "awk", &["awk"], HASH).interpreters(&["awk", "gawk", "mawk"]),
+    lang("bash", "Bourne Shell", &["sh", "bash", "zsh", "ksh"], HASH)
+        .filenames(&[
+            ".bashrc",
+            ".bash_profile",
+            ".profile",
+            ".zshrc",
+            ".zprofile",
+        ])
+        .interpreters(&["sh", "bash", "zsh", "ksh", "dash"]),
+    lang(
+        "batch",
+        "DOS Batch",
+        &["bat", "cmd"],
+        comments(&["::", "REM ", "rem "], &[]),
+    ),
+    lang("c", "C", &["c", "h"], C_STYLE),
+    lang(
+        "clojure",
+        "Clojure",
+        &["clj", "cljs", "cljc", "edn"],
+        comments(&[";"], &[]),
+    ),
+    lang("cmake", "CMake", &["cmake"], HASH).filenames(&["CMakeLists.txt"]),
+    lang(
+        "cpp",
+        "C++",
+        &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        C_STYLE,
+    ),
+    lang("crystal", "Crystal", &["cr"], HASH).interpreters(&["crystal"]),
+    lang("csharp", "C#", &["cs"], C_STYLE),
+    lang("css", "CSS", &["css"], comments(&[], &[("/*", "*/")])),
+    lang("dart", "Dart", &["dart"], C_STYLE),
+    lang("dockerfile", "Dockerfile", &["dockerfile"], HASH)
+        .filenames(&["Dockerfile", "Containerfile"]),
+    lang("elixir", "Elixir", &["ex", "exs"], HASH).interpreters(&["elixir"]),
+    lang("elm", "Elm", &["elm"], comments(&["--"], &[("{-", "-}")])),
+    lang("erlang", "Erlang", &["erl", "hrl"], comments(&["%"], &[])).interpreters(&["escript"]),
+    lang("fish", "fish", &["fish"], HASH).interpreters(&["fish"]),
+    lang(
+        "fsharp",
+        "F#",
+        &["fs", "fsi", "fsx"],
+        comments(&["//"], &[("(*", "*)")]),
+    ),
+    lang("go", "Go", &["go"], C_STYLE),
+    lang("graphql", "GraphQL", &["graphql", "gql"], HASH),
+    lang("groovy", "Groovy", &["groovy", "gradle"], C_STYLE)
+        .filenames(&["Jenkinsfile"])
+        .interpreters(&["groovy"]),
+    lang(
+        "haskell",
+        "Haskell",
+        &["hs"],
+        comments(&["--"], &[("{-", "-}")]),
+    )
+    .interpreters(&["runhaskell"]),
+    lang(
+        "hcl",
+        "HCL",
+        &["hcl", "tf", "tfvars"],
+        comments(&["#", "//"], &[("/*", "*/")]),
+    ),
+    lang("html", "HTML", &["html", "htm"], MARKUP),
+    lang("ini", "INI", &["ini"], comments(&[";", "#"], &[])),
+    lang("java", "Java", &["java"], C_STYLE),
+    lang("javascript", "JavaScript", &["js", "mjs", "cjs"], C_STYLE).interpreters(&["node"]),
+    lang("json", "JSON", &["json"], NO_COMMENTS),
+    lang("jsx", "JSX", &["jsx"], C_STYLE),
+    lang("julia", "Julia", &["jl"], comments(&["#"], &[("#=", "=#")])).interpreters(&["julia"]),
+    lang("kotlin", "Kotlin", &["kt", "kts"], C_STYLE),
+    lang("less", "LESS", &["less"], C_STYLE),
+    lang(
+        "lisp",
+        "Lisp",
+        &["lisp", "lsp", "el"],
+        comments(&[";"], &[]),
+    ),
+    lang("lua", "Lua", &["lua"], comments(&["--"], &[("--[[", "]]")])).interpreters(&["lua"]),
+    lang("makefile", "make", &["mk", "mak"], HASH)
+        .filenames(&["Makefile", "makefile", "GNUmakefile"])
+        .interpreters(&["make"]),
+    lang("markdown", "Markdown", &["md", "markdown"], MARKUP),
+    lang("mdx", "MDX", &["mdx"], MARKUP),
+    lang("nim", "Nim", &["nim"], comments(&["#"], &[("#[", "]#")])),
+    lang("nix", "Nix", &["nix"], comments(&["#"], &[("/*", "*/")])),
+    lang("objectivec", "Objective-C", &["m", "mm"], C_STYLE),
+    lang(
+        "ocaml",
+        "OCaml",
+        &["ml", "mli"],
+        comments(&[], &[("(*", "*)")]),
+    ),
+    lang("perl", "Perl", &["pl", "pm"], HASH).interpreters(&["perl"]),
+    lang(
+        "php",
+        "PHP",
+        &["php"],
+        comments(&["//", "#"], &[("/*", "*/")]),
+    )
+    .interpreters(&["php"]),
+    lang(
+        "powershell",
+        "PowerShell",
+        &["ps1", "psm1", "psd1"],
+        comments(&["#"], &[("<#", "#>")]),
+    )
+    .interpreters(&["pwsh", "powershell"]),
+    lang("protobuf", "Protocol Buffers", &["proto"], C_STYLE),
+    lang("python", "Python", &["py", "pyi", "pyw"], HASH)
+        .filenames(&["SConstruct", "SConscript"])
+        .interpreters(&["python", "python2", "python3"]),
+    lang("r", "R", &["r"], HASH).interpreters(&["Rscript"]),
+    lang("ruby", "Ruby", &["rb", "rake", "gemspec"], HASH)
+        .filenames(&["Gemfile", "Rakefile", "Vagrantfile", "Podfile", "Brewfile"])
+        .interpreters(&["ruby"]),
+    lang("rust", "Rust", &["rs"], C_STYLE),
+    lang("sass", "Sass", &["sass"], C_STYLE),
+    lang("scala", "Scala", &["scala", "sc"], C_STYLE).interpreters(&["scala"]),
+    lang("scss", "SCSS", &["scss"], C_STYLE),
+    lang("solidity", "Solidity", &["sol"], C_STYLE),
+    lang("sql", "SQL", &["sql"], comments(&["--"], &[("/*", "*/")])),
+    lang("svelte", "Svelte", &["svelte"], MARKUP),
+    lang("swift", "Swift", &["swift"], C_STYLE),
+    lang("tcl", "Tcl/Tk", &["tcl"], HASH).interpreters(&["tclsh", "wish"]),
+    lang("toml", "TOML", &["toml"], HASH).filenames(&["Pipfile"]),
+    lang("tsx", "TypeScript", &["tsx"], C_STYLE),
+    lang("typescript", "TypeScript", &["ts", "mts", "cts"], C_STYLE)
+        .interpreters(&["deno", "ts-node", "tsx", "bun"]),
+    lang("vim", "Vim Script", &["vim"], comments(&["\""], &[])).filenames(&[".vimrc"]),
+    lang("vue", "Vue", &["vue"], MARKUP),
+    lang("xml", "XML", &["xml", "xsd", "xsl", "xslt"], MARKUP),
+    lang("yaml", "YAML", &["yaml", "yml"], HASH),
+    lang("zig", "Zig", &["zig"], comments(&["//"], &[])),
+];
+
+/// Overrides installed with [`set_overrides`], checked in order before the
+/// extension table.
+static OVERRIDES: OnceLock<Vec<(GlobMatcher, &'static str)>> = OnceLock::new();
+
+/// A `PATTERN=LANGUAGE` pair from the command line or the configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct LanguageOverride {
+    pub pattern: String,
+    pub language: String,
+}
+
+impl FromStr for LanguageOverride {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((pattern, language)) if !pattern.is_empty() && !language.is_empty() => {
+                Ok(LanguageOverride {
+                    pattern: pattern.to_string(),
+                    language: language.to_string(),
+                })
+            }
+            _ => Err("expected PATTERN=LANGUAGE, e.g. *.tpl=gotemplate".to_string()),
+        }
+    }
+}
+
+impl TryFrom<String> for LanguageOverride {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Makes every later [`language`] lookup check `overrides` first, so stats,
+/// highlighting and colors agree. Patterns containing a `/` match the path
+/// relative to the project root; others match the file name in any
+/// directory. Only the first call has an effect.
+pub fn set_overrides(overrides: &[LanguageOverride]) -> io::Result<()> {
+    let mut matchers = Vec::with_capacity(overrides.len());
+    for o in overrides {
+        let pattern = if o.pattern.contains('/') {
+            o.pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", o.pattern)
+        };
+        let glob = Glob::new(&pattern).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid language override {}: {}", o.pattern, err),
+            )
+        })?;
+        // Leaked once per process so lookups can keep returning `&'static str`.
+        let language: &'static str = Box::leak(o.language.to_ascii_lowercase().into_boxed_str());
+        matchers.push((glob.compile_matcher(), language));
+    }
+    let _ = OVERRIDES.set(matchers);
+    Ok(())
+}
+
+/// Returns the language identifier for `path`, from the overrides if one
+/// matches and otherwise based on its extension or, for files such as
+/// `Makefile`, its name.
+pub fn language(path: &Path) -> Option<&'static str> {
+    if let Some((_, language)) = OVERRIDES
+        .get()
+        .and_then(|overrides| overrides.iter().find(|(glob, _)| glob.is_match(path)))
+    {
+        return Some(language);
+    }
+    path.extension()
+        .and_then(|extension| by_extension(extension.to_str()?))
+        .or_else(|| by_filename(path.file_name()?.to_str()?))
+}
+
+/// The registry entry of the language identified by `id`, or `None` for
+/// languages only known from overrides.
+pub fn get(id: &str) -> Option<&'static Language> {
+    LANGUAGES.iter().find(|language| language.id == id)
+}
+
+/// Name of the language identified by `id` for people to read, or the
+/// identifier itself for languages added through overrides.
+pub fn display_name(id: &'static str) -> &'static str {
+    get(id).map_or(id, |language| language.name)
+}
+
+/// How the language identified by `id` writes comments. Languages without
+/// comments, or only known from overrides, have no markers.
+pub fn comment_syntax(id: &str) -> CommentSyntax {
+    get(id).map_or(NO_COMMENTS, |language| language.comments)
+}
+
+/// Returns the language identifier for a name written by hand, such as a
+/// Markdown fence info string: either an identifier itself (`rust`) or an
+/// extension (`rs`).
+pub fn by_name(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    get(&name)
+        .map(|language| language.id)
+        .or_else(|| by_extension(&name))
+}
+
+/// Returns the language identifier for a file extension, ignoring case.
+pub fn by_extension(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+        .map(|language| language.id)
+}
+
+/// Returns the language identifier for a well-known file name, matched
+/// exactly.
+pub fn by_filename(name: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|language| language.filenames.contains(&name))
+        .map(|language| language.id)
+}
+
+/// Returns the language identifier for a script's first line, if it is a
+/// `#!` line naming a known interpreter, directly (`#!/bin/bash`) or through
+/// `env` (`#!/usr/bin/env python3`).
+pub fn by_shebang(first_line: &str) -> Option<&'static str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    // Versioned interpreters such as `python3.12` or `perl5`.
+    let interpreter = |name: &str| {
+        program == name
+            || program
+                .strip_prefix(name)
+                .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+    };
+    LANGUAGES
+        .iter()
+        .find(|language| language.interpreters.iter().any(|name| interpreter(name)))
+        .map(|language| language.id)
+}
@@ -0,0 +1,52 @@
+//! Scan a project into a [`report::Report`]: its file tree, the contents of
+//! every included file and the statistics derived from them, ready to be
+//! rendered by one of the formats in [`output`].
+//!
+//! The `codetree` binary is a thin command-line wrapper around this crate.
+//! To embed the analysis in other tooling:
+//!
+//! ```no_run
+//! use codetree::events::{LogFormat, Logger};
+//! use codetree::scan::{scan_project, ScanOptions};
+//! use std::path::Path;
+//!
+//! let root = Path::new("path/to/project");
+//! let log = Logger::new(LogFormat::Text);
+//! let report = scan_project(root, &ScanOptions::new(root, &log)?, &log)?;
+//! println!("{} files, {} lines", report.files.len(), report.total_lines());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [`scan::ScanOptions`] holds the same settings as the command-line flags,
+//! such as exclusions, limits and timeouts. Use [`events::Logger::silent`]
+//! to scan without printing anything.
+
+pub mod anonymize;
+pub mod authors;
+pub mod bench;
+pub mod cache;
+pub mod category;
+pub mod churn;
+pub mod comments;
+pub mod config;
+pub mod detect;
+pub mod diff;
+pub mod doc_blocks;
+pub mod doctor;
+pub mod events;
+pub mod ignores;
+pub mod language;
+pub mod mcp;
+pub mod merge;
+pub mod output;
+pub mod remote;
+pub mod report;
+pub mod scan;
+pub mod secrets;
+pub mod sfc;
+pub mod spool;
+pub mod timings;
+pub mod tokens;
+pub mod tui;
+pub mod watch;
+pub mod workspace;
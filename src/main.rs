@@ -1,212 +1,1387 @@
+use clap::{Args, Parser, Subcommand};
+use codetree::cache::{self, Cache};
+use codetree::churn::Churn;
+use codetree::config::{self, Config, SensitivePolicy};
+use codetree::detect::Detection;
+use codetree::diff::{self, DiffFormat, TableFormat};
+use codetree::events::{LogFormat, Logger};
+use codetree::ignores::{Ignores, PathGlobs, SensitiveFiles, TrackedFiles};
+use codetree::language::{self, LanguageOverride};
+use codetree::output::json::{JsonLayout, Snapshot};
+use codetree::output::text::{TreeGlyphs, TreeStyle};
+use codetree::output::{self, OutputFormat};
+use codetree::remote::{self, Checkout};
+use codetree::report::Report;
+use codetree::scan::{self, archive, scan_project, Hidden, ScanOptions, Verdict};
+use codetree::timings::TimingLayer;
+use codetree::watch::Watcher;
+use codetree::{anonymize, bench, doctor, mcp, merge, tui};
 use std::env;
-use std::ffi::OsStr;
-use std::fs;
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
-use walkdir::{DirEntry, WalkDir};
-
-const EXCLUDED_DIRS: [&str; 22] = [
-    ".idea",
-    ".git",
-    ".github",
-    ".gitlab",
-    ".next",
-    ".vscode",
-    ".venv",
-    ".target",
-    ".zig-cache",
-    "node_modules",
-    "assets",
-    "asset",
-    "public",
-    "bin",
-    "build",
-    "cache",
-    "dist",
-    "fonts",
-    "obj",
-    "out",
-    "target",
-    "vendor",
-];
-
-const EXCLUDED_FILES: [&str; 25] = [
-    ".DS_Store",
-    ".env",
-    ".eslintrc.json",
-    ".gitignore",
-    ".npmignore",
-    "Cargo.lock",
-    "eslint.config.js",
-    "favicon.ico",
-    "globals.css",
-    "next.config.mjs",
-    "next-env.d.ts",
-    "postcss.config.js",
-    "postcss.config.mjs",
-    "README.md",
-    "package-lock.json",
-    "pnpm-lock.yaml",
-    "tailwind.config.js",
-    "tailwind.config.ts",
-    "tsconfig.app.json",
-    "tsconfig.node.json",
-    "tsconfig.json",
-    "thumbs.db",
-    "tsconfig.json",
-    "vite.config.ts",
-    "yarn.lock",
-];
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+use tracing::info_span;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Generate a file tree and the contents of a project as a single text file",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+/// Options of a scan, taken by `codetree scan` and by `codetree` without a
+/// subcommand alike.
+#[derive(Args)]
+struct ScanArgs {
+    /// Directories to analyze (defaults to the current directory), or URLs
+    /// of git repositories to clone and analyze. Several are combined into
+    /// one report, each under its own name
+    #[arg(value_name = "PATH", env = "CODETREE_PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Branch or tag to check out when a PATH is a repository URL
+    #[arg(long = "ref", value_name = "REF", env = "CODETREE_REF")]
+    git_ref: Option<String>,
+
+    /// Format of the generated report [default: text]
+    #[arg(short, long, value_enum, env = "CODETREE_FORMAT")]
+    format: Option<OutputFormat>,
+
+    /// Write the report to PATH instead of the format's default name in the
+    /// analyzed directory
+    #[arg(long, value_name = "PATH", env = "CODETREE_OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// Write the report under its default name in DIR, created if needed,
+    /// instead of in the analyzed directory
+    #[arg(
+        long,
+        value_name = "DIR",
+        env = "CODETREE_OUTPUT_DIR",
+        conflicts_with = "output"
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Write the report to stdout instead of a file, with messages on stderr
+    #[arg(
+        long,
+        env = "CODETREE_STDOUT",
+        conflicts_with_all = ["output", "output_dir"]
+    )]
+    stdout: bool,
+
+    /// Print the statistics and detected projects to stdout instead of
+    /// writing a report, with messages on stderr
+    #[arg(
+        long,
+        env = "CODETREE_STATS_ONLY",
+        conflicts_with_all = ["output", "output_dir", "stdout"]
+    )]
+    stats_only: bool,
+
+    /// Print the files that would be analyzed, one relative path per line,
+    /// without reading them or writing a report
+    #[arg(
+        long,
+        env = "CODETREE_LIST",
+        conflicts_with_all = ["output", "output_dir", "stdout", "stats_only"]
+    )]
+    list: bool,
+
+    /// Only descend N directory levels below the analyzed directory
+    #[arg(long, value_name = "N", env = "CODETREE_MAX_DEPTH")]
+    max_depth: Option<usize>,
+
+    /// Shape of the JSON report: one nested document, or a flat array of
+    /// self-contained per-file records
+    #[arg(
+        long,
+        value_enum,
+        env = "CODETREE_JSON_LAYOUT",
+        default_value_t = JsonLayout::Nested
+    )]
+    json_layout: JsonLayout,
+
+    /// Characters used to draw the file tree [default: unicode]
+    #[arg(long, value_enum, env = "CODETREE_TREE_STYLE")]
+    tree_style: Option<TreeStyle>,
+
+    /// Custom tree glyphs as BRANCH,LAST,VERTICAL (e.g. "+-- ,+-- ,|   "),
+    /// overriding --tree-style
+    #[arg(long, value_name = "GLYPHS", env = "CODETREE_TREE_GLYPHS")]
+    tree_glyphs: Option<TreeGlyphs>,
+
+    /// Treat files matching PATTERN as LANGUAGE, e.g. "*.tpl=gotemplate" or
+    /// "Jenkinsfile=groovy" (repeatable)
+    #[arg(
+        long,
+        value_name = "PATTERN=LANGUAGE",
+        env = "CODETREE_LANGUAGE_OVERRIDE",
+        value_delimiter = ','
+    )]
+    language_override: Vec<LanguageOverride>,
+
+    /// Previous JSON report to compare against in the pr-comment format
+    #[arg(long, value_name = "FILE", env = "CODETREE_BASELINE")]
+    baseline: Option<PathBuf>,
+
+    /// Count fenced code blocks in Markdown and code in HTML documents
+    /// towards their declared languages in language statistics
+    #[arg(long, env = "CODETREE_COUNT_DOC_BLOCKS")]
+    count_doc_blocks: bool,
+
+    /// Replace directory and file names with pseudonyms (keeping
+    /// extensions) and withhold file contents, for a report that is safe to
+    /// share
+    #[arg(long, env = "CODETREE_ANONYMIZE")]
+    anonymize: bool,
+
+    /// Derive --anonymize pseudonyms from KEY, so they stay the same from
+    /// run to run, instead of from a random key drawn for each run
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "CODETREE_ANONYMIZE_KEY",
+        hide_env_values = true
+    )]
+    anonymize_key: Option<String>,
+
+    /// Also write per-file metrics (size, lines, language, hash) as CSV to
+    /// FILE, whatever the report format
+    #[arg(long, value_name = "FILE", env = "CODETREE_METRICS_CSV")]
+    metrics_csv: Option<PathBuf>,
+
+    /// Also write per-extension totals of files, lines (code, comments,
+    /// blanks) and size as CSV to FILE, whatever the report format
+    #[arg(long, value_name = "FILE", env = "CODETREE_EXTENSIONS_CSV")]
+    extensions_csv: Option<PathBuf>,
+
+    /// Number of entries in ranked sections, such as hotspots, authors and
+    /// extensions (defaults depend on the format)
+    #[arg(long, value_name = "N", env = "CODETREE_TOP")]
+    top: Option<usize>,
+
+    /// Split the llm format into numbered chunks of at most N estimated
+    /// tokens each
+    #[arg(long, value_name = "N", env = "CODETREE_MAX_TOKENS")]
+    max_tokens: Option<usize>,
+
+    /// Format of progress and warning messages
+    #[arg(long, value_enum, env = "CODETREE_LOG_FORMAT", default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Format of progress updates (defaults to --log-format); `json` streams
+    /// phase, files done and total, percent and current path on stderr
+    #[arg(long, value_enum, env = "CODETREE_PROGRESS")]
+    progress: Option<LogFormat>,
+
+    /// Print how long each phase of the run took
+    #[arg(long, env = "CODETREE_TIMINGS")]
+    timings: bool,
+
+    /// Stop scanning after SECONDS and write a report, marked as partial,
+    /// of what was collected so far
+    #[arg(long, value_name = "SECONDS", env = "CODETREE_TIMEOUT")]
+    timeout: Option<u64>,
+
+    /// Read the contents of at most N files; the tree still lists every
+    /// file and the report is marked as truncated
+    #[arg(long, value_name = "N", env = "CODETREE_MAX_FILES")]
+    max_files: Option<usize>,
+
+    /// Leave the content of files larger than SIZE (e.g. 500KB or 2MB) out
+    /// of the report; they still count towards the statistics
+    #[arg(long, value_name = "SIZE", env = "CODETREE_MAX_FILE_SIZE", value_parser = output::parse_size)]
+    max_file_size: Option<u64>,
+
+    /// Walk into symlinked directories and read symlinked files, skipping
+    /// directories already walked; by default symlinks are listed in the
+    /// tree as `name -> target`
+    #[arg(long, env = "CODETREE_FOLLOW_SYMLINKS")]
+    follow_symlinks: bool,
+
+    /// Remember what was measured in .codetree/cache.json, so later runs skip
+    /// re-reading files whose size and modification time are unchanged
+    #[arg(long, env = "CODETREE_CACHE")]
+    cache: bool,
+
+    /// Keep the --cache file in DIR instead of .codetree in the analyzed
+    /// directory
+    #[arg(long, value_name = "DIR", env = "CODETREE_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Keep the --cache file under SIZE (such as 50MB) by leaving out the
+    /// most recently modified files
+    #[arg(long, value_name = "SIZE", env = "CODETREE_CACHE_MAX_SIZE", value_parser = output::parse_size)]
+    cache_max_size: Option<u64>,
+
+    /// Scan the project N times without writing a report and print throughput
+    #[arg(long, value_name = "N", env = "CODETREE_BENCH")]
+    bench: Option<u32>,
+
+    /// Skip paths matching the gitignore-style patterns in FILE (repeatable)
+    #[arg(
+        long,
+        value_name = "FILE",
+        env = "CODETREE_IGNORE_FILE",
+        value_delimiter = ','
+    )]
+    ignore_file: Vec<PathBuf>,
+
+    /// Do not skip the built-in lists of directories (such as assets, public
+    /// and build) and files (such as README.md and lock files); .git and
+    /// sensitive files are still skipped
+    #[arg(long, env = "CODETREE_NO_DEFAULT_EXCLUDES")]
+    no_default_excludes: bool,
+
+    /// Skip files named NAME, in addition to the built-in list (repeatable)
+    #[arg(
+        long,
+        value_name = "NAME",
+        env = "CODETREE_EXTRA_EXCLUDE_FILE",
+        value_delimiter = ','
+    )]
+    extra_exclude_file: Vec<String>,
+
+    /// Never read files matching GLOB, such as "*.secret", in addition to
+    /// the built-in list of keys and .env files (repeatable)
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "CODETREE_SENSITIVE_PATTERN",
+        value_delimiter = ','
+    )]
+    sensitive_pattern: Vec<String>,
+
+    /// Never read files whose path, relative to the analyzed directory,
+    /// matches REGEX (repeatable)
+    #[arg(long, value_name = "REGEX", env = "CODETREE_SENSITIVE_REGEX")]
+    sensitive_regex: Vec<String>,
+
+    /// Read files matching GLOB even though they match a sensitive file
+    /// pattern (repeatable)
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "CODETREE_ALLOW_SENSITIVE",
+        value_delimiter = ','
+    )]
+    allow_sensitive: Vec<String>,
+
+    /// Keep secrets found in file contents (keys, tokens, high-entropy
+    /// strings) instead of replacing them with [REDACTED:kind] markers
+    #[arg(long, env = "CODETREE_NO_REDACT")]
+    no_redact: bool,
+
+    /// Skip paths matching GLOB, relative to the analyzed directory
+    /// (repeatable)
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "CODETREE_EXCLUDE",
+        value_delimiter = ','
+    )]
+    exclude: Vec<String>,
+
+    /// Only include files matching GLOB, relative to the analyzed directory
+    /// (repeatable)
+    #[arg(
+        long,
+        value_name = "GLOB",
+        env = "CODETREE_INCLUDE",
+        value_delimiter = ','
+    )]
+    include: Vec<String>,
+
+    /// Only include files tracked by git, as listed by `git ls-files`
+    #[arg(long, env = "CODETREE_GIT_TRACKED")]
+    git_tracked: bool,
+
+    /// Rank files by how often git history shows them changing, weighted
+    /// by their size, to find the hotspots of the project
+    #[arg(long, env = "CODETREE_HOTSPOTS")]
+    hotspots: bool,
+
+    /// Attribute lines to their authors with `git blame`, and estimate the
+    /// bus factor of each top-level directory
+    #[arg(long, env = "CODETREE_AUTHORS")]
+    authors: bool,
+
+    /// Analyze dotfiles and dot-directories like any other file (the
+    /// default)
+    #[arg(long, overrides_with_all = ["no_hidden", "hidden_tree_only"])]
+    hidden: bool,
+
+    /// Skip dotfiles and dot-directories
+    #[arg(
+        long,
+        env = "CODETREE_NO_HIDDEN",
+        overrides_with_all = ["hidden", "hidden_tree_only"]
+    )]
+    no_hidden: bool,
+
+    /// List dotfiles and dot-directories in the tree without reading their
+    /// contents
+    #[arg(
+        long,
+        env = "CODETREE_HIDDEN_TREE_ONLY",
+        overrides_with_all = ["hidden", "no_hidden"]
+    )]
+    hidden_tree_only: bool,
+
+    /// Do not honor .gitignore files, .git/info/exclude or the global git
+    /// excludes file
+    #[arg(long, env = "CODETREE_NO_GITIGNORE")]
+    no_gitignore: bool,
+
+    /// Print every skipped path together with the rule that excluded it
+    #[arg(long, env = "CODETREE_SHOW_IGNORES")]
+    show_ignores: bool,
+
+    /// Prepend FILE to the text report, filling in {{project}}, {{stats}},
+    /// {{tree}} and {{instructions}}
+    #[arg(long, value_name = "FILE", env = "CODETREE_PROMPT_TEMPLATE")]
+    prompt_template: Option<PathBuf>,
+
+    /// Text substituted for {{instructions}} in the prompt template
+    #[arg(
+        long,
+        value_name = "TEXT",
+        env = "CODETREE_INSTRUCTIONS",
+        default_value = "",
+        requires = "prompt_template"
+    )]
+    instructions: String,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Scan a directory and write a report (what `codetree [PATH]` does)
+    Scan(Box<ScanArgs>),
+    /// Scan a directory, then regenerate the report whenever files change
+    Watch(Box<ScanArgs>),
+    /// Scan a directory and browse it in the terminal, then write the
+    /// report for the files selected there
+    Tui(Box<ScanArgs>),
+    /// Scan a directory and answer questions about it as a Model Context
+    /// Protocol server on stdin and stdout
+    Mcp(Box<ScanArgs>),
+    /// Scan a directory and serve the report over HTTP, regenerating it
+    /// whenever files change
+    Serve {
+        /// Address to listen on; 0.0.0.0 makes the report reachable from
+        /// other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        #[command(flatten)]
+        scan: Box<ScanArgs>,
+    },
+    /// Check git, filesystem capabilities and permissions for a directory
+    Doctor {
+        /// Directory to check (defaults to the current directory)
+        path: Option<PathBuf>,
+
+        /// Format the report will be written in, to check its file can be
+        /// (defaults to the configured format, or text)
+        #[arg(long, value_enum, env = "CODETREE_FORMAT")]
+        format: Option<OutputFormat>,
+
+        /// Check that the report can be written to PATH, as with scan
+        #[arg(long, value_name = "PATH", env = "CODETREE_OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Check that the report can be written into DIR, as with scan
+        #[arg(
+            long,
+            value_name = "DIR",
+            env = "CODETREE_OUTPUT_DIR",
+            conflicts_with = "output"
+        )]
+        output_dir: Option<PathBuf>,
+
+        /// The report goes to stdout, so no file needs to be written
+        #[arg(
+            long,
+            env = "CODETREE_STDOUT",
+            conflicts_with_all = ["output", "output_dir"]
+        )]
+        stdout: bool,
+    },
+    /// Tell whether a file or directory would be part of a scan, and which
+    /// rule excludes it if not
+    Explain {
+        /// File or directory to explain
+        #[arg(value_name = "TARGET")]
+        target: PathBuf,
+
+        #[command(flatten)]
+        scan: Box<ScanArgs>,
+    },
+    /// Show, locate or remove the cache kept by --cache
+    Cache {
+        #[arg(value_enum)]
+        action: cache::Action,
+
+        /// Directory whose cache to manage (defaults to the current
+        /// directory)
+        path: Option<PathBuf>,
+
+        /// Directory the cache is kept in, as given to scans
+        #[arg(long, value_name = "DIR", env = "CODETREE_CACHE_DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    /// Write a commented .codetree.toml to start from
+    Init {
+        /// Directory to write it to (defaults to the current directory)
+        path: Option<PathBuf>,
+    },
+    /// Combine JSON reports of several projects into one, with each
+    /// project's tree under a directory named after it
+    Merge {
+        /// Reports to combine, written with `--format json`
+        #[arg(required = true, num_args = 2.., value_name = "REPORT")]
+        reports: Vec<PathBuf>,
+
+        /// Where to write the combined JSON report
+        #[arg(short, long, value_name = "PATH", default_value = "codetree.json")]
+        output: PathBuf,
+
+        /// Project name of the combined report
+        #[arg(long, default_value = "combined")]
+        name: String,
+    },
+    /// Print the JSON Schema of reports written with `--format json`
+    Schema,
+    /// Render a report written with `--format json` in another format,
+    /// without scanning the project again
+    Convert {
+        /// JSON report to convert
+        #[arg(value_name = "REPORT")]
+        report: PathBuf,
+
+        #[command(flatten)]
+        scan: Box<ScanArgs>,
+    },
+    /// Compare JSON reports written with `--format json`
+    Diff {
+        /// Print a side-by-side table of key metrics across all the reports
+        /// instead of the files changed between two of them
+        #[arg(long)]
+        table: bool,
+
+        /// Format of the comparison table
+        #[arg(long, value_enum, default_value_t = TableFormat::Markdown, requires = "table")]
+        table_format: TableFormat,
+
+        /// Format of the changes between two reports
+        #[arg(long, value_enum, default_value_t = DiffFormat::Text, conflicts_with = "table")]
+        format: DiffFormat,
+
+        /// Reports to compare, oldest first
+        #[arg(required = true, num_args = 2.., value_name = "REPORT")]
+        reports: Vec<PathBuf>,
+    },
+}
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let start_dir = if args.len() > 1 {
-        PathBuf::from(&args[1])
+    let cli = Cli::parse();
+
+    let args = match cli.command {
+        Some(Commands::Scan(args)) => *args,
+        Some(Commands::Watch(args)) => return scan(*args, true),
+        Some(Commands::Tui(args)) => return tui(*args),
+        Some(Commands::Serve { host, port, scan }) => return serve(*scan, &host, port),
+        Some(Commands::Mcp(args)) => return mcp(*args),
+        Some(Commands::Doctor {
+            path,
+            format,
+            output,
+            output_dir,
+            stdout,
+        }) => {
+            let dir = match path {
+                Some(path) => path,
+                None => env::current_dir()?,
+            };
+            let target = if stdout {
+                doctor::Target::Stdout
+            } else {
+                doctor::Target::File { output, output_dir }
+            };
+            if !doctor::run(&dir, format, target)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Explain { target, scan }) => return explain(&target, *scan),
+        Some(Commands::Cache {
+            action,
+            path,
+            cache_dir,
+        }) => {
+            let root = match path {
+                Some(path) => path,
+                None => env::current_dir()?,
+            };
+            let dir = match cache_dir {
+                Some(dir) => dir,
+                None => cache::dir(&root, Config::load(&root)?.cache_dir.as_deref()),
+            };
+            return cache::run(action, &dir);
+        }
+        Some(Commands::Init { path }) => {
+            let dir = match path {
+                Some(path) => path,
+                None => env::current_dir()?,
+            };
+            let path = config::init(&dir)?;
+            println!("Wrote {}", path.display());
+            return Ok(());
+        }
+        Some(Commands::Merge {
+            reports,
+            output: path,
+            name,
+        }) => {
+            let reports = reports
+                .iter()
+                .map(|path| output::json::load(path))
+                .collect::<io::Result<_>>()?;
+            let report = merge::merge(reports, &name)?;
+            let mut out = BufWriter::new(File::create(&path)?);
+            output::json::write(&report, &TreeStyle::Unicode.glyphs(), &mut out)?;
+            out.flush()?;
+            println!("Wrote {}", path.display());
+            return Ok(());
+        }
+        Some(Commands::Schema) => return output::json::write_schema(&mut io::stdout().lock()),
+        Some(Commands::Convert { report, scan }) => return convert(&report, *scan),
+        Some(Commands::Diff {
+            table,
+            table_format,
+            format,
+            reports,
+        }) => {
+            if table {
+                return diff::table(&reports, table_format);
+            }
+            let [old, new] = reports.as_slice() else {
+                eprintln!("Error: diff compares exactly two reports; use --table for more");
+                std::process::exit(2);
+            };
+            return diff::files(old, new, format);
+        }
+        None => cli.scan,
+    };
+    scan(args, false)
+}
+
+/// Scans the directory given in `cli` and writes the report. With `watch`,
+/// keeps the report up to date until interrupted. Several directories are
+/// scanned one by one into a combined report, configured and written as a
+/// scan of the current directory would be.
+fn scan(mut cli: ScanArgs, watch: bool) -> io::Result<()> {
+    let several = cli.paths.len() > 1;
+    if several && (watch || cli.bench.is_some()) {
+        eprintln!("Error: watch and --bench take a single directory");
+        std::process::exit(2);
+    }
+    let remote = cli.paths.iter().any(|path| remote::is_url(path));
+    if remote && watch {
+        eprintln!("Error: watch takes a local directory, not a repository URL");
+        std::process::exit(2);
+    }
+    if !remote && cli.git_ref.is_some() {
+        eprintln!("Error: --ref only applies to repository URLs");
+        std::process::exit(2);
+    }
+    let archive = cli.paths.iter().any(|path| archive::is_archive(path));
+    if archive && watch {
+        eprintln!("Error: watch takes a directory, not an archive");
+        std::process::exit(2);
+    }
+    if archive && (cli.git_tracked || cli.hotspots || cli.authors || cli.cache) {
+        eprintln!("Error: --git-tracked, --hotspots, --authors and --cache need a directory, not an archive");
+        std::process::exit(2);
+    }
+
+    let mut log = Logger::new(cli.log_format)
+        .show_ignores(cli.show_ignores)
+        .to_stderr(cli.stdout || cli.stats_only || cli.list);
+    if let Some(format) = cli.progress {
+        log = log.progress_format(format);
+    }
+
+    // Clones are deleted when they go out of scope at the end of the scan.
+    let mut checkouts = Vec::new();
+    for path in cli.paths.iter_mut().filter(|path| remote::is_url(path)) {
+        log.info(&format!("Cloning {}...", path.display()));
+        let checkout = Checkout::clone(
+            &path.to_string_lossy(),
+            cli.git_ref.as_deref(),
+            !(cli.hotspots || cli.authors),
+        )?;
+        *path = checkout.path();
+        checkouts.push(checkout);
+    }
+
+    let start_dir = match cli.paths.as_slice() {
+        [path] => path.clone(),
+        _ => env::current_dir()?,
+    };
+    let config = Config::load(&start_dir)?;
+    let format = cli.format.or(config.format).unwrap_or(OutputFormat::Text);
+    let output_path = if remote || archive {
+        // A clone's own settings cannot send the report into the clone,
+        // which is deleted, nor can anything be written into an archive; it
+        // goes to the current directory instead.
+        output_path(&cli, Path::new(""), &Config::default(), format)?
+    } else {
+        output_path(&cli, &start_dir, &config, format)?
+    };
+    apply_config(&mut cli, &config)?;
+    let sensitive = sensitive_files(&cli, &config)?;
+
+    let phase_timings = if cli.timings {
+        let (layer, timings) = TimingLayer::new();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::set_global_default(subscriber).map_err(io::Error::other)?;
+        Some(timings)
     } else {
-        env::current_dir()?
+        None
     };
 
-    let script_name = env::args().next().unwrap();
-    let output_file_name = "codetree.txt";
-    let output_file_path = start_dir.join(output_file_name);
+    let roots = if several {
+        let mut roots = Vec::new();
+        for root in &cli.paths {
+            let config = Config::load(root)?;
+            let options = scan_options(&cli, root, config, format, &output_path, &log)?;
+            roots.push((root.clone(), options));
+        }
+        roots
+    } else {
+        let options = scan_options(&cli, &start_dir, config, format, &output_path, &log)?;
+        vec![(start_dir.clone(), options)]
+    };
+
+    if let Some(runs) = cli.bench {
+        let (root, options) = &roots[0];
+        return bench::run(root, runs, options, &log);
+    }
+    if cli.list {
+        let mut out = BufWriter::new(io::stdout().lock());
+        for (root, options) in &roots {
+            let name = project_name(root);
+            for path in scan::list_files(root, options, &log)? {
+                let path = if several {
+                    Path::new(&name).join(path)
+                } else {
+                    path
+                };
+                writeln!(out, "{}", path.display())?;
+            }
+        }
+        log.progress_done();
+        return out.flush();
+    }
+
+    let destination = Destination::new(&cli, format, output_path, sensitive, &log)?;
 
-    if output_file_path.exists() {
-        fs::remove_file(&output_file_path)?;
+    let mut reports = Vec::new();
+    for (root, options) in &roots {
+        log.info(&format!("Generating file tree for {}...", root.display()));
+        reports.push(scan_project(root, options, &log)?);
+        log.progress_done();
     }
+    let mut report = if several {
+        merge::merge(reports, &project_name(&start_dir))?
+    } else {
+        reports.remove(0)
+    };
+    if cli.anonymize {
+        anonymize(&mut report, &cli)?;
+    }
+    if let Some(truncation) = report.truncated {
+        log.warn(&truncation.to_string());
+    }
+    destination.write(&cli, &report, &log)?;
 
-    let mut file_paths = Vec::new();
-    let mut output = String::new();
+    if let Some(timings) = phase_timings {
+        if let Ok(timings) = timings.lock() {
+            log.timings(&timings);
+        }
+    }
+    if !watch {
+        return Ok(());
+    }
 
-    println!("Generating file tree for {}...", start_dir.display());
-    output.push_str("Project File Tree:\n\n");
-    get_file_tree_and_contents(
+    let (_, options) = &roots[0];
+    let mut watcher = Watcher::new(&start_dir)?;
+    if !destination.stdout && !destination.stats_only {
+        watcher.ignore(&destination.path)?;
+    }
+    for path in [&cli.metrics_csv, &cli.extensions_csv]
+        .into_iter()
+        .flatten()
+    {
+        watcher.ignore(path)?;
+    }
+    log.info(&format!(
+        "Watching {} for changes (press Ctrl+C to stop)...",
+        start_dir.display()
+    ));
+    let report = Mutex::new(report);
+    follow_changes(
+        &report,
         &start_dir,
-        0,
-        &mut file_paths,
-        &mut output,
-        &script_name,
-        output_file_name,
-    )?;
+        options,
+        &cli,
+        watcher,
+        &log,
+        |report| destination.write(&cli, report, &log),
+    )
+}
 
-    output.push_str("\nProject Codes:\n\n");
+/// Keeps `report`, a scan of `start_dir` with `options`, up to date with the
+/// changes `watcher` sees until interrupted, passing it to `update` after
+/// every change that shows in it. The report is locked while it changes.
+fn follow_changes(
+    report: &Mutex<Report>,
+    start_dir: &Path,
+    options: &ScanOptions,
+    cli: &ScanArgs,
+    watcher: Watcher,
+    log: &Logger,
+    mut update: impl FnMut(&Report) -> io::Result<()>,
+) -> io::Result<()> {
+    loop {
+        let changes = watcher.changes()?;
+        let mut report = report.lock().unwrap();
+        // Anonymized reports no longer carry the paths to patch.
+        let refreshed = if cli.anonymize {
+            None
+        } else {
+            scan::refresh(&mut report, start_dir, &changes, options, log)?
+        };
+        if let Some(files) = refreshed {
+            // Nothing that shows in the report changed.
+            if files == 0 {
+                continue;
+            }
+            log.info(&format!("{} changed files; report updated", files));
+        } else {
+            log.info(&format!("{} changed paths; rescanning...", changes.len()));
+            *report = scan_project(start_dir, options, log)?;
+            if cli.anonymize {
+                anonymize(&mut report, cli)?;
+            }
+            log.progress_done();
+        }
+        update(&report)?;
+    }
+}
 
-    for (i, file) in file_paths.iter().enumerate() {
-        let progress = (i + 1) as f32 / file_paths.len() as f32 * 100.0;
-        print!("\rProcessing Files: {}% Complete", progress as u32);
-        io::stdout().flush()?;
+/// Scans the directory given in `cli` and serves the report over HTTP at
+/// `host` and `port`, keeping it up to date as files change, until
+/// interrupted. Without a format, the HTML summary is served.
+fn serve(mut cli: ScanArgs, host: &str, port: u16) -> io::Result<()> {
+    let start_dir = match cli.paths.as_slice() {
+        [] => env::current_dir()?,
+        [path] if !remote::is_url(path) && !archive::is_archive(path) => path.clone(),
+        _ => {
+            eprintln!("Error: serve takes a single local directory");
+            std::process::exit(2);
+        }
+    };
+    let config = Config::load(&start_dir)?;
+    let format = cli.format.or(config.format).unwrap_or(OutputFormat::Email);
+    if matches!(format, OutputFormat::Sqlite | OutputFormat::Wiki) {
+        eprintln!("Error: serve takes a format rendered as one document, not a database or pages");
+        std::process::exit(2);
+    }
+    let output_path = output_path(&cli, &start_dir, &config, format)?;
+    // The report is only rendered in memory, so no earlier report on disk
+    // is touched.
+    cli.stdout = true;
+    let mut log = Logger::new(cli.log_format).show_ignores(cli.show_ignores);
+    if let Some(format) = cli.progress {
+        log = log.progress_format(format);
+    }
+    apply_config(&mut cli, &config)?;
+    let sensitive = sensitive_files(&cli, &config)?;
+    let options = scan_options(&cli, &start_dir, config, format, &output_path, &log)?;
+    let destination = Destination::new(&cli, format, output_path, sensitive, &log)?;
+
+    log.info(&format!(
+        "Generating file tree for {}...",
+        start_dir.display()
+    ));
+    let mut report = scan_project(&start_dir, &options, &log)?;
+    if cli.anonymize {
+        anonymize(&mut report, &cli)?;
+    }
+    log.progress_done();
+    let render = |report: &Report| {
+        let mut page = Vec::new();
+        destination.render(&cli, report, &mut page)?;
+        Ok::<_, io::Error>(page)
+    };
+    let page = Mutex::new(render(&report)?);
+    let report = Mutex::new(report);
 
-        if file.file_name().unwrap_or_default().to_str() == Some(&script_name)
-            || file.file_name().unwrap_or_default() == OsStr::new(output_file_name)
-            || is_excluded_file(file)
-        {
-            continue;
+    let server = Server::http((host, port)).map_err(io::Error::other)?;
+    let watcher = Watcher::new(&start_dir)?;
+    log.info(&format!(
+        "Serving the report at http://{}:{}/ and updating it as {} changes (press Ctrl+C to stop)...",
+        host,
+        port,
+        start_dir.display()
+    ));
+    thread::scope(|scope| {
+        scope.spawn(|| respond(&server, &page, format.content_type()));
+        let result = follow_changes(
+            &report,
+            &start_dir,
+            &options,
+            &cli,
+            watcher,
+            &log,
+            |report| {
+                *page.lock().unwrap() = render(report)?;
+                Ok(())
+            },
+        );
+        server.unblock();
+        result
+    })
+}
+
+/// Scans the directory given in `cli` and answers Model Context Protocol
+/// requests about it on stdin until the client closes it, keeping the
+/// report up to date as files change.
+fn mcp(mut cli: ScanArgs) -> io::Result<()> {
+    let start_dir = match cli.paths.as_slice() {
+        [] => env::current_dir()?,
+        [path] if !remote::is_url(path) && !archive::is_archive(path) => path.clone(),
+        _ => {
+            eprintln!("Error: mcp takes a single local directory");
+            std::process::exit(2);
         }
+    };
+    let config = Config::load(&start_dir)?;
+    let format = cli.format.or(config.format).unwrap_or(OutputFormat::Text);
+    let output_path = output_path(&cli, &start_dir, &config, format)?;
+    // Stdout carries the protocol, and nothing else.
+    let log = Logger::new(cli.log_format)
+        .show_ignores(cli.show_ignores)
+        .to_stderr(true);
+    apply_config(&mut cli, &config)?;
+    let mut options = scan_options(&cli, &start_dir, config, format, &output_path, &log)?;
+    // Tools answer with the contents of files, whatever the format.
+    options.keep_contents = true;
 
-        output.push_str(&format!(
-            "{}. {}\n",
-            i + 1,
-            file.strip_prefix(&start_dir).unwrap_or(file).display()
-        ));
+    log.info(&format!(
+        "Generating file tree for {}...",
+        start_dir.display()
+    ));
+    let mut report = scan_project(&start_dir, &options, &log)?;
+    if cli.anonymize {
+        anonymize(&mut report, &cli)?;
+    }
+    log.progress_done();
+    let report = Arc::new(Mutex::new(report));
+    let watcher = Watcher::new(&start_dir)?;
+    log.info("Answering Model Context Protocol requests on stdin...");
+    // Watching never ends on its own; the thread is left behind once the
+    // client is done.
+    thread::spawn({
+        let report = Arc::clone(&report);
+        move || {
+            let watched =
+                follow_changes(&report, &start_dir, &options, &cli, watcher, &log, |_| {
+                    Ok(())
+                });
+            if let Err(err) = watched {
+                log.warn(&format!("Stopped following changes: {}", err));
+            }
+        }
+    });
+    mcp::serve(&report, io::stdin().lock(), io::stdout().lock())
+}
 
-        if file.exists() {
-            match fs::read_to_string(file) {
-                Ok(content) => {
-                    output.push_str("\n");
-                    output.push_str(&content);
-                    output.push_str("\n");
-                }
-                Err(_) => output.push_str(" (Unable to read file content)\n"),
+/// Answers every request to `server` for `/` with the current `page`, and
+/// any other with 404 Not Found, until the server is unblocked.
+fn respond(server: &Server, page: &Mutex<Vec<u8>>, content_type: &str) {
+    let headers = [
+        Header::from_bytes("Content-Type", content_type).unwrap(),
+        // Reloading the page shows the report as it is now.
+        Header::from_bytes("Cache-Control", "no-cache").unwrap(),
+    ];
+    for request in server.incoming_requests() {
+        let path = request.url().split('?').next().unwrap_or_default();
+        let response = if path == "/" {
+            let mut response = Response::from_data(page.lock().unwrap().clone());
+            for header in &headers {
+                response.add_header(header.clone());
             }
+            response
         } else {
-            output.push_str(" (File not found)\n");
-        }
-        output.push('\n');
+            Response::from_string("Not Found").with_status_code(404)
+        };
+        // A client that went away before the answer is no concern of ours.
+        let _ = request.respond(response);
     }
+}
 
-    println!("\nWriting to file...");
-    fs::write(&output_file_path, output)?;
+/// Name of the directory at `dir`, as the project name of its report.
+fn project_name(dir: &Path) -> String {
+    fs::canonicalize(dir)
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| dir.display().to_string())
+}
 
-    println!(
-        "File tree and contents have been written to {}",
-        output_file_path.display()
-    );
-    Ok(())
+/// Renders the JSON report at `report` in the format given in `cli`,
+/// written where a scan of the current directory would write it, or
+/// anonymized or to stdout as requested.
+fn convert(report: &Path, cli: ScanArgs) -> io::Result<()> {
+    let format = cli.format.unwrap_or(OutputFormat::Text);
+    let output_path = output_path(&cli, Path::new(""), &Config::default(), format)?;
+    let log = Logger::new(cli.log_format).to_stderr(cli.stdout || cli.stats_only);
+    language::set_overrides(&cli.language_override)?;
+    let destination = Destination::new(
+        &cli,
+        format,
+        output_path,
+        sensitive_files(&cli, &Config::default())?,
+        &log,
+    )?;
+
+    let mut report = output::json::load(report)?;
+    if cli.anonymize {
+        anonymize(&mut report, &cli)?;
+    }
+    destination.write(&cli, &report, &log)
 }
 
-fn get_file_tree_and_contents(
-    dir: &Path,
-    depth: usize,
-    file_paths: &mut Vec<PathBuf>,
-    output: &mut String,
-    script_name: &str,
-    output_file_name: &str,
-) -> io::Result<()> {
-    let indent = "│   ".repeat(depth);
-    let last_indent = if depth > 0 {
-        format!("{}└── ", "│   ".repeat(depth - 1))
+/// Scans the directory or archive given in `cli`, lets the user browse it
+/// and choose files, and writes the report for those files only.
+fn tui(mut cli: ScanArgs) -> io::Result<()> {
+    let start_dir = match cli.paths.as_slice() {
+        [] => env::current_dir()?,
+        [path] if !remote::is_url(path) => path.clone(),
+        _ => {
+            eprintln!("Error: tui takes a single local directory or archive");
+            std::process::exit(2);
+        }
+    };
+    let config = Config::load(&start_dir)?;
+    let format = cli.format.or(config.format).unwrap_or(OutputFormat::Text);
+    let output_path = if archive::is_archive(&start_dir) {
+        output_path(&cli, Path::new(""), &Config::default(), format)?
     } else {
-        String::new()
+        output_path(&cli, &start_dir, &config, format)?
     };
+    let log = Logger::new(cli.log_format)
+        .show_ignores(cli.show_ignores)
+        .to_stderr(true);
+    apply_config(&mut cli, &config)?;
+    let sensitive = sensitive_files(&cli, &config)?;
+    let mut options = scan_options(&cli, &start_dir, config, format, &output_path, &log)?;
+    // The preview shows the contents of every file, whatever the format.
+    options.keep_contents = true;
 
-    let mut entries: Vec<_> = WalkDir::new(dir)
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_entry(|e| !is_excluded(e))
-        .filter_map(|e| e.ok())
-        .collect();
-
-    entries.sort_by_key(|a| {
-        (
-            !a.file_type().is_dir(),
-            a.file_name().to_string_lossy().to_string(),
-        )
-    });
+    log.info(&format!(
+        "Generating file tree for {}...",
+        start_dir.display()
+    ));
+    let mut report = scan_project(&start_dir, &options, &log)?;
+    log.progress_done();
+    let Some(selected) = tui::run(&report)? else {
+        return Ok(());
+    };
+    report.retain(|path| selected.contains(path));
+    if cli.anonymize {
+        anonymize(&mut report, &cli)?;
+    }
+    let destination = Destination::new(&cli, format, output_path, sensitive, &log)?;
+    destination.write(&cli, &report, &log)
+}
 
-    for (i, entry) in entries.iter().enumerate() {
-        let is_last = i == entries.len() - 1;
-        let file_name = entry.file_name().to_string_lossy();
+/// The sensitive file patterns of `config` and `cli`, with their
+/// exceptions.
+fn sensitive_files(cli: &ScanArgs, config: &Config) -> io::Result<SensitiveFiles> {
+    let mut sensitive = config.sensitive.clone();
+    sensitive.extend(SensitivePolicy {
+        patterns: cli.sensitive_pattern.clone(),
+        regex: cli.sensitive_regex.clone(),
+        allow: cli.allow_sensitive.clone(),
+    });
+    SensitiveFiles::new(&sensitive)
+}
 
-        if file_name == script_name
-            || file_name == output_file_name
-            || is_excluded_file(entry.path())
-        {
-            continue;
+/// Anonymizes `report` under the key given in `cli`, or else a random key
+/// drawn once per run, so that a watched report keeps its pseudonyms.
+fn anonymize(report: &mut Report, cli: &ScanArgs) -> io::Result<()> {
+    static RANDOM_KEY: OnceLock<anonymize::Key> = OnceLock::new();
+    let key = match (&cli.anonymize_key, RANDOM_KEY.get()) {
+        (Some(secret), _) => anonymize::Key::new(secret),
+        (None, Some(key)) => key.clone(),
+        (None, None) => {
+            let key = anonymize::Key::random()?;
+            let _ = RANDOM_KEY.set(key.clone());
+            key
         }
+    };
+    anonymize::anonymize(report, &key);
+    Ok(())
+}
 
-        if entry.file_type().is_dir() {
-            output.push_str(&format!(
-                "{}{}{}/\n",
-                if is_last { &last_indent } else { &indent },
-                if is_last { "└── " } else { "├── " },
-                file_name
-            ));
-            get_file_tree_and_contents(
-                entry.path(),
-                depth + 1,
-                file_paths,
-                output,
-                script_name,
-                output_file_name,
-            )?;
+/// Fills in the settings `cli` leaves unset from `config`, and installs the
+/// language overrides of both, the command line's first.
+fn apply_config(cli: &mut ScanArgs, config: &Config) -> io::Result<()> {
+    cli.top = cli.top.or(config.top);
+    if cli.tree_style.is_none() && cli.tree_glyphs.is_none() {
+        cli.tree_style = config.tree_style;
+        cli.tree_glyphs = config.tree_glyphs.clone();
+    }
+    cli.language_override
+        .extend(config.language_overrides.iter().cloned());
+    language::set_overrides(&cli.language_override)
+}
+
+/// Where the report goes: `--output`, a file in `--output-dir`, the
+/// configured output or the format's default name, in that order.
+fn output_path(
+    cli: &ScanArgs,
+    start_dir: &Path,
+    config: &Config,
+    format: OutputFormat,
+) -> io::Result<PathBuf> {
+    if let (None, Some(dir)) = (&cli.output, &cli.output_dir) {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(config.report_path(
+        start_dir,
+        format,
+        cli.output.as_deref(),
+        cli.output_dir.as_deref(),
+    ))
+}
+
+/// The settings of a scan of `start_dir` from the flags in `cli` and the
+/// loaded `config`.
+fn scan_options(
+    cli: &ScanArgs,
+    start_dir: &Path,
+    config: Config,
+    format: OutputFormat,
+    output_path: &Path,
+    log: &Logger,
+) -> io::Result<ScanOptions> {
+    let ecosystems = scan::detect(start_dir)?;
+    if !ecosystems.is_empty() {
+        let names: Vec<_> = ecosystems.iter().map(Detection::to_string).collect();
+        log.info(&format!("Detected projects: {}", names.join(", ")));
+    }
+    let sensitive = sensitive_files(cli, &config)?;
+    Ok(ScanOptions {
+        script_name: env::args().next().unwrap(),
+        ignores: Ignores::new(
+            start_dir,
+            &cli.ignore_file,
+            !cli.no_gitignore,
+            &ecosystems,
+            log,
+        )?,
+        globs: PathGlobs::new(&cli.exclude, &cli.include)?,
+        tracked: cli
+            .git_tracked
+            .then(|| TrackedFiles::load(start_dir))
+            .transpose()?,
+        churn: cli.hotspots.then(|| Churn::load(start_dir)).transpose()?,
+        authors: cli.authors,
+        count_doc_blocks: cli.count_doc_blocks,
+        timeout: cli.timeout.map(Duration::from_secs),
+        max_files: cli.max_files,
+        max_depth: cli.max_depth.or(config.max_depth),
+        max_file_size: cli.max_file_size,
+        follow_symlinks: cli.follow_symlinks,
+        hidden: if cli.no_hidden {
+            Hidden::Exclude
+        } else if cli.hidden_tree_only {
+            Hidden::TreeOnly
         } else {
-            output.push_str(&format!(
-                "{}{}{}\n",
-                if is_last { &last_indent } else { &indent },
-                if is_last { "└── " } else { "├── " },
-                file_name
-            ));
-            file_paths.push(entry.path().to_path_buf());
+            Hidden::Include
+        },
+        default_excludes: !cli.no_default_excludes && config.default_excludes.unwrap_or(true),
+        excluded_dirs: config.exclude_dirs,
+        excluded_files: [config.exclude_files, cli.extra_exclude_file.clone()].concat(),
+        sensitive,
+        redact: !cli.no_redact,
+        output_name: output_path.file_name().map(|name| name.to_os_string()),
+        ecosystems,
+        cache: cli.cache.then(|| {
+            Cache::path(
+                &cli.cache_dir
+                    .clone()
+                    .unwrap_or_else(|| cache::dir(start_dir, config.cache_dir.as_deref())),
+            )
+        }),
+        cache_max_size: cli.cache_max_size.or(config.cache_max_size),
+        keep_contents: format.shows_contents() && !cli.anonymize && !cli.stats_only,
+    })
+}
+
+/// Prints whether `target` would be part of a scan with the settings in
+/// `cli`, exiting with status 1 if it would not.
+fn explain(target: &Path, mut cli: ScanArgs) -> io::Result<()> {
+    let start_dir = match cli.paths.as_slice() {
+        [] => env::current_dir()?,
+        [path] => path.clone(),
+        _ => {
+            eprintln!("Error: explain takes a single directory to analyze");
+            std::process::exit(2);
+        }
+    };
+    let config = Config::load(&start_dir)?;
+    let format = cli.format.or(config.format).unwrap_or(OutputFormat::Text);
+    let output_path = output_path(&cli, &start_dir, &config, format)?;
+    let log = Logger::new(cli.log_format).to_stderr(true);
+    apply_config(&mut cli, &config)?;
+    let options = scan_options(&cli, &start_dir, config, format, &output_path, &log)?;
+
+    // Resolve `.` and `..` without following symlinks, which are part of
+    // what is being explained.
+    let mut relative = env::current_dir()?;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                relative.pop();
+            }
+            component => relative.push(component),
         }
     }
+    let root = fs::canonicalize(&start_dir)?;
+    let verdict = match relative.strip_prefix(&root) {
+        Ok(relative) => scan::explain(&root, relative, &options, &log)?,
+        Err(_) => Verdict::NotFound,
+    };
 
+    let shown = target.display();
+    match &verdict {
+        Verdict::Included => println!("{}: included", shown),
+        Verdict::Listed(reason) => println!("{}: listed in the tree only, as {}", shown, reason),
+        Verdict::Excluded(excluded) if root.join(&excluded.path) == relative => {
+            println!("{}: excluded ({})", shown, excluded.rule)
+        }
+        Verdict::Excluded(excluded) => println!(
+            "{}: excluded, as {} is excluded ({})",
+            shown,
+            excluded.path.display(),
+            excluded.rule
+        ),
+        Verdict::NotFound => println!("{}: not found in {}", shown, start_dir.display()),
+    }
+    if !matches!(verdict, Verdict::Included | Verdict::Listed(_)) {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-fn is_excluded(entry: &DirEntry) -> bool {
-    entry.file_type().is_dir() && EXCLUDED_DIRS.contains(&entry.file_name().to_str().unwrap_or(""))
+/// Where and how the report is written.
+struct Destination {
+    format: OutputFormat,
+    /// Flags the sensitive files in a CSV report.
+    sensitive: SensitiveFiles,
+    path: PathBuf,
+    /// Write to stdout instead of `path`.
+    stdout: bool,
+    /// Print the statistics instead of writing the report.
+    stats_only: bool,
+    prompt_template: Option<String>,
+    baseline: Option<Snapshot>,
+    glyphs: TreeGlyphs,
 }
 
-fn is_excluded_file(path: &Path) -> bool {
-    EXCLUDED_FILES.contains(&path.file_name().unwrap_or_default().to_str().unwrap_or(""))
+impl Destination {
+    /// The destination for a report in `format` at `path` as set by `cli`.
+    /// Removes an earlier report at `path`, and exits if the format cannot
+    /// go to stdout.
+    fn new(
+        cli: &ScanArgs,
+        format: OutputFormat,
+        path: PathBuf,
+        sensitive: SensitiveFiles,
+        log: &Logger,
+    ) -> io::Result<Self> {
+        let prompt_template = match &cli.prompt_template {
+            Some(path) if format == OutputFormat::Text => Some(fs::read_to_string(path)?),
+            Some(_) => {
+                log.warn("--prompt-template only applies to the text format; ignoring it");
+                None
+            }
+            None => None,
+        };
+
+        let baseline = match &cli.baseline {
+            Some(path) => Some(Snapshot::load(path)?),
+            None => None,
+        };
+
+        let glyphs = cli
+            .tree_glyphs
+            .clone()
+            .unwrap_or_else(|| cli.tree_style.unwrap_or(TreeStyle::Unicode).glyphs());
+
+        if cli.stdout && matches!(format, OutputFormat::Sqlite | OutputFormat::Wiki) {
+            log.warn("--stdout cannot carry a database or a directory of pages");
+            std::process::exit(2);
+        }
+        if !cli.stdout && !cli.stats_only && path.is_file() {
+            fs::remove_file(&path)?;
+        }
+        Ok(Destination {
+            format,
+            sensitive,
+            path,
+            stdout: cli.stdout,
+            stats_only: cli.stats_only,
+            prompt_template,
+            baseline,
+            glyphs,
+        })
+    }
+
+    /// Writes `report`, or only its statistics, and the per-file metrics if
+    /// requested.
+    fn write(&self, cli: &ScanArgs, report: &Report, log: &Logger) -> io::Result<()> {
+        if self.stats_only {
+            info_span!("write").in_scope(|| {
+                let mut out = io::stdout().lock();
+                out.write_all(output::text::stats(report, cli.top).as_bytes())?;
+                out.flush()
+            })?;
+        } else {
+            self.write_report(cli, report, log)?;
+        }
+
+        if let Some(path) = &cli.metrics_csv {
+            info_span!("write")
+                .in_scope(|| fs::write(path, output::metrics_csv::render(report)))?;
+            log.info(&format!(
+                "Per-file metrics have been written to {}",
+                path.display()
+            ));
+        }
+        if let Some(path) = &cli.extensions_csv {
+            info_span!("write")
+                .in_scope(|| fs::write(path, output::csv::render_extensions(report)))?;
+            log.info(&format!(
+                "Per-extension totals have been written to {}",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the report in `format` to `path` or stdout.
+    fn write_report(&self, cli: &ScanArgs, report: &Report, log: &Logger) -> io::Result<()> {
+        log.info("Writing to file...");
+        info_span!("write").in_scope(|| match self.format {
+            OutputFormat::Sqlite => output::sqlite::write(report, &self.path),
+            OutputFormat::Wiki => output::wiki::write(report, &self.path),
+            _ => {
+                let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(if self.stdout {
+                    Box::new(io::stdout().lock())
+                } else {
+                    Box::new(File::create(&self.path)?)
+                });
+                self.render(cli, report, &mut out)?;
+                out.flush()
+            }
+        })?;
+        if self.stdout {
+            log.info("File tree and contents have been written to stdout");
+        } else {
+            log.info(&format!(
+                "File tree and contents have been written to {}",
+                self.path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Renders `report` to `out` in `format`, any but the database and the
+    /// wiki, which are written to `path` directly.
+    fn render(&self, cli: &ScanArgs, report: &Report, out: &mut impl Write) -> io::Result<()> {
+        let top = |default| cli.top.unwrap_or(default);
+        match self.format {
+            OutputFormat::Text => {
+                if let Some(template) = &self.prompt_template {
+                    let prompt =
+                        output::prompt::render(template, report, &cli.instructions, &self.glyphs);
+                    write!(out, "{}\n\n", prompt)?;
+                }
+                output::text::write(report, &self.glyphs, cli.top, out)
+            }
+            OutputFormat::Json => match cli.json_layout {
+                JsonLayout::Nested => output::json::write(report, &self.glyphs, out),
+                JsonLayout::Flat => output::json::write_flat(report, out),
+            },
+            OutputFormat::Ndjson => output::json::write_ndjson(report, out),
+            OutputFormat::Confluence => output::confluence::write(report, &self.glyphs, out),
+            OutputFormat::Llm => output::llm::write(report, &self.glyphs, cli.max_tokens, out),
+            OutputFormat::Openmetrics => {
+                out.write_all(output::openmetrics::render(report).as_bytes())
+            }
+            OutputFormat::PrComment => out.write_all(
+                output::pr_comment::render(
+                    report,
+                    self.baseline.as_ref(),
+                    top(output::pr_comment::MAX_LISTED),
+                )
+                .as_bytes(),
+            ),
+            OutputFormat::Slack => out.write_all(
+                output::chat::render_slack(report, top(output::chat::TOP_N))?.as_bytes(),
+            ),
+            OutputFormat::Discord => out.write_all(
+                output::chat::render_discord(report, top(output::chat::TOP_N)).as_bytes(),
+            ),
+            OutputFormat::Email => {
+                out.write_all(output::email::render(report, top(output::email::TOP_N)).as_bytes())
+            }
+            OutputFormat::Csv => {
+                out.write_all(output::csv::render(report, &self.sensitive).as_bytes())
+            }
+            OutputFormat::ClocJson => out.write_all(output::cloc::render(report).as_bytes()),
+            OutputFormat::TreemapSvg => out.write_all(output::treemap::render(report).as_bytes()),
+            OutputFormat::SunburstSvg => out.write_all(output::sunburst::render(report).as_bytes()),
+            OutputFormat::Sqlite | OutputFormat::Wiki => {
+                unreachable!("{:?} is written to its path directly", self.format)
+            }
+        }
+    }
 }
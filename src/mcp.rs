@@ -0,0 +1,308 @@
+//! Answering questions about a scanned project over the Model Context
+//! Protocol, so AI assistants can look up its tree, files and statistics
+//! as they need them instead of reading a whole report.
+//!
+//! Messages are JSON-RPC 2.0, one per line, as in the protocol's stdio
+//! transport. Only tools are offered.
+
+use crate::output::text::{self, TreeStyle};
+use crate::report::{FileContent, Report, TreeNode};
+use regex::RegexBuilder;
+use schemars::{schema_for, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Protocol revisions understood, newest first. A client asking for another
+/// is offered the newest.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Matching lines returned by `search` unless it asks for another number.
+const MAX_RESULTS: usize = 100;
+
+/// Arguments of the `get_tree` tool.
+#[derive(Deserialize, JsonSchema)]
+struct GetTree {
+    /// Directory to show, relative to the project root; the whole project
+    /// if left out.
+    path: Option<String>,
+}
+
+/// Arguments of the `get_file` tool.
+#[derive(Deserialize, JsonSchema)]
+struct GetFile {
+    /// File to read, relative to the project root.
+    path: String,
+    /// First line to return, counting from 1.
+    start_line: Option<usize>,
+    /// Last line to return, inclusive.
+    end_line: Option<usize>,
+}
+
+/// Arguments of the `get_stats` tool, which takes none.
+#[derive(Deserialize, JsonSchema)]
+struct GetStats {}
+
+/// Arguments of the `search` tool.
+#[derive(Deserialize, JsonSchema)]
+struct Search {
+    /// Text to look for in the contents of files.
+    query: String,
+    /// Treat `query` as a regular expression.
+    #[serde(default)]
+    regex: bool,
+    /// Match upper and lower case exactly.
+    #[serde(default)]
+    case_sensitive: bool,
+    /// Only search files below this directory, relative to the project
+    /// root.
+    path: Option<String>,
+    /// How many matching lines to return at most (default 100).
+    max_results: Option<usize>,
+}
+
+/// Reads requests from `input` until it ends and writes the responses to
+/// `output`, answering from `report`, which is locked for each tool call.
+pub fn serve(
+    report: &Mutex<Report>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(message) => handle(report, message),
+            Err(err) => Some(error(Value::Null, PARSE_ERROR, err.to_string())),
+        };
+        if let Some(response) = response {
+            serde_json::to_writer(&mut output, &response)?;
+            output.write_all(b"\n")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The response to `message`, or `None` for notifications, which get none.
+fn handle(report: &Mutex<Report>, message: Value) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let method = message.get("method").and_then(Value::as_str);
+    let (id, method) = match (id, method) {
+        (Some(id), Some(method)) => (id, method),
+        // Notifications, such as `notifications/initialized`, and
+        // responses, to requests that are never sent, need no answer.
+        (None, _) => return None,
+        (Some(id), None) => {
+            return Some(error(id, INVALID_REQUEST, "Missing method".to_string()));
+        }
+    };
+    let params = message.get("params").unwrap_or(&Value::Null);
+    let result = match method {
+        "initialize" => Ok(initialize(params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => call(report, params),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error(id, code, message),
+    })
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|version| Some(*version) == requested)
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "codetree", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Tools to explore a scanned project: its file tree, the contents \
+                         of its files, its statistics and a search through its files.",
+    })
+}
+
+fn tools() -> Vec<Value> {
+    let tool = |name, description, schema| json!({ "name": name, "description": description, "inputSchema": schema });
+    vec![
+        tool(
+            "get_tree",
+            "The file tree of the project, or of one of its directories.",
+            json!(schema_for!(GetTree)),
+        ),
+        tool(
+            "get_file",
+            "The contents of a file of the project, or a range of its lines.",
+            json!(schema_for!(GetFile)),
+        ),
+        tool(
+            "get_stats",
+            "Statistics of the project: files, lines, languages, largest files and more.",
+            json!(schema_for!(GetStats)),
+        ),
+        tool(
+            "search",
+            "Lines of the project's files holding some text or matching a regular \
+             expression, as path:line: text.",
+            json!(schema_for!(Search)),
+        ),
+    ]
+}
+
+/// Runs the tool named in `params`. A tool that fails still answers, with
+/// a result marked as an error that the assistant gets to read.
+fn call(report: &Mutex<Report>, params: &Value) -> Result<Value, (i64, String)> {
+    let Some(name) = params.get("name").and_then(Value::as_str) else {
+        return Err((INVALID_PARAMS, "Missing tool name".to_string()));
+    };
+    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+    let report = report.lock().unwrap();
+    let output = match name {
+        "get_tree" => parse(arguments).and_then(|args| get_tree(&report, args)),
+        "get_file" => parse(arguments).and_then(|args| get_file(&report, args)),
+        "get_stats" => parse::<GetStats>(arguments).map(|_| text::stats(&report, None)),
+        "search" => parse(arguments).and_then(|args| search(&report, args)),
+        _ => return Err((INVALID_PARAMS, format!("Unknown tool {}", name))),
+    };
+    let (text, is_error) = match output {
+        Ok(text) => (text, false),
+        Err(message) => (message, true),
+    };
+    Ok(json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    }))
+}
+
+fn parse<T: DeserializeOwned>(arguments: Value) -> Result<T, String> {
+    serde_json::from_value(arguments).map_err(|err| format!("Invalid arguments: {}", err))
+}
+
+fn get_tree(report: &Report, args: GetTree) -> Result<String, String> {
+    let path = normalize(args.path.as_deref().unwrap_or(""));
+    match find(&report.root, &path) {
+        Some(node) if node.is_dir => Ok(text::tree(node, &TreeStyle::Unicode.glyphs())),
+        _ => Err(format!(
+            "{} is not a directory of the report",
+            path.display()
+        )),
+    }
+}
+
+fn get_file(report: &Report, args: GetFile) -> Result<String, String> {
+    let path = normalize(&args.path);
+    let Some(file) = report.files.iter().find(|file| file.path == path) else {
+        return Err(match find(&report.root, &path) {
+            Some(node) if node.is_dir => format!("{} is a directory", args.path),
+            Some(_) => format!("{} is listed, but its contents are not read", args.path),
+            None => format!("{} is not part of the report", args.path),
+        });
+    };
+    let content = match &file.content {
+        FileContent::Text(text) if text.too_large => {
+            return Err(format!("{} is larger than --max-file-size", args.path))
+        }
+        FileContent::Text(text) => report.read(text).map_err(|err| err.to_string())?,
+        FileContent::Binary => return Err(format!("{} is a binary file", args.path)),
+        FileContent::Unreadable | FileContent::Missing => None,
+    };
+    let Some(content) = content else {
+        return Err(format!("The contents of {} are not available", args.path));
+    };
+    if args.start_line.is_none() && args.end_line.is_none() {
+        return Ok(content);
+    }
+    let start = args.start_line.unwrap_or(1).max(1);
+    let end = args.end_line.unwrap_or(usize::MAX);
+    Ok(content
+        .lines()
+        .skip(start - 1)
+        .take(end.saturating_sub(start - 1))
+        .map(|line| format!("{}\n", line))
+        .collect())
+}
+
+fn search(report: &Report, args: Search) -> Result<String, String> {
+    let pattern = if args.regex {
+        args.query.clone()
+    } else {
+        regex::escape(&args.query)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!args.case_sensitive)
+        .build()
+        .map_err(|err| format!("Invalid regular expression: {}", err))?;
+    let below = normalize(args.path.as_deref().unwrap_or(""));
+    let max = args.max_results.unwrap_or(MAX_RESULTS);
+
+    let mut results = String::new();
+    let mut found = 0;
+    for file in report
+        .files
+        .iter()
+        .filter(|file| file.path.starts_with(&below))
+    {
+        let FileContent::Text(text) = &file.content else {
+            continue;
+        };
+        let Some(content) = report.read(text).map_err(|err| err.to_string())? else {
+            continue;
+        };
+        for (i, line) in content.lines().enumerate() {
+            if !regex.is_match(line) {
+                continue;
+            }
+            if found == max {
+                results.push_str("(more matches left out)\n");
+                return Ok(results);
+            }
+            found += 1;
+            results.push_str(&format!("{}:{}: {}\n", file.path.display(), i + 1, line));
+        }
+    }
+    if found == 0 {
+        return Ok(format!("No lines match {}", args.query));
+    }
+    Ok(results)
+}
+
+/// `path` as given by a client, relative to the project root, without any
+/// `.`, `..` or root components.
+fn normalize(path: &str) -> PathBuf {
+    Path::new(path)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+/// The node at `path`, relative to the root, if the tree holds one.
+fn find<'a>(root: &'a TreeNode, path: &Path) -> Option<&'a TreeNode> {
+    path.components().try_fold(root, |node, component| {
+        node.children
+            .iter()
+            .find(|child| child.name == component.as_os_str().to_string_lossy())
+    })
+}
@@ -0,0 +1,225 @@
+//! Combining the reports of several projects, such as separately scanned
+//! services, into one report.
+
+use crate::authors::Authorship;
+use crate::churn::Churn;
+use crate::detect::Detection;
+use crate::report::{Excluded, FileContent, Project, Report, TreeNode};
+use crate::spool::Spool;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Combines `reports` into one report of a project called `name`. Each
+/// report's tree becomes a top-level directory named after its project,
+/// with a number appended to names used more than once, and its files and
+/// excluded paths move below it. Statistics of the result cover all the
+/// projects, and [`Report::projects`] lists them, each followed by its own
+/// projects; the first partial report, if any, makes the result partial.
+pub fn merge(reports: Vec<Report>, name: &str) -> io::Result<Report> {
+    let mut root = TreeNode {
+        name: name.to_string(),
+        path: PathBuf::new(),
+        is_dir: true,
+        size: 0,
+        children: Vec::new(),
+        link: None,
+    };
+    let mut spool = Spool::new()?;
+    let mut files = Vec::new();
+    let mut excluded = Vec::new();
+    let mut ecosystems: Vec<Detection> = Vec::new();
+    let mut truncated = None;
+    let mut churn: Option<Churn> = None;
+    let mut authors: Option<Authorship> = None;
+    let mut labels = HashSet::new();
+    let mut projects = Vec::new();
+
+    for report in reports {
+        let label = unique_label(&report.project_name, &mut labels);
+        let mut tree = report.root;
+        tree.name = label.clone();
+        relocate(&mut tree, Path::new(&label));
+        root.size += tree.size;
+        root.children.push(tree);
+        projects.push(Project {
+            path: PathBuf::from(&label),
+            ecosystems: report.ecosystems.clone(),
+        });
+        projects.extend(report.projects.into_iter().map(|project| Project {
+            path: Path::new(&label).join(project.path),
+            ecosystems: project.ecosystems,
+        }));
+
+        for mut file in report.files {
+            if let FileContent::Text(text) = &mut file.content {
+                if let Some(spooled) = text.spooled {
+                    text.spooled = Some(spool.append(&report.spool.read(spooled)?)?);
+                }
+            }
+            file.path = Path::new(&label).join(&file.path);
+            files.push(file);
+        }
+        excluded.extend(report.excluded.into_iter().map(|entry| Excluded {
+            path: Path::new(&label).join(entry.path),
+            rule: entry.rule,
+        }));
+        for detection in report.ecosystems {
+            match ecosystems
+                .iter_mut()
+                .find(|known| known.ecosystem.name == detection.ecosystem.name)
+            {
+                Some(known) => {
+                    for framework in detection.frameworks {
+                        if !known.frameworks.contains(&framework) {
+                            known.frameworks.push(framework);
+                        }
+                    }
+                }
+                None => ecosystems.push(detection),
+            }
+        }
+        truncated = truncated.or(report.truncated);
+        if let Some(changes) = report.churn {
+            let churn = churn.get_or_insert_with(Churn::default);
+            for (path, file) in changes.iter() {
+                churn.insert(Path::new(&label).join(path), file);
+            }
+        }
+        if let Some(authorship) = report.authors {
+            let authors = authors.get_or_insert_with(Authorship::default);
+            for (path, file) in authorship.iter() {
+                authors.insert(Path::new(&label).join(path), file.to_vec());
+            }
+        }
+    }
+
+    Ok(Report {
+        project_name: name.to_string(),
+        root,
+        files,
+        spool,
+        ecosystems,
+        churn,
+        authors,
+        truncated,
+        excluded,
+        projects,
+    })
+}
+
+/// `name`, or `name-2`, `name-3` and so on if it is already in `labels`,
+/// which the returned label is added to.
+fn unique_label(name: &str, labels: &mut HashSet<String>) -> String {
+    let mut label = name.to_string();
+    let mut n = 1;
+    while !labels.insert(label.clone()) {
+        n += 1;
+        label = format!("{}-{}", name, n);
+    }
+    label
+}
+
+/// Moves `node` and everything below it to `path`.
+fn relocate(node: &mut TreeNode, path: &Path) {
+    node.path = path.to_path_buf();
+    for child in &mut node.children {
+        let path = path.join(&child.name);
+        relocate(child, &path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Logger;
+    use crate::report::Truncation;
+    use crate::scan::{scan_project, ScanOptions};
+    use std::fs;
+
+    /// Scans a project directory called `name`, holding `files` as path and
+    /// content pairs, kept alive by the returned directory.
+    fn report(name: &str, files: &[(&str, &str)]) -> (tempfile::TempDir, Report) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join(name);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let log = Logger::silent();
+        let options = ScanOptions::new(&root, &log).unwrap();
+        let report = scan_project(&root, &options, &log).unwrap();
+        (dir, report)
+    }
+
+    #[test]
+    fn projects_become_top_level_directories() {
+        let (_api, api) = report("api", &[("src/main.rs", "fn main() {}\n")]);
+        let (_web, web) = report("web", &[("index.js", "a;\nb;\n")]);
+        let merged = merge(vec![api, web], "all").unwrap();
+
+        assert_eq!(merged.project_name, "all");
+        let paths: Vec<_> = merged.files.iter().map(|file| file.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("api/src/main.rs"),
+                PathBuf::from("web/index.js")
+            ]
+        );
+        let names: Vec<_> = merged.root.children.iter().map(|node| &node.name).collect();
+        assert_eq!(names, ["api", "web"]);
+        assert_eq!(
+            merged.root.children[0].children[0].path,
+            Path::new("api/src")
+        );
+        assert_eq!(merged.total_lines(), 3);
+    }
+
+    #[test]
+    fn repeated_names_are_numbered() {
+        let (_a, a) = report("svc", &[("a.txt", "a\n")]);
+        let (_b, b) = report("svc", &[("b.txt", "b\n")]);
+        let (_c, c) = report("svc", &[("c.txt", "c\n")]);
+        let merged = merge(vec![a, b, c], "all").unwrap();
+        let labels: Vec<_> = merged
+            .projects
+            .iter()
+            .map(|project| project.path.clone())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                PathBuf::from("svc"),
+                PathBuf::from("svc-2"),
+                PathBuf::from("svc-3")
+            ]
+        );
+    }
+
+    #[test]
+    fn contents_move_to_the_merged_spool() {
+        let (_a, a) = report("a", &[("x.txt", "first\n")]);
+        let (_b, b) = report("b", &[("y.txt", "second\n")]);
+        let merged = merge(vec![a, b], "all").unwrap();
+        let contents: Vec<_> = merged
+            .files
+            .iter()
+            .map(|file| match &file.content {
+                FileContent::Text(text) => merged.read(text).unwrap().unwrap(),
+                _ => panic!("{} was not read", file.path.display()),
+            })
+            .collect();
+        assert_eq!(contents, ["first\n", "second\n"]);
+    }
+
+    #[test]
+    fn a_partial_report_makes_the_result_partial() {
+        let (_a, a) = report("a", &[("x.txt", "x\n")]);
+        let (_b, mut b) = report("b", &[("y.txt", "y\n")]);
+        b.truncated = Some(Truncation::MaxFiles(1));
+        let merged = merge(vec![a, b], "all").unwrap();
+        assert!(matches!(merged.truncated, Some(Truncation::MaxFiles(1))));
+    }
+}
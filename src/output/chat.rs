@@ -0,0 +1,219 @@
+use super::format_size;
+use crate::report::{wasted_bytes, Report};
+use serde_json::json;
+use std::fmt::Write;
+
+/// Default number of languages and files listed in the summaries.
+pub const TOP_N: usize = 5;
+
+/// Renders a Slack Block Kit message payload with the headline statistics,
+/// top `top` languages, largest files and, when collected, authors, ready
+/// to post to an incoming webhook.
+pub fn render_slack(report: &Report, top: usize) -> serde_json::Result<String> {
+    let headline = headline(report);
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": format!("Codetree report: {}", report.project_name),
+        },
+    })];
+    if let Some(truncation) = report.truncated {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!(":warning: {}.", truncation) },
+        }));
+    }
+    blocks.push(json!({
+        "type": "section",
+        "fields": [
+            { "type": "mrkdwn", "text": format!("*Files*\n{}", report.files.len()) },
+            { "type": "mrkdwn", "text": format!("*Lines*\n{}", report.total_lines()) },
+            { "type": "mrkdwn", "text": format!("*Size*\n{}", format_size(report.total_bytes())) },
+            { "type": "mrkdwn", "text": format!("*Tokens*\n~{}", report.total_tokens()) },
+        ],
+    }));
+    if let Some(languages) = top_languages(report, "•", top) {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*Top languages*\n{}", languages) },
+        }));
+    }
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{ "type": "mrkdwn", "text": composition(report) }],
+    }));
+    if let Some(files) = largest_files(report, "•", top) {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*Largest files*\n{}", files) },
+        }));
+    }
+    if let Some(duplicates) = duplicates(report, "•", top) {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": duplicates },
+        }));
+    }
+    if let Some(authors) = top_authors(report, "•", top) {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*Top authors*\n{}", authors) },
+        }));
+    }
+
+    serde_json::to_string_pretty(&json!({ "text": headline, "blocks": blocks }))
+}
+
+/// Renders a Discord-flavored Markdown message with the same content as the
+/// Slack payload.
+pub fn render_discord(report: &Report, top: usize) -> String {
+    let mut output = format!(
+        "**Codetree report: {}**\nFiles: **{}** · Lines: **{}** · Size: **{}** · Tokens: **~{}**\n",
+        report.project_name,
+        report.files.len(),
+        report.total_lines(),
+        format_size(report.total_bytes()),
+        report.total_tokens()
+    );
+    if let Some(truncation) = report.truncated {
+        let _ = writeln!(output, ":warning: {}.", truncation);
+    }
+    if let Some(languages) = top_languages(report, "-", top) {
+        let _ = write!(output, "\n**Top languages**\n{}\n", languages);
+    }
+    let _ = writeln!(output, "\n{}", composition(report));
+    if let Some(files) = largest_files(report, "-", top) {
+        let _ = write!(output, "\n**Largest files**\n{}\n", files);
+    }
+    if let Some(duplicates) = duplicates(report, "-", top) {
+        let _ = write!(output, "\n{}\n", duplicates.replace('*', "**"));
+    }
+    if let Some(authors) = top_authors(report, "-", top) {
+        let _ = write!(output, "\n**Top authors**\n{}\n", authors);
+    }
+    output
+}
+
+fn headline(report: &Report) -> String {
+    format!(
+        "Codetree: {} has {} files, {} lines, {}",
+        report.project_name,
+        report.files.len(),
+        report.total_lines(),
+        format_size(report.total_bytes())
+    )
+}
+
+fn top_languages(report: &Report, bullet: &str, top: usize) -> Option<String> {
+    let total = report.total_lines().max(1) as f64;
+    let lines: Vec<_> = report
+        .language_totals()
+        .into_iter()
+        .take(top)
+        .map(|(language, totals)| {
+            format!(
+                "{} {}: {} lines ({:.1}%)",
+                bullet,
+                language,
+                totals.lines,
+                totals.lines as f64 / total * 100.0
+            )
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Share of lines per category, skipping empty ones.
+fn composition(report: &Report) -> String {
+    let total = report.total_lines().max(1) as f64;
+    let shares: Vec<_> = report
+        .category_totals()
+        .into_iter()
+        .filter(|(_, totals)| totals.lines > 0)
+        .map(|(category, totals)| {
+            format!("{} {:.0}%", category, totals.lines as f64 / total * 100.0)
+        })
+        .collect();
+    format!("Composition: {}", shares.join(" · "))
+}
+
+fn largest_files(report: &Report, bullet: &str, top: usize) -> Option<String> {
+    let lines: Vec<_> = report
+        .largest_files(top)
+        .into_iter()
+        .map(|file| {
+            format!(
+                "{} `{}` ({})",
+                bullet,
+                file.path.display(),
+                format_size(file.size)
+            )
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The authors with the most lines, with the bus factor of each top-level
+/// directory below them.
+fn top_authors(report: &Report, bullet: &str, top: usize) -> Option<String> {
+    let authors = report.author_totals();
+    if authors.is_empty() {
+        return None;
+    }
+    let total = authors
+        .iter()
+        .map(|author| author.lines)
+        .sum::<usize>()
+        .max(1) as f64;
+    let mut lines: Vec<_> = authors
+        .iter()
+        .take(top)
+        .map(|author| {
+            format!(
+                "{} {}: {} lines ({:.1}%)",
+                bullet,
+                author.name,
+                author.lines,
+                author.lines as f64 / total * 100.0
+            )
+        })
+        .collect();
+    let bus_factors: Vec<_> = report
+        .ownership()
+        .iter()
+        .map(|directory| {
+            format!(
+                "`{}/` {}",
+                directory.directory.display(),
+                directory.bus_factor
+            )
+        })
+        .collect();
+    if !bus_factors.is_empty() {
+        lines.push(format!("Bus factor: {}", bus_factors.join(" · ")));
+    }
+    Some(lines.join("\n"))
+}
+
+/// A titled list of the groups of identical files, with the title in Slack
+/// bold syntax.
+fn duplicates(report: &Report, bullet: &str, top: usize) -> Option<String> {
+    let groups = report.duplicate_groups();
+    if groups.is_empty() {
+        return None;
+    }
+    let mut text = format!(
+        "*Identical files* ({} wasted)",
+        format_size(wasted_bytes(&groups))
+    );
+    for group in groups.iter().take(top) {
+        let paths: Vec<_> = group
+            .paths
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+        let _ = write!(text, "\n{} {}", bullet, paths.join(", "));
+    }
+    Some(text)
+}
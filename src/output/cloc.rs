@@ -0,0 +1,92 @@
+use crate::comments::LineCounts;
+use crate::language::display_name;
+use crate::report::Report;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Per-language totals under cloc's field names.
+#[derive(Default, Serialize)]
+struct ClocCounts {
+    #[serde(rename = "nFiles")]
+    files: usize,
+    blank: usize,
+    comment: usize,
+    code: usize,
+}
+
+impl ClocCounts {
+    fn add(&mut self, counts: LineCounts) {
+        self.files += 1;
+        self.blank += counts.blanks;
+        self.comment += counts.comments;
+        self.code += counts.code;
+    }
+}
+
+#[derive(Serialize)]
+struct Header {
+    codetree_version: &'static str,
+    n_files: usize,
+    n_lines: usize,
+    /// Estimated tokens of the counted files; not part of cloc's output.
+    n_tokens: usize,
+}
+
+/// The document `cloc --json` prints: a header, one entry per language
+/// and the sum over all of them, in that order.
+struct ClocReport {
+    header: Header,
+    languages: Vec<(&'static str, ClocCounts)>,
+    sum: ClocCounts,
+}
+
+impl Serialize for ClocReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.languages.len() + 2))?;
+        map.serialize_entry("header", &self.header)?;
+        for (name, counts) in &self.languages {
+            map.serialize_entry(name, counts)?;
+        }
+        map.serialize_entry("SUM", &self.sum)?;
+        map.end()
+    }
+}
+
+/// Renders the per-language code, comment and blank line counts in the
+/// shape of `cloc --json`, largest by code first, so tooling built around
+/// cloc can read them unchanged. Like cloc, files in languages it does not
+/// know are left out.
+pub fn render(report: &Report) -> String {
+    let mut totals: HashMap<&'static str, ClocCounts> = HashMap::new();
+    let mut sum = ClocCounts::default();
+    let mut tokens = 0;
+    for file in &report.files {
+        let Some(language) = file.language() else {
+            continue;
+        };
+        totals
+            .entry(display_name(language))
+            .or_default()
+            .add(file.line_counts());
+        sum.add(file.line_counts());
+        tokens += file.token_count();
+    }
+    let mut languages: Vec<_> = totals.into_iter().collect();
+    languages.sort_by_key(|(name, counts)| (Reverse(counts.code), *name));
+
+    let cloc = ClocReport {
+        header: Header {
+            codetree_version: env!("CARGO_PKG_VERSION"),
+            n_files: sum.files,
+            n_lines: sum.blank + sum.comment + sum.code,
+            n_tokens: tokens,
+        },
+        languages,
+        sum,
+    };
+    let mut json = serde_json::to_string_pretty(&cloc).unwrap_or_default();
+    json.push('\n');
+    json
+}
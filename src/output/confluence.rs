@@ -0,0 +1,158 @@
+use super::text::{self, TreeGlyphs};
+use super::{escape_html, format_size};
+use crate::report::{wasted_bytes, FileContent, Report};
+use std::io::{self, Write};
+
+/// Renders the report in Confluence storage format (XHTML with `ac:`
+/// macros), ready to be used as the body of a page created through the
+/// Confluence REST API. Each file sits in a collapsed expand macro holding a
+/// code macro with its language. The file tree is drawn with `glyphs`.
+/// The page is written to `out` one file at a time.
+pub fn write(report: &Report, glyphs: &TreeGlyphs, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "<h1>Codetree report: {}</h1>",
+        escape_html(&report.project_name)
+    )?;
+    if let Some(truncation) = report.truncated {
+        writeln!(
+            out,
+            "<ac:structured-macro ac:name=\"warning\"><ac:rich-text-body><p>{}.</p></ac:rich-text-body></ac:structured-macro>",
+            escape_html(&truncation.to_string())
+        )?;
+    }
+    writeln!(
+        out,
+        "<table><tbody>\n<tr><th>Files</th><td>{}</td></tr>\n<tr><th>Lines</th><td>{}</td></tr>\n<tr><th>Size</th><td>{}</td></tr>\n<tr><th>Estimated tokens</th><td>{}</td></tr>\n</tbody></table>",
+        report.files.len(),
+        report.total_lines(),
+        format_size(report.total_bytes()),
+        report.total_tokens()
+    )?;
+
+    out.write_all(b"<h2>File tree</h2>\n")?;
+    code_macro(out, None, None, &text::tree(&report.root, glyphs))?;
+
+    let duplicates = report.duplicate_groups();
+    if !duplicates.is_empty() {
+        writeln!(
+            out,
+            "<h2>Identical files</h2>\n<p>{} wasted by redundant copies.</p>\n<table><tbody>\n<tr><th>Files</th><th>Copies</th><th>Size</th></tr>",
+            format_size(wasted_bytes(&duplicates))
+        )?;
+        for group in &duplicates {
+            let paths: Vec<_> = group
+                .paths
+                .iter()
+                .map(|path| format!("<code>{}</code>", escape_html(&path.display().to_string())))
+                .collect();
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                paths.join("<br />"),
+                group.paths.len(),
+                format_size(group.size)
+            )?;
+        }
+        out.write_all(b"</tbody></table>\n")?;
+    }
+
+    let authors = report.author_totals();
+    if !authors.is_empty() {
+        out.write_all(
+            b"<h2>Authors</h2>\n<table><tbody>\n<tr><th>Author</th><th>Files</th><th>Lines</th></tr>\n",
+        )?;
+        for author in &authors {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(author.name),
+                author.files,
+                author.lines
+            )?;
+        }
+        out.write_all(b"</tbody></table>\n<table><tbody>\n<tr><th>Directory</th><th>Bus factor</th><th>Lines</th><th>Main author</th></tr>\n")?;
+        for directory in report.ownership() {
+            writeln!(
+                out,
+                "<tr><td><code>{}/</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&directory.directory.display().to_string()),
+                directory.bus_factor,
+                directory.lines,
+                escape_html(directory.authors.first().map_or("", |(name, _)| *name))
+            )?;
+        }
+        out.write_all(b"</tbody></table>\n")?;
+    }
+
+    out.write_all(b"<h2>Files</h2>\n")?;
+    let earlier_copies = report.earlier_copies();
+    for file in &report.files {
+        let path = file.path.display().to_string();
+        let title = match &file.content {
+            FileContent::Text(text) => format!(
+                "{} ({} lines: {} code, {} comments, {} blank)",
+                path, text.lines, text.counts.code, text.counts.comments, text.counts.blanks
+            ),
+            _ => path.clone(),
+        };
+        writeln!(
+            out,
+            "<ac:structured-macro ac:name=\"expand\"><ac:parameter ac:name=\"title\">{}</ac:parameter><ac:rich-text-body>",
+            escape_html(&title)
+        )?;
+        match (&file.content, earlier_copies.get(file.path.as_path())) {
+            (FileContent::Text(_), Some(original)) => {
+                writeln!(
+                    out,
+                    "<p><em>Identical to <code>{}</code>.</em></p>",
+                    escape_html(&original.display().to_string())
+                )?;
+            }
+            (FileContent::Text(text), None) => match report.read(text)? {
+                Some(content) => code_macro(out, file.language(), Some(&path), &content)?,
+                None if text.too_large => writeln!(
+                    out,
+                    "<p><em>File too large, {} lines omitted.</em></p>",
+                    text.lines
+                )?,
+                None => out.write_all(b"<p><em>Content withheld.</em></p>\n")?,
+            },
+            (FileContent::Binary, _) => out.write_all(b"<p><em>Binary file omitted.</em></p>\n")?,
+            (FileContent::Unreadable, _) => {
+                out.write_all(b"<p><em>Unable to read file content.</em></p>\n")?
+            }
+            (FileContent::Missing, _) => out.write_all(b"<p><em>File not found.</em></p>\n")?,
+        }
+        out.write_all(b"</ac:rich-text-body></ac:structured-macro>\n")?;
+    }
+    Ok(())
+}
+
+fn code_macro(
+    out: &mut impl Write,
+    language: Option<&str>,
+    title: Option<&str>,
+    body: &str,
+) -> io::Result<()> {
+    out.write_all(b"<ac:structured-macro ac:name=\"code\">")?;
+    if let Some(language) = language {
+        write!(
+            out,
+            "<ac:parameter ac:name=\"language\">{}</ac:parameter>",
+            escape_html(language)
+        )?;
+    }
+    if let Some(title) = title {
+        write!(
+            out,
+            "<ac:parameter ac:name=\"title\">{}</ac:parameter>",
+            escape_html(title)
+        )?;
+    }
+    writeln!(
+        out,
+        "<ac:plain-text-body><![CDATA[{}]]></ac:plain-text-body></ac:structured-macro>",
+        body.replace("]]>", "]]]]><![CDATA[>")
+    )
+}
@@ -0,0 +1,65 @@
+use super::{csv_authors, csv_field};
+use crate::ignores::SensitiveFiles;
+use crate::report::Report;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Renders one CSV row per included file with its line breakdown, estimated
+/// tokens, size, whether `sensitive` matches it and, when collected, its
+/// authors.
+pub fn render(report: &Report, sensitive: &SensitiveFiles) -> String {
+    let mut csv =
+        String::from("path,extension,language,lines,code,comments,blanks,tokens,size,sensitive");
+    if report.authors.is_some() {
+        csv.push_str(",authors");
+    }
+    csv.push('\n');
+    for file in &report.files {
+        let counts = file.line_counts();
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&file.path.display().to_string()),
+            csv_field(&extension(&file.path)),
+            csv_field(file.language().unwrap_or("")),
+            file.line_count(),
+            counts.code,
+            counts.comments,
+            counts.blanks,
+            file.token_count(),
+            file.size,
+            sensitive.is_sensitive(&file.path)
+        );
+        if report.authors.is_some() {
+            let _ = write!(csv, ",{}", csv_authors(report, &file.path));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Renders one CSV row of totals per file extension, in alphabetical order.
+pub fn render_extensions(report: &Report) -> String {
+    let mut csv = String::from("extension,files,lines,code,comments,blanks,tokens,size\n");
+    for (extension, totals) in report.extension_totals() {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            csv_field(&extension),
+            totals.files,
+            totals.lines,
+            totals.counts.code,
+            totals.counts.comments,
+            totals.counts.blanks,
+            totals.tokens,
+            totals.bytes
+        );
+    }
+    csv
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
@@ -0,0 +1,213 @@
+use super::{escape_html, format_size};
+use crate::report::{wasted_bytes, Report};
+use std::fmt::Write;
+
+/// Default number of languages and files listed in the digest.
+pub const TOP_N: usize = 10;
+
+const CELL: &str = "padding:6px 12px;border-bottom:1px solid #e5e7eb;";
+const NUMBER_CELL: &str = "padding:6px 12px;border-bottom:1px solid #e5e7eb;text-align:right;";
+const HEADER_CELL: &str =
+    "padding:6px 12px;border-bottom:2px solid #d1d5db;text-align:left;color:#374151;";
+
+/// Renders a minimal HTML summary for use as an email body: inline styles
+/// and table layout only, no scripts or external resources. Each ranking
+/// lists at most `top` entries.
+pub fn render(report: &Report, top: usize) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Codetree report: {name}</title></head>\n\
+         <body style=\"margin:0;padding:0;background:#f3f4f6;\">\n\
+         <table role=\"presentation\" width=\"100%\" cellpadding=\"0\" cellspacing=\"0\" style=\"background:#f3f4f6;\">\n\
+         <tr><td align=\"center\" style=\"padding:24px;\">\n\
+         <table role=\"presentation\" width=\"600\" cellpadding=\"0\" cellspacing=\"0\" style=\"background:#ffffff;font-family:Arial,Helvetica,sans-serif;font-size:14px;color:#111827;\">\n\
+         <tr><td style=\"padding:24px 24px 8px;\"><h1 style=\"margin:0;font-size:20px;\">Codetree report: {name}</h1></td></tr>\n",
+        name = escape_html(&report.project_name)
+    );
+    if let Some(truncation) = report.truncated {
+        let _ = writeln!(
+            html,
+            "<tr><td style=\"padding:8px 24px;color:#b45309;\"><strong>{}.</strong></td></tr>",
+            escape_html(&truncation.to_string())
+        );
+    }
+
+    let _ = write!(
+        html,
+        "<tr><td style=\"padding:8px 24px;\">\n\
+         <table role=\"presentation\" width=\"100%\" cellpadding=\"0\" cellspacing=\"0\">\n<tr>\n\
+         {}{}{}{}</tr>\n</table>\n</td></tr>\n",
+        stat("Files", &report.files.len().to_string()),
+        stat("Lines", &report.total_lines().to_string()),
+        stat("Size", &format_size(report.total_bytes())),
+        stat("Tokens", &format!("~{}", report.total_tokens()))
+    );
+
+    let languages = report.language_totals();
+    if !languages.is_empty() {
+        let total = report.total_lines().max(1) as f64;
+        section_start(
+            &mut html,
+            "Languages",
+            &["Language", "Files", "Lines", "Share"],
+        );
+        for (language, totals) in languages.iter().take(top) {
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"{CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{:.1}%</td></tr>",
+                escape_html(language),
+                totals.files,
+                totals.lines,
+                totals.lines as f64 / total * 100.0
+            );
+        }
+        section_end(&mut html);
+    }
+
+    let total_bytes = report.total_bytes().max(1) as f64;
+    section_start(
+        &mut html,
+        "Composition",
+        &["Category", "Files", "Lines", "Size"],
+    );
+    for (category, totals) in report.category_totals() {
+        let _ = writeln!(
+            html,
+            "<tr><td style=\"{CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+             <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{} ({:.1}%)</td></tr>",
+            category,
+            totals.files,
+            totals.lines,
+            format_size(totals.bytes),
+            totals.bytes as f64 / total_bytes * 100.0
+        );
+    }
+    section_end(&mut html);
+
+    let largest = report.largest_files(top);
+    if !largest.is_empty() {
+        section_start(
+            &mut html,
+            "Largest files",
+            &["File", "Lines", "Code", "Comments", "Blank", "Size"],
+        );
+        for file in largest {
+            let counts = file.line_counts();
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"{CELL}font-family:Consolas,Menlo,monospace;\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td></tr>",
+                escape_html(&file.path.display().to_string()),
+                file.line_count(),
+                counts.code,
+                counts.comments,
+                counts.blanks,
+                format_size(file.size)
+            );
+        }
+        section_end(&mut html);
+    }
+
+    let duplicates = report.duplicate_groups();
+    if !duplicates.is_empty() {
+        let title = format!(
+            "Identical files ({} wasted)",
+            format_size(wasted_bytes(&duplicates))
+        );
+        section_start(&mut html, &title, &["Files", "Copies", "Size"]);
+        for group in duplicates.iter().take(top) {
+            let paths: Vec<_> = group
+                .paths
+                .iter()
+                .map(|path| escape_html(&path.display().to_string()))
+                .collect();
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"{CELL}font-family:Consolas,Menlo,monospace;\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td></tr>",
+                paths.join("<br>"),
+                group.paths.len(),
+                format_size(group.size)
+            );
+        }
+        section_end(&mut html);
+    }
+
+    let authors = report.author_totals();
+    if !authors.is_empty() {
+        let total = authors
+            .iter()
+            .map(|author| author.lines)
+            .sum::<usize>()
+            .max(1) as f64;
+        section_start(&mut html, "Authors", &["Author", "Files", "Lines", "Share"]);
+        for author in authors.iter().take(top) {
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"{CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{:.1}%</td></tr>",
+                escape_html(author.name),
+                author.files,
+                author.lines,
+                author.lines as f64 / total * 100.0
+            );
+        }
+        section_end(&mut html);
+
+        section_start(
+            &mut html,
+            "Bus factor",
+            &["Directory", "Bus factor", "Lines", "Main author"],
+        );
+        for directory in report.ownership() {
+            let _ = writeln!(
+                html,
+                "<tr><td style=\"{CELL}font-family:Consolas,Menlo,monospace;\">{}/</td>\
+                 <td style=\"{NUMBER_CELL}\">{}</td><td style=\"{NUMBER_CELL}\">{}</td>\
+                 <td style=\"{CELL}\">{}</td></tr>",
+                escape_html(&directory.directory.display().to_string()),
+                directory.bus_factor,
+                directory.lines,
+                escape_html(directory.authors.first().map_or("", |(name, _)| *name))
+            );
+        }
+        section_end(&mut html);
+    }
+
+    html.push_str(
+        "<tr><td style=\"padding:16px 24px;color:#6b7280;font-size:12px;\">Generated by Codetree</td></tr>\n\
+         </table>\n</td></tr>\n</table>\n</body>\n</html>\n",
+    );
+    html
+}
+
+fn stat(label: &str, value: &str) -> String {
+    format!(
+        "<td width=\"33%\" style=\"padding:12px;background:#f9fafb;border:1px solid #e5e7eb;\">\
+         <div style=\"color:#6b7280;font-size:12px;\">{}</div>\
+         <div style=\"font-size:20px;font-weight:bold;\">{}</div></td>\n",
+        label,
+        escape_html(value)
+    )
+}
+
+fn section_start(html: &mut String, title: &str, headers: &[&str]) {
+    let _ = write!(
+        html,
+        "<tr><td style=\"padding:16px 24px 8px;\"><h2 style=\"margin:0 0 8px;font-size:16px;\">{}</h2>\n\
+         <table role=\"presentation\" width=\"100%\" cellpadding=\"0\" cellspacing=\"0\">\n<tr>",
+        title
+    );
+    for header in headers {
+        let _ = write!(html, "<th style=\"{HEADER_CELL}\">{}</th>", header);
+    }
+    html.push_str("</tr>\n");
+}
+
+fn section_end(html: &mut String) {
+    html.push_str("</table>\n</td></tr>\n");
+}
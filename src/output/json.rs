@@ -0,0 +1,860 @@
+use super::text::{self, TreeGlyphs};
+use crate::authors::{AuthorLines, Authorship};
+use crate::category::Category;
+use crate::churn::{Churn, FileChurn};
+use crate::comments::LineCounts;
+use crate::detect::{self, Detection};
+use crate::language::{self, language};
+use crate::report::{
+    wasted_bytes, DuplicateGroup, FileContent, FileEntry, Project, Report, Text, TreeNode,
+    Truncation,
+};
+use crate::sfc::Section;
+use crate::spool::Spool;
+use clap::ValueEnum;
+use encoding_rs::Encoding;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JsonLayout {
+    /// One document with the tree, statistics and files
+    Nested,
+    /// An array of self-contained per-file records
+    Flat,
+}
+
+/// Version of the structure of JSON reports, written as `schema_version`.
+/// Raised whenever a field is removed, renamed or changes meaning; adding
+/// fields does not change it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A codetree JSON report.
+#[derive(Serialize, JsonSchema)]
+#[schemars(title = "codetree JSON report")]
+struct JsonReport<'a> {
+    /// Version of the report structure. It is raised whenever a field is
+    /// removed, renamed or changes meaning, but not when fields are added.
+    schema_version: u32,
+    project_name: &'a str,
+    /// Why the scan stopped early, or `null` for a complete report.
+    partial: Option<String>,
+    /// Project ecosystems detected in the root.
+    ecosystems: Vec<JsonEcosystem<'a>>,
+    /// The tree as rendered in the text report, for humans.
+    file_tree: String,
+    /// The same tree as nested nodes, for programs.
+    tree: JsonNode<'a>,
+    statistics: JsonStatistics<'a>,
+    /// Groups of files with identical content.
+    duplicates: Vec<JsonDuplicateGroup<'a>>,
+    files: Vec<JsonFile<'a>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "Ecosystem")]
+struct JsonEcosystem<'a> {
+    name: &'static str,
+    category: &'static str,
+    frameworks: &'a [&'static str],
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "DuplicateGroup")]
+struct JsonDuplicateGroup<'a> {
+    sha256: String,
+    size: u64,
+    wasted_bytes: u64,
+    paths: Vec<&'a Path>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "Node")]
+struct JsonNode<'a> {
+    name: &'a str,
+    path: &'a Path,
+    #[serde(rename = "type")]
+    kind: NodeKind,
+    size: u64,
+    /// Where a symlink that was not followed points.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<JsonNode<'a>>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "NodeType")]
+#[serde(rename_all = "lowercase")]
+enum NodeKind {
+    Directory,
+    File,
+    Symlink,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "Statistics")]
+struct JsonStatistics<'a> {
+    total_files: usize,
+    total_lines: usize,
+    total_bytes: u64,
+    /// Estimated tokens, to judge whether the project fits in a model's
+    /// context window.
+    total_tokens: usize,
+    /// Secrets in file contents replaced by `[REDACTED:kind]` markers.
+    redactions: usize,
+    /// Files whose content was omitted as binary data.
+    binary_files: usize,
+    duplicate_wasted_bytes: u64,
+    /// Files, lines and bytes per category, in a fixed order.
+    categories: Vec<JsonCategory>,
+    /// Files, lines and estimated tokens per lowercased file extension,
+    /// keyed by the extension. Files without one are totalled under `""`.
+    extensions: BTreeMap<String, JsonExtension>,
+    /// Files, lines and bytes of each project scanned into a combined
+    /// report and of each member of a detected workspace. Omitted when
+    /// there are none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    projects: Vec<JsonProject<'a>>,
+    /// Files and lines attributed to each author, most lines first. Only
+    /// present with `--authors`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonAuthor<'a>>,
+    /// Authors of each top-level directory. Only present with `--authors`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ownership: Vec<JsonOwnership<'a>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "AuthorTotals")]
+struct JsonAuthor<'a> {
+    name: &'a str,
+    /// Files with at least one line by the author.
+    files: usize,
+    lines: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "Ownership")]
+struct JsonOwnership<'a> {
+    directory: &'a Path,
+    /// Lines attributed to any author.
+    lines: usize,
+    /// The fewest authors who together wrote more than half of the lines.
+    bus_factor: usize,
+    /// Lines per author, most first.
+    authors: Vec<AuthorLines>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "ProjectTotals")]
+struct JsonProject<'a> {
+    /// Directory of the project, relative to the root.
+    path: &'a Path,
+    /// Project ecosystems detected in that directory.
+    ecosystems: Vec<JsonEcosystem<'a>>,
+    files: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "CategoryTotals")]
+struct JsonCategory {
+    category: Category,
+    files: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "ExtensionTotals")]
+struct JsonExtension {
+    files: usize,
+    lines: usize,
+    tokens: usize,
+}
+
+/// An included file.
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "File")]
+struct JsonFile<'a> {
+    path: &'a Path,
+    /// Language from the path or, for scripts, from the `#!` line.
+    language: Option<&'static str>,
+    size: u64,
+    lines: usize,
+    /// The lines split into code, comments and blanks.
+    #[serde(flatten)]
+    counts: LineCounts,
+    tokens: usize,
+    status: FileStatus,
+    /// Encoding the content was decoded from, such as `UTF-8` or
+    /// `windows-1252`.
+    encoding: Option<&'static str>,
+    /// Secrets replaced by `[REDACTED:kind]` markers in `content`.
+    redactions: usize,
+    /// Hex-encoded SHA-256 of `content`, whether or not it is included.
+    sha256: Option<&'a str>,
+    /// Lines in other languages embedded in the file, such as the blocks
+    /// of a Vue component. Omitted when there are none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<JsonSection>,
+    /// How often the file changed in the git history. Only present with
+    /// `--hotspots`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<FileChurn>,
+    /// Lines last changed by each author, most first. Only present with
+    /// `--authors`, for files git has history of.
+    #[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+    authors: &'a [AuthorLines],
+    /// The text of the file, or `null` when it was not read or is withheld.
+    #[schemars(with = "Option<String>")]
+    content: Option<Content<'a>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "Section")]
+struct JsonSection {
+    language: &'static str,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum FileStatus {
+    Ok,
+    #[serde(rename = "too_large")]
+    TooLarge,
+    Binary,
+    Unreadable,
+    Missing,
+}
+
+/// A file with everything needed to analyze it on its own, for loading into
+/// dataframes and SQL engines.
+#[derive(Serialize)]
+struct FlatRecord<'a> {
+    project_name: &'a str,
+    path: &'a Path,
+    directory: Option<&'a Path>,
+    name: String,
+    extension: Option<String>,
+    language: Option<&'static str>,
+    category: Category,
+    size: u64,
+    lines: usize,
+    #[serde(flatten)]
+    counts: LineCounts,
+    tokens: usize,
+    status: FileStatus,
+    encoding: Option<&'static str>,
+    redactions: usize,
+    sha256: Option<&'a str>,
+    /// Number of included files with this exact content, including this one.
+    copies: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<FileChurn>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    authors: &'a [AuthorLines],
+    content: Option<Content<'a>>,
+}
+
+/// A file's text, read back from the spool only as it is serialized so that
+/// a single file is held in memory at a time. Withheld text becomes `null`.
+struct Content<'a> {
+    report: &'a Report,
+    text: &'a Text,
+}
+
+impl Serialize for Content<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let content = self
+            .report
+            .read(self.text)
+            .map_err(serde::ser::Error::custom)?;
+        content.serialize(serializer)
+    }
+}
+
+/// Writes the report to `out` as a single pretty-printed JSON document, with
+/// `file_tree` drawn using `glyphs`.
+pub fn write(report: &Report, glyphs: &TreeGlyphs, out: &mut impl Write) -> io::Result<()> {
+    let duplicates = report.duplicate_groups();
+    let json = JsonReport {
+        schema_version: SCHEMA_VERSION,
+        project_name: &report.project_name,
+        partial: report.truncated.map(|truncation| truncation.to_string()),
+        ecosystems: ecosystems(&report.ecosystems),
+        file_tree: text::tree(&report.root, glyphs),
+        tree: node(&report.root),
+        statistics: statistics(report, &duplicates),
+        files: report
+            .files
+            .iter()
+            .map(|entry| file(report, entry))
+            .collect(),
+        duplicates: self::duplicates(duplicates),
+    };
+    serde_json::to_writer_pretty(out, &json).map_err(io::Error::from)
+}
+
+fn ecosystems(detections: &[Detection]) -> Vec<JsonEcosystem<'_>> {
+    detections
+        .iter()
+        .map(|detection| JsonEcosystem {
+            name: detection.ecosystem.name,
+            category: detection.ecosystem.category,
+            frameworks: &detection.frameworks,
+        })
+        .collect()
+}
+
+fn statistics<'a>(report: &'a Report, duplicates: &[DuplicateGroup]) -> JsonStatistics<'a> {
+    JsonStatistics {
+        total_files: report.files.len(),
+        total_lines: report.total_lines(),
+        total_bytes: report.total_bytes(),
+        total_tokens: report.total_tokens(),
+        redactions: report.total_redactions(),
+        binary_files: report.binary_files(),
+        duplicate_wasted_bytes: wasted_bytes(duplicates),
+        categories: report
+            .category_totals()
+            .into_iter()
+            .map(|(category, totals)| JsonCategory {
+                category,
+                files: totals.files,
+                lines: totals.lines,
+                bytes: totals.bytes,
+            })
+            .collect(),
+        extensions: report
+            .extension_totals()
+            .into_iter()
+            .map(|(extension, totals)| {
+                let totals = JsonExtension {
+                    files: totals.files,
+                    lines: totals.lines,
+                    tokens: totals.tokens,
+                };
+                (extension, totals)
+            })
+            .collect(),
+        projects: report
+            .project_totals()
+            .into_iter()
+            .map(|(project, totals)| JsonProject {
+                path: &project.path,
+                ecosystems: ecosystems(&project.ecosystems),
+                files: totals.files,
+                lines: totals.lines,
+                bytes: totals.bytes,
+            })
+            .collect(),
+        authors: report
+            .author_totals()
+            .into_iter()
+            .map(|author| JsonAuthor {
+                name: author.name,
+                files: author.files,
+                lines: author.lines,
+            })
+            .collect(),
+        ownership: report
+            .ownership()
+            .into_iter()
+            .map(|directory| JsonOwnership {
+                directory: directory.directory,
+                lines: directory.lines,
+                bus_factor: directory.bus_factor,
+                authors: directory
+                    .authors
+                    .into_iter()
+                    .map(|(name, lines)| AuthorLines {
+                        name: name.to_string(),
+                        lines,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn duplicates(groups: Vec<DuplicateGroup>) -> Vec<JsonDuplicateGroup> {
+    groups
+        .into_iter()
+        .map(|group| JsonDuplicateGroup {
+            wasted_bytes: group.wasted_bytes(),
+            sha256: group.sha256,
+            size: group.size,
+            paths: group.paths,
+        })
+        .collect()
+}
+
+fn node(node: &TreeNode) -> JsonNode<'_> {
+    JsonNode {
+        name: &node.name,
+        path: &node.path,
+        kind: if node.is_dir {
+            NodeKind::Directory
+        } else if node.link.is_some() {
+            NodeKind::Symlink
+        } else {
+            NodeKind::File
+        },
+        size: node.size,
+        target: node.link.as_deref(),
+        children: node
+            .is_dir
+            .then(|| node.children.iter().map(self::node).collect()),
+    }
+}
+
+fn file<'a>(report: &'a Report, file: &'a FileEntry) -> JsonFile<'a> {
+    let (status, content) = match &file.content {
+        FileContent::Text(text) if text.too_large => (FileStatus::TooLarge, None),
+        FileContent::Text(text) => (FileStatus::Ok, Some(Content { report, text })),
+        FileContent::Binary => (FileStatus::Binary, None),
+        FileContent::Unreadable => (FileStatus::Unreadable, None),
+        FileContent::Missing => (FileStatus::Missing, None),
+    };
+    let sections = match &file.content {
+        FileContent::Text(text) => &text.sections[..],
+        _ => &[],
+    };
+    JsonFile {
+        path: &file.path,
+        language: file.language(),
+        size: file.size,
+        lines: file.line_count(),
+        counts: file.line_counts(),
+        tokens: file.token_count(),
+        status,
+        encoding: file.encoding(),
+        redactions: file.redactions(),
+        sha256: file.sha256(),
+        sections: sections
+            .iter()
+            .map(|section| JsonSection {
+                language: section.language,
+                lines: section.lines,
+                bytes: section.bytes,
+            })
+            .collect(),
+        churn: report
+            .churn
+            .as_ref()
+            .map(|churn| churn.get(&file.path).unwrap_or_default()),
+        authors: report
+            .authors
+            .as_ref()
+            .map_or(&[], |authors| authors.get(&file.path)),
+        content,
+    }
+}
+
+/// Writes the JSON Schema of the reports written by [`write`] to `out`.
+pub fn write_schema(out: &mut impl Write) -> io::Result<()> {
+    let schema = schemars::schema_for!(JsonReport);
+    serde_json::to_writer_pretty(&mut *out, &schema)?;
+    out.write_all(b"\n")
+}
+
+/// Writes the report to `out` as a JSON array of self-contained records,
+/// one per file.
+pub fn write_flat(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let copies = report.identical_copies();
+    let records: Vec<_> = report
+        .files
+        .iter()
+        .map(|entry| flat_record(report, entry, &copies))
+        .collect();
+    serde_json::to_writer_pretty(out, &records).map_err(io::Error::from)
+}
+
+fn flat_record<'a>(
+    report: &'a Report,
+    entry: &'a FileEntry,
+    copies: &HashMap<&Path, usize>,
+) -> FlatRecord<'a> {
+    let JsonFile {
+        path,
+        language,
+        size,
+        lines,
+        counts,
+        tokens,
+        status,
+        encoding,
+        redactions,
+        sha256,
+        sections: _,
+        churn,
+        authors,
+        content,
+    } = file(report, entry);
+    FlatRecord {
+        project_name: &report.project_name,
+        path,
+        directory: path.parent(),
+        name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        extension: path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string()),
+        language,
+        category: entry.category(),
+        size,
+        lines,
+        counts,
+        tokens,
+        status,
+        encoding,
+        redactions,
+        sha256,
+        copies: copies.get(path).copied().unwrap_or(1),
+        churn,
+        authors,
+        content,
+    }
+}
+
+/// One line of an NDJSON report.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum NdjsonRecord<'a> {
+    /// Written first, before any file is read back from the spool.
+    Header {
+        /// Version of the report structure, see [`SCHEMA_VERSION`].
+        schema_version: u32,
+        project_name: &'a str,
+        /// Why the scan stopped early, or `null` for a complete report.
+        partial: Option<String>,
+        ecosystems: Vec<JsonEcosystem<'a>>,
+    },
+    File(FlatRecord<'a>),
+    /// Written last, once every file has been.
+    Summary {
+        statistics: JsonStatistics<'a>,
+        duplicates: Vec<JsonDuplicateGroup<'a>>,
+    },
+}
+
+/// Writes the report to `out` as newline-delimited JSON: a header record,
+/// one self-contained record per file, as in the flat layout, and a summary
+/// record with the statistics and identical files. Every line can be parsed
+/// on its own, so consumers can process the report as a stream.
+pub fn write_ndjson(report: &Report, out: &mut impl Write) -> io::Result<()> {
+    let mut write_line = |record: &NdjsonRecord| -> io::Result<()> {
+        serde_json::to_writer(&mut *out, record)?;
+        out.write_all(b"\n")
+    };
+
+    write_line(&NdjsonRecord::Header {
+        schema_version: SCHEMA_VERSION,
+        project_name: &report.project_name,
+        partial: report.truncated.map(|truncation| truncation.to_string()),
+        ecosystems: ecosystems(&report.ecosystems),
+    })?;
+    let copies = report.identical_copies();
+    for entry in &report.files {
+        write_line(&NdjsonRecord::File(flat_record(report, entry, &copies)))?;
+    }
+    let duplicates = report.duplicate_groups();
+    write_line(&NdjsonRecord::Summary {
+        statistics: statistics(report, &duplicates),
+        duplicates: self::duplicates(duplicates),
+    })
+}
+
+/// The parts of a previously written nested JSON report needed to compare
+/// it against a new scan.
+#[derive(Deserialize)]
+pub struct Snapshot {
+    pub statistics: SnapshotStatistics,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotStatistics {
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub total_bytes: u64,
+    /// Missing from reports written before duplicate detection.
+    #[serde(default)]
+    pub duplicate_wasted_bytes: u64,
+    /// Missing from reports written before token estimates.
+    #[serde(default)]
+    pub total_tokens: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SnapshotFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub lines: usize,
+    /// Missing for binary and unreadable files, and from reports written
+    /// before content hashes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl SnapshotFile {
+    /// Whether `other`, the same file in another report, has different
+    /// content: by hash when both have one, or else by size and lines.
+    pub fn differs_from(&self, other: &SnapshotFile) -> bool {
+        match (&self.sha256, &other.sha256) {
+            (Some(hash), Some(other_hash)) => hash != other_hash,
+            _ => self.size != other.size || self.lines != other.lines,
+        }
+    }
+}
+
+impl Snapshot {
+    /// Reads a report written with `--format json`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a codetree JSON report: {}", path.display(), err),
+            )
+        })
+    }
+}
+
+/// A report written with `--format json`, as read back by [`load`].
+#[derive(Deserialize)]
+struct Stored {
+    schema_version: Option<u32>,
+    project_name: String,
+    partial: Option<String>,
+    ecosystems: Vec<StoredEcosystem>,
+    tree: StoredNode,
+    statistics: StoredStatistics,
+    files: Vec<StoredFile>,
+}
+
+#[derive(Deserialize)]
+struct StoredStatistics {
+    #[serde(default)]
+    projects: Vec<StoredProject>,
+}
+
+#[derive(Deserialize)]
+struct StoredProject {
+    path: PathBuf,
+    #[serde(default)]
+    ecosystems: Vec<StoredEcosystem>,
+}
+
+#[derive(Deserialize)]
+struct StoredEcosystem {
+    name: String,
+    frameworks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct StoredNode {
+    name: String,
+    path: PathBuf,
+    #[serde(rename = "type")]
+    kind: NodeKind,
+    size: u64,
+    target: Option<PathBuf>,
+    children: Option<Vec<StoredNode>>,
+}
+
+#[derive(Deserialize)]
+struct StoredFile {
+    path: PathBuf,
+    language: Option<String>,
+    size: u64,
+    lines: usize,
+    #[serde(flatten)]
+    counts: LineCounts,
+    tokens: usize,
+    status: FileStatus,
+    encoding: Option<String>,
+    redactions: usize,
+    sha256: Option<String>,
+    #[serde(default)]
+    sections: Vec<StoredSection>,
+    churn: Option<FileChurn>,
+    #[serde(default)]
+    authors: Vec<AuthorLines>,
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StoredSection {
+    language: String,
+    lines: usize,
+    bytes: u64,
+}
+
+/// Reads a report written with `--format json` back into a [`Report`], so
+/// it can be rendered in another format without scanning the project
+/// again. File contents are spooled as in a scan. Excluded paths are not
+/// part of JSON reports, so the loaded report has none.
+pub fn load(path: &Path) -> io::Result<Report> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), message),
+        )
+    };
+    let json = fs::read_to_string(path)?;
+    let mut stored: Stored = serde_json::from_str(&json)
+        .map_err(|err| invalid(format!("not a nested codetree JSON report: {}", err)))?;
+    match stored.schema_version {
+        Some(SCHEMA_VERSION) => {}
+        Some(version) => {
+            return Err(invalid(format!(
+                "written with schema version {}, but this codetree reads version {}",
+                version, SCHEMA_VERSION
+            )))
+        }
+        None => {
+            return Err(invalid(
+                "written by an older codetree; scan the project again".to_string(),
+            ))
+        }
+    }
+
+    let mut spool = Spool::new()?;
+    let mut churn: Option<Churn> = None;
+    let mut authors: Option<Authorship> = None;
+    for file in &mut stored.files {
+        if let Some(changes) = file.churn {
+            churn
+                .get_or_insert_with(Churn::default)
+                .insert(file.path.clone(), changes);
+        }
+        if !file.authors.is_empty() {
+            authors
+                .get_or_insert_with(Authorship::default)
+                .insert(file.path.clone(), std::mem::take(&mut file.authors));
+        }
+    }
+    let files = stored
+        .files
+        .into_iter()
+        .map(|file| stored_file(file, &mut spool))
+        .collect::<io::Result<_>>()?;
+    let ecosystems = stored_ecosystems(&stored.ecosystems);
+    Ok(Report {
+        project_name: stored.project_name,
+        root: stored_node(stored.tree),
+        files,
+        spool,
+        ecosystems,
+        churn,
+        authors,
+        truncated: stored
+            .partial
+            .and_then(|partial| partial.parse::<Truncation>().ok()),
+        excluded: Vec::new(),
+        projects: stored
+            .statistics
+            .projects
+            .into_iter()
+            .map(|project| Project {
+                path: project.path,
+                ecosystems: stored_ecosystems(&project.ecosystems),
+            })
+            .collect(),
+    })
+}
+
+fn stored_ecosystems(ecosystems: &[StoredEcosystem]) -> Vec<Detection> {
+    ecosystems
+        .iter()
+        .filter_map(|ecosystem| detect::by_name(&ecosystem.name, &ecosystem.frameworks))
+        .collect()
+}
+
+fn stored_node(node: StoredNode) -> TreeNode {
+    TreeNode {
+        name: node.name,
+        path: node.path,
+        is_dir: matches!(node.kind, NodeKind::Directory),
+        size: node.size,
+        children: node
+            .children
+            .unwrap_or_default()
+            .into_iter()
+            .map(stored_node)
+            .collect(),
+        link: node.target,
+    }
+}
+
+fn stored_file(file: StoredFile, spool: &mut Spool) -> io::Result<FileEntry> {
+    let too_large = match file.status {
+        FileStatus::Ok => false,
+        FileStatus::TooLarge => true,
+        FileStatus::Binary => return Ok(entry(file, FileContent::Binary)),
+        FileStatus::Unreadable => return Ok(entry(file, FileContent::Unreadable)),
+        FileStatus::Missing => return Ok(entry(file, FileContent::Missing)),
+    };
+    let sha256 = match (&file.sha256, &file.content) {
+        (Some(sha256), _) => sha256.clone(),
+        (None, Some(content)) => format!("{:x}", Sha256::digest(content)),
+        (None, None) => String::new(),
+    };
+    let text = Text {
+        lines: file.lines,
+        counts: file.counts,
+        tokens: file.tokens,
+        // Only a language the path does not tell came from a `#!` line.
+        script: file
+            .language
+            .as_deref()
+            .filter(|_| language(&file.path).is_none())
+            .and_then(language::by_name),
+        encoding: file
+            .encoding
+            .as_deref()
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .map_or("UTF-8", |encoding| encoding.name()),
+        redactions: file.redactions,
+        sha256,
+        sections: file
+            .sections
+            .iter()
+            .filter_map(|section| {
+                Some(Section {
+                    language: language::by_name(&section.language)?,
+                    lines: section.lines,
+                    bytes: section.bytes,
+                })
+            })
+            .collect(),
+        spooled: match &file.content {
+            Some(content) if !too_large => Some(spool.append(content)?),
+            _ => None,
+        },
+        too_large,
+    };
+    Ok(entry(file, FileContent::Text(text)))
+}
+
+fn entry(file: StoredFile, content: FileContent) -> FileEntry {
+    FileEntry {
+        path: file.path,
+        size: file.size,
+        content,
+    }
+}
@@ -0,0 +1,290 @@
+use super::escape_html;
+use super::text::{self, TreeGlyphs};
+use crate::report::{FileContent, FileEntry, Report};
+use crate::tokens;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+const CHUNK_END: &str = "</chunk>\n";
+const FILE_END: &str = "</file>\n";
+
+/// A piece of the export, which is never split across chunks: the tree, a
+/// note standing in for a file's content, or all or part of a file.
+struct Piece {
+    kind: Kind,
+    /// Estimated tokens of the piece, including its tags.
+    tokens: usize,
+}
+
+enum Kind {
+    Tree,
+    /// A file without content to show, with the attributes saying why.
+    Note {
+        file: usize,
+        attributes: String,
+    },
+    /// Bytes `range` of a file's content, part `part` of `parts`, with
+    /// `tokens` estimated for the content alone.
+    Part {
+        file: usize,
+        range: Range<usize>,
+        part: usize,
+        parts: usize,
+        tokens: usize,
+    },
+}
+
+/// Writes the file tree and the contents of every file to `out`, each file
+/// delimited by `<file>` tags carrying its path and estimated token count,
+/// for pasting into an AI assistant.
+///
+/// With `max_tokens`, the export is split into numbered `<chunk>` elements
+/// of at most that many estimated tokens each; files larger than a chunk are
+/// split between lines into several parts. Without it, everything goes into
+/// a single chunk.
+pub fn write(
+    report: &Report,
+    glyphs: &TreeGlyphs,
+    max_tokens: Option<usize>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let tree = format!(
+        "<tree>\n{}</tree>\n{}",
+        text::tree(&report.root, glyphs),
+        authors(report)
+    );
+    let budget = max_tokens.map(|max| {
+        let tags = chunk_tag(&report.project_name, usize::MAX, usize::MAX, usize::MAX);
+        max.saturating_sub(tokens::estimate(&tags) + tokens::estimate(CHUNK_END))
+    });
+
+    let mut pieces = vec![Piece {
+        kind: Kind::Tree,
+        tokens: tokens::estimate(&tree),
+    }];
+    let earlier_copies = report.earlier_copies();
+    for (index, file) in report.files.iter().enumerate() {
+        let note = |attributes: String| Piece {
+            tokens: tokens::estimate(&note_tag(&file.path, &attributes)),
+            kind: Kind::Note {
+                file: index,
+                attributes,
+            },
+        };
+        if let Some(original) = earlier_copies.get(file.path.as_path()) {
+            pieces.push(note(format!(
+                " identical-to=\"{}\"",
+                escape_html(&original.display().to_string())
+            )));
+            continue;
+        }
+        match &file.content {
+            FileContent::Text(text) => match report.read(text)? {
+                Some(content) => split(index, file, &content, budget, &mut pieces),
+                None if text.too_large => pieces.push(note(format!(
+                    " status=\"too-large\" lines=\"{}\"",
+                    text.lines
+                ))),
+                None => pieces.push(note(" status=\"withheld\"".to_string())),
+            },
+            FileContent::Binary => pieces.push(note(" status=\"binary\"".to_string())),
+            FileContent::Unreadable => pieces.push(note(" status=\"unreadable\"".to_string())),
+            FileContent::Missing => pieces.push(note(" status=\"missing\"".to_string())),
+        }
+    }
+
+    let chunks = pack(&pieces, budget);
+    let mut current: Option<(usize, String)> = None;
+    for (number, chunk) in chunks.iter().enumerate() {
+        let tokens = chunk.iter().map(|piece| piece.tokens).sum();
+        let tag = chunk_tag(&report.project_name, number + 1, chunks.len(), tokens);
+        out.write_all(tag.as_bytes())?;
+        for piece in chunk {
+            match &piece.kind {
+                Kind::Tree => {
+                    out.write_all(tree.as_bytes())?;
+                    if let Some(truncation) = report.truncated {
+                        writeln!(out, "<partial>{}.</partial>", truncation)?;
+                    }
+                }
+                Kind::Note { file, attributes } => {
+                    out.write_all(note_tag(&report.files[*file].path, attributes).as_bytes())?
+                }
+                Kind::Part {
+                    file,
+                    range,
+                    part,
+                    parts,
+                    tokens,
+                } => {
+                    let entry = &report.files[*file];
+                    if current.as_ref().map(|(index, _)| index) != Some(file) {
+                        let FileContent::Text(text) = &entry.content else {
+                            unreachable!("only text files are split into parts")
+                        };
+                        current = Some((*file, report.read(text)?.unwrap_or_default()));
+                    }
+                    let content = current.as_ref().map_or("", |(_, content)| content);
+                    let content = &content[range.clone()];
+                    let tag = file_tag(&entry.path, *tokens, *part, *parts);
+                    write!(out, "{}{}", tag, content)?;
+                    if !content.ends_with('\n') {
+                        out.write_all(b"\n")?;
+                    }
+                    out.write_all(FILE_END.as_bytes())?;
+                }
+            }
+        }
+        out.write_all(CHUNK_END.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Adds the `content` of `file` to `pieces`, split between lines into parts
+/// that each fit in `budget` if it does not fit whole.
+fn split(
+    index: usize,
+    file: &FileEntry,
+    content: &str,
+    budget: Option<usize>,
+    pieces: &mut Vec<Piece>,
+) {
+    let tags = |part, parts| {
+        tokens::estimate(&file_tag(&file.path, usize::MAX, part, parts))
+            + tokens::estimate(FILE_END)
+    };
+    let whole = file.token_count();
+    let Some(budget) = budget.filter(|&budget| tags(1, 1) + whole > budget) else {
+        pieces.push(part(index, 0..content.len(), whole, tags(1, 1)));
+        return;
+    };
+
+    // Parts are numbered once the number of them is known.
+    let overhead = tags(usize::MAX, usize::MAX);
+    let first = pieces.len();
+    let (mut start, mut end, mut tokens) = (0, 0, 0);
+    for line in content.split_inclusive('\n') {
+        let line_tokens = tokens::estimate(line);
+        // A single line larger than the budget gets a part of its own.
+        if end > start && overhead + tokens + line_tokens > budget {
+            pieces.push(part(index, start..end, tokens, overhead));
+            (start, tokens) = (end, 0);
+        }
+        end += line.len();
+        tokens += line_tokens;
+    }
+    pieces.push(part(index, start..end, tokens, overhead));
+
+    let parts = pieces.len() - first;
+    for (number, piece) in pieces[first..].iter_mut().enumerate() {
+        if let Kind::Part {
+            part, parts: total, ..
+        } = &mut piece.kind
+        {
+            *part = number + 1;
+            *total = parts;
+        }
+    }
+}
+
+/// A part of a file with `tokens` of content and `overhead` tokens of tags.
+fn part(file: usize, range: Range<usize>, tokens: usize, overhead: usize) -> Piece {
+    Piece {
+        kind: Kind::Part {
+            file,
+            range,
+            part: 1,
+            parts: 1,
+            tokens,
+        },
+        tokens: overhead + tokens,
+    }
+}
+
+/// Groups `pieces` into chunks of at most `budget` tokens, in order. A piece
+/// larger than the budget on its own gets a chunk of its own.
+fn pack(pieces: &[Piece], budget: Option<usize>) -> Vec<Vec<&Piece>> {
+    let mut chunks: Vec<Vec<&Piece>> = vec![Vec::new()];
+    let mut tokens = 0;
+    for piece in pieces {
+        let chunk = chunks.last_mut().expect("there is always a chunk");
+        if budget.is_some_and(|budget| !chunk.is_empty() && tokens + piece.tokens > budget) {
+            chunks.push(vec![piece]);
+            tokens = piece.tokens;
+        } else {
+            chunk.push(piece);
+            tokens += piece.tokens;
+        }
+    }
+    chunks
+}
+
+/// The authors of the project and the ownership of each top-level
+/// directory, empty unless authors were collected.
+fn authors(report: &Report) -> String {
+    let authors = report.author_totals();
+    if authors.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("<authors>\n");
+    for author in &authors {
+        output.push_str(&format!(
+            "<author name=\"{}\" lines=\"{}\" files=\"{}\"/>\n",
+            escape_html(author.name),
+            author.lines,
+            author.files
+        ));
+    }
+    output.push_str("</authors>\n<ownership>\n");
+    for directory in report.ownership() {
+        output.push_str(&format!(
+            "<directory path=\"{}\" lines=\"{}\" bus-factor=\"{}\">\n",
+            escape_html(&directory.directory.display().to_string()),
+            directory.lines,
+            directory.bus_factor
+        ));
+        for (name, lines) in &directory.authors {
+            output.push_str(&format!(
+                "<author name=\"{}\" lines=\"{}\"/>\n",
+                escape_html(name),
+                lines
+            ));
+        }
+        output.push_str("</directory>\n");
+    }
+    output.push_str("</ownership>\n");
+    output
+}
+
+fn chunk_tag(project: &str, number: usize, chunks: usize, tokens: usize) -> String {
+    format!(
+        "<chunk project=\"{}\" number=\"{}\" of=\"{}\" tokens=\"{}\">\n",
+        escape_html(project),
+        number,
+        chunks,
+        tokens
+    )
+}
+
+fn file_tag(path: &Path, tokens: usize, part: usize, parts: usize) -> String {
+    let part = if parts > 1 {
+        format!(" part=\"{}\" of=\"{}\"", part, parts)
+    } else {
+        String::new()
+    };
+    format!(
+        "<file path=\"{}\" tokens=\"{}\"{}>\n",
+        escape_html(&path.display().to_string()),
+        tokens,
+        part
+    )
+}
+
+fn note_tag(path: &Path, attributes: &str) -> String {
+    format!(
+        "<file path=\"{}\"{}/>\n",
+        escape_html(&path.display().to_string()),
+        attributes
+    )
+}
@@ -0,0 +1,52 @@
+use super::{csv_authors, csv_field};
+use crate::report::{FileContent, Report};
+use std::fmt::Write;
+
+/// Renders one CSV row of metrics per included file, without contents,
+/// and with its authors when they were collected.
+pub fn render(report: &Report) -> String {
+    let copies = report.identical_copies();
+    let mut csv = String::from(
+        "path,extension,language,category,size,lines,tokens,status,encoding,redactions,sha256,copies",
+    );
+    if report.authors.is_some() {
+        csv.push_str(",authors");
+    }
+    csv.push('\n');
+    for file in &report.files {
+        let status = match &file.content {
+            FileContent::Text(text) if text.too_large => "too_large",
+            FileContent::Text(_) => "ok",
+            FileContent::Binary => "binary",
+            FileContent::Unreadable => "unreadable",
+            FileContent::Missing => "missing",
+        };
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&file.path.display().to_string()),
+            csv_field(
+                &file
+                    .path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy())
+                    .unwrap_or_default()
+            ),
+            csv_field(file.language().unwrap_or("")),
+            file.category(),
+            file.size,
+            file.line_count(),
+            file.token_count(),
+            status,
+            file.encoding().unwrap_or_default(),
+            file.redactions(),
+            file.sha256().unwrap_or_default(),
+            copies.get(file.path.as_path()).copied().unwrap_or(1)
+        );
+        if report.authors.is_some() {
+            let _ = write!(csv, ",{}", csv_authors(report, &file.path));
+        }
+        csv.push('\n');
+    }
+    csv
+}
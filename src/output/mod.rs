@@ -0,0 +1,230 @@
+pub mod chat;
+pub mod cloc;
+pub mod confluence;
+pub mod csv;
+pub mod email;
+pub mod json;
+pub mod llm;
+pub mod metrics_csv;
+pub mod openmetrics;
+pub mod pr_comment;
+pub mod prompt;
+pub mod sqlite;
+pub mod sunburst;
+pub mod text;
+pub mod treemap;
+pub mod wiki;
+
+use crate::report::Report;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// File tree followed by every file's contents, in codetree.txt
+    Text,
+    /// Report with the rendered and the structured tree, in codetree.json
+    Json,
+    /// Newline-delimited JSON: a header, one record per file and a summary,
+    /// in codetree.ndjson
+    Ndjson,
+    /// OpenMetrics gauges for monitoring systems, in codetree.prom
+    Openmetrics,
+    /// Short Markdown summary for a pull request comment, in codetree-pr-comment.md
+    PrComment,
+    /// Slack Block Kit message with headline statistics, in codetree-slack.json
+    Slack,
+    /// Discord Markdown message with headline statistics, in codetree-discord.md
+    Discord,
+    /// Inline-styled HTML summary for email bodies, in codetree-email.html
+    Email,
+    /// Confluence storage-format page body, in codetree-confluence.xml
+    Confluence,
+    /// One row of line counts and size per file, in codetree.csv
+    Csv,
+    /// SQLite database with statistics, files, extensions and excluded
+    /// tables, in codetree.db
+    Sqlite,
+    /// Per-language code, comment and blank lines shaped like `cloc --json`,
+    /// in codetree-cloc.json
+    ClocJson,
+    /// File contents between per-file delimiters with estimated token
+    /// counts, in numbered chunks for AI assistants, in codetree-llm.txt
+    Llm,
+    /// Squarified treemap of file sizes as a standalone SVG, in codetree-treemap.svg
+    TreemapSvg,
+    /// Sunburst of the directory hierarchy weighted by lines, in codetree-sunburst.svg
+    SunburstSvg,
+    /// GitHub wiki pages (Home.md plus one page per directory) in codetree-wiki/
+    Wiki,
+}
+
+impl OutputFormat {
+    /// Name of the file or directory the report is written to inside the
+    /// analyzed project.
+    pub fn output_name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "codetree.txt",
+            OutputFormat::Json => "codetree.json",
+            OutputFormat::Ndjson => "codetree.ndjson",
+            OutputFormat::Openmetrics => "codetree.prom",
+            OutputFormat::PrComment => "codetree-pr-comment.md",
+            OutputFormat::Slack => "codetree-slack.json",
+            OutputFormat::Discord => "codetree-discord.md",
+            OutputFormat::Email => "codetree-email.html",
+            OutputFormat::Confluence => "codetree-confluence.xml",
+            OutputFormat::Csv => "codetree.csv",
+            OutputFormat::Sqlite => "codetree.db",
+            OutputFormat::ClocJson => "codetree-cloc.json",
+            OutputFormat::Llm => "codetree-llm.txt",
+            OutputFormat::TreemapSvg => "codetree-treemap.svg",
+            OutputFormat::SunburstSvg => "codetree-sunburst.svg",
+            OutputFormat::Wiki => "codetree-wiki",
+        }
+    }
+
+    /// Whether the report includes the contents of files, rather than only
+    /// statistics derived from them.
+    pub fn shows_contents(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Text
+                | OutputFormat::Json
+                | OutputFormat::Ndjson
+                | OutputFormat::Confluence
+                | OutputFormat::Llm
+                | OutputFormat::Wiki
+        )
+    }
+
+    /// The media type the report is served with by `codetree serve`, for
+    /// viewing in a browser: HTML, SVG and JSON as such, everything else as
+    /// plain text rather than a download.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::Email => "text/html; charset=utf-8",
+            OutputFormat::TreemapSvg | OutputFormat::SunburstSvg => "image/svg+xml",
+            OutputFormat::Json | OutputFormat::Slack | OutputFormat::ClocJson => "application/json",
+            OutputFormat::Sqlite | OutputFormat::Wiki => "application/octet-stream",
+            _ => "text/plain; charset=utf-8",
+        }
+    }
+
+    /// Whether `name` is the output of any format, so reports never end up
+    /// inside later scans.
+    pub fn is_output_name(name: &str) -> bool {
+        Self::value_variants()
+            .iter()
+            .any(|format| format.output_name() == name)
+    }
+}
+
+/// Returns a backtick fence long enough that `content` cannot close it.
+pub fn code_fence(content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        if c == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 KB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Parses a size such as `500`, `200KB` or `1.5 MB`, in the units of
+/// [`format_size`], into bytes.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let digits = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size \"{}\"", text))?;
+    let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" => 1,
+        "M" | "MB" => 2,
+        "G" | "GB" => 3,
+        unit => {
+            return Err(format!(
+                "unknown size unit \"{}\", expected B, KB, MB or GB",
+                unit
+            ))
+        }
+    };
+    Ok((number * 1024f64.powi(exponent)) as u64)
+}
+
+/// Escapes text for inclusion in HTML or XML content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The authors of the file at `path` as a CSV field, `name:lines` pairs
+/// separated by `;`, most lines first.
+pub fn csv_authors(report: &Report, path: &Path) -> String {
+    let authors: Vec<_> = report
+        .authors
+        .iter()
+        .flat_map(|authorship| authorship.get(path))
+        .map(|author| format!("{}:{}", author.name, author.lines))
+        .collect();
+    csv_field(&authors.join(";"))
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_with_separators_or_line_breaks_are_quoted() {
+        assert_eq!(csv_field("src/main.rs"), "src/main.rs");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::report::{wasted_bytes, Report};
+use std::fmt::Write;
+
+/// Renders the report's totals as OpenMetrics gauges, for scraping into
+/// monitoring systems that track codebase growth.
+pub fn render(report: &Report) -> String {
+    let project = escape(&report.project_name);
+
+    let mut languages = report.language_totals();
+    languages.sort_by_key(|(language, _)| *language);
+
+    let mut output = String::new();
+    // Each sample has an optional extra label, such as `language="rust"`.
+    let mut gauge = |name: &str, help: &str, samples: Vec<(Option<(&str, &str)>, String)>| {
+        let _ = writeln!(output, "# TYPE {} gauge", name);
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        for (label, value) in samples {
+            match label {
+                Some((key, label)) => {
+                    let _ = writeln!(
+                        output,
+                        "{}{{project=\"{}\",{}=\"{}\"}} {}",
+                        name,
+                        project,
+                        key,
+                        escape(label),
+                        value
+                    );
+                }
+                None => {
+                    let _ = writeln!(output, "{}{{project=\"{}\"}} {}", name, project, value);
+                }
+            }
+        }
+    };
+
+    gauge(
+        "codetree_partial",
+        "1 if the scan stopped early and the totals cover part of the project.",
+        vec![(None, u8::from(report.truncated.is_some()).to_string())],
+    );
+    gauge(
+        "codetree_total_files",
+        "Number of files included in the report.",
+        vec![(None, report.files.len().to_string())],
+    );
+    gauge(
+        "codetree_total_lines",
+        "Lines across all included files.",
+        vec![(None, report.total_lines().to_string())],
+    );
+    gauge(
+        "codetree_total_tokens",
+        "Estimated tokens across all included files.",
+        vec![(None, report.total_tokens().to_string())],
+    );
+    gauge(
+        "codetree_total_bytes",
+        "Size in bytes of all included files.",
+        vec![(None, report.total_bytes().to_string())],
+    );
+    gauge(
+        "codetree_binary_files",
+        "Number of included files holding binary data, counted by size only.",
+        vec![(None, report.binary_files().to_string())],
+    );
+    let duplicates = report.duplicate_groups();
+    gauge(
+        "codetree_duplicate_groups",
+        "Groups of included files with identical content.",
+        vec![(None, duplicates.len().to_string())],
+    );
+    gauge(
+        "codetree_duplicate_wasted_bytes",
+        "Bytes taken up by redundant copies of identical files.",
+        vec![(None, wasted_bytes(&duplicates).to_string())],
+    );
+    gauge(
+        "codetree_files",
+        "Number of included files per language.",
+        languages
+            .iter()
+            .map(|(language, totals)| (Some(("language", *language)), totals.files.to_string()))
+            .collect(),
+    );
+    gauge(
+        "codetree_lines",
+        "Lines per language.",
+        languages
+            .iter()
+            .map(|(language, totals)| (Some(("language", *language)), totals.lines.to_string()))
+            .collect(),
+    );
+    gauge(
+        "codetree_bytes",
+        "Size in bytes per language.",
+        languages
+            .iter()
+            .map(|(language, totals)| (Some(("language", *language)), totals.bytes.to_string()))
+            .collect(),
+    );
+
+    let categories: Vec<_> = report
+        .category_totals()
+        .into_iter()
+        .map(|(category, totals)| (category.to_string(), totals))
+        .collect();
+    gauge(
+        "codetree_category_lines",
+        "Lines per category (code, tests, docs, config, data).",
+        categories
+            .iter()
+            .map(|(category, totals)| {
+                (
+                    Some(("category", category.as_str())),
+                    totals.lines.to_string(),
+                )
+            })
+            .collect(),
+    );
+    gauge(
+        "codetree_category_bytes",
+        "Size in bytes per category (code, tests, docs, config, data).",
+        categories
+            .iter()
+            .map(|(category, totals)| {
+                (
+                    Some(("category", category.as_str())),
+                    totals.bytes.to_string(),
+                )
+            })
+            .collect(),
+    );
+
+    let authors = report.author_totals();
+    if !authors.is_empty() {
+        gauge(
+            "codetree_author_lines",
+            "Lines last changed by each author, according to git blame.",
+            authors
+                .iter()
+                .map(|author| (Some(("author", author.name)), author.lines.to_string()))
+                .collect(),
+        );
+        let ownership = report.ownership();
+        let directories: Vec<_> = ownership
+            .iter()
+            .map(|directory| (directory.directory.to_string_lossy(), directory.bus_factor))
+            .collect();
+        gauge(
+            "codetree_bus_factor",
+            "Fewest authors who together wrote more than half of the lines of each top-level directory.",
+            directories
+                .iter()
+                .map(|(directory, bus_factor)| {
+                    (Some(("directory", directory.as_ref())), bus_factor.to_string())
+                })
+                .collect(),
+        );
+    }
+
+    output.push_str("# EOF\n");
+    output
+}
+
+/// Escapes a label value as required by the OpenMetrics text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
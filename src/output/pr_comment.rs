@@ -0,0 +1,221 @@
+use super::format_size;
+use super::json::Snapshot;
+use crate::report::{wasted_bytes, Report};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Files at least this large are called out when they are new or grew past it.
+const LARGE_FILE_BYTES: u64 = 100 * 1024;
+
+/// Default number of paths listed per section, to keep the comment short.
+pub const MAX_LISTED: usize = 10;
+
+/// Renders a short Markdown summary sized for a bot's pull request comment.
+/// With a `baseline`, it shows the change in totals and calls out added,
+/// removed and newly large files. At most `top` paths are listed per section.
+pub fn render(report: &Report, baseline: Option<&Snapshot>, top: usize) -> String {
+    let mut output = format!("### Codetree report for `{}`\n\n", report.project_name);
+    if let Some(truncation) = report.truncated {
+        let _ = writeln!(output, "> **Warning:** {}.\n", truncation);
+    }
+
+    let Some(baseline) = baseline else {
+        let _ = writeln!(
+            output,
+            "{} files, {} lines, {}, about {} tokens.",
+            report.files.len(),
+            report.total_lines(),
+            format_size(report.total_bytes()),
+            report.total_tokens()
+        );
+        duplicates(&mut output, report, top);
+        authors(&mut output, report, top);
+        return output;
+    };
+
+    output.push_str("| | Baseline | Current | Change |\n|---|---:|---:|---:|\n");
+    let _ = writeln!(
+        output,
+        "| Files | {} | {} | {} |",
+        baseline.statistics.total_files,
+        report.files.len(),
+        delta(
+            baseline.statistics.total_files as i64,
+            report.files.len() as i64
+        )
+    );
+    let _ = writeln!(
+        output,
+        "| Lines | {} | {} | {} |",
+        baseline.statistics.total_lines,
+        report.total_lines(),
+        delta(
+            baseline.statistics.total_lines as i64,
+            report.total_lines() as i64
+        )
+    );
+    let _ = writeln!(
+        output,
+        "| Tokens | {} | {} | {} |",
+        baseline.statistics.total_tokens,
+        report.total_tokens(),
+        delta(
+            baseline.statistics.total_tokens as i64,
+            report.total_tokens() as i64
+        )
+    );
+    let _ = writeln!(
+        output,
+        "| Size | {} | {} | {} |",
+        format_size(baseline.statistics.total_bytes),
+        format_size(report.total_bytes()),
+        size_delta(baseline.statistics.total_bytes, report.total_bytes())
+    );
+
+    let before: HashMap<&Path, u64> = baseline
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file.size))
+        .collect();
+    let current: HashMap<&Path, u64> = report
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file.size))
+        .collect();
+
+    let mut large: Vec<_> = report
+        .files
+        .iter()
+        .filter(|file| {
+            file.size >= LARGE_FILE_BYTES
+                && before
+                    .get(file.path.as_path())
+                    .is_none_or(|&size| size < LARGE_FILE_BYTES)
+        })
+        .map(|file| (file.path.as_path(), file.size))
+        .collect();
+    large.sort_by_key(|&(_, size)| Reverse(size));
+    if !large.is_empty() {
+        let _ = writeln!(
+            output,
+            "\n**New large files** (at least {}):\n",
+            format_size(LARGE_FILE_BYTES)
+        );
+        for (path, size) in large.iter().take(top) {
+            let _ = writeln!(output, "- `{}` ({})", path.display(), format_size(*size));
+        }
+        more(&mut output, large.len(), top);
+    }
+
+    let added: Vec<_> = report
+        .files
+        .iter()
+        .map(|file| file.path.as_path())
+        .filter(|path| !before.contains_key(path))
+        .collect();
+    list_paths(&mut output, "Added files", &added, top);
+
+    let removed: Vec<_> = baseline
+        .files
+        .iter()
+        .map(|file| file.path.as_path())
+        .filter(|path| !current.contains_key(path))
+        .collect();
+    list_paths(&mut output, "Removed files", &removed, top);
+
+    duplicates(&mut output, report, top);
+    authors(&mut output, report, top);
+    output
+}
+
+fn authors(output: &mut String, report: &Report, top: usize) {
+    let authors = report.author_totals();
+    if authors.is_empty() {
+        return;
+    }
+    let total = authors
+        .iter()
+        .map(|author| author.lines)
+        .sum::<usize>()
+        .max(1) as f64;
+    output.push_str("\n**Authors**:\n\n| Author | Lines | Share | Files |\n|---|---:|---:|---:|\n");
+    for author in authors.iter().take(top) {
+        let _ = writeln!(
+            output,
+            "| {} | {} | {:.1}% | {} |",
+            author.name.replace('|', "\\|"),
+            author.lines,
+            author.lines as f64 / total * 100.0,
+            author.files
+        );
+    }
+    if authors.len() > top {
+        let _ = writeln!(output, "| ...and {} more | | | |", authors.len() - top);
+    }
+    let ownership = report.ownership();
+    if !ownership.is_empty() {
+        output.push_str("\n**Bus factor**:\n\n");
+        for directory in &ownership {
+            let _ = writeln!(
+                output,
+                "- `{}/`: {} ({} lines)",
+                directory.directory.display(),
+                directory.bus_factor,
+                directory.lines
+            );
+        }
+    }
+}
+
+fn duplicates(output: &mut String, report: &Report, top: usize) {
+    let groups = report.duplicate_groups();
+    if groups.is_empty() {
+        return;
+    }
+    let _ = writeln!(
+        output,
+        "\n**Identical files**: {} groups, {} wasted\n",
+        groups.len(),
+        format_size(wasted_bytes(&groups))
+    );
+    for group in groups.iter().take(top) {
+        let paths: Vec<_> = group
+            .paths
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+        let _ = writeln!(output, "- {}", paths.join(", "));
+    }
+    more(output, groups.len(), top);
+}
+
+fn list_paths(output: &mut String, title: &str, paths: &[&Path], top: usize) {
+    if paths.is_empty() {
+        return;
+    }
+    let _ = writeln!(output, "\n**{}** ({}):\n", title, paths.len());
+    for path in paths.iter().take(top) {
+        let _ = writeln!(output, "- `{}`", path.display());
+    }
+    more(output, paths.len(), top);
+}
+
+fn more(output: &mut String, total: usize, top: usize) {
+    if total > top {
+        let _ = writeln!(output, "- ...and {} more", total - top);
+    }
+}
+
+fn delta(before: i64, after: i64) -> String {
+    format!("{:+}", after - before)
+}
+
+fn size_delta(before: u64, after: u64) -> String {
+    if after >= before {
+        format!("+{}", format_size(after - before))
+    } else {
+        format!("-{}", format_size(before - after))
+    }
+}
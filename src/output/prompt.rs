@@ -0,0 +1,83 @@
+use super::text::{self, TreeGlyphs};
+use crate::report::Report;
+
+/// Fills the placeholders of a user-supplied prompt template:
+///
+/// - `{{project}}`: name of the analyzed directory
+/// - `{{stats}}`: file, line, byte and estimated token totals
+/// - `{{tree}}`: the file tree drawn with `glyphs`
+/// - `{{instructions}}`: the text passed with `--instructions`
+///
+/// Other `{{...}}` text is kept as written, and placeholders inside the
+/// values, such as a file named `{{tree}}`, are not expanded.
+pub fn render(template: &str, report: &Report, instructions: &str, glyphs: &TreeGlyphs) -> String {
+    substitute(template, |name| match name {
+        "project" => Some(report.project_name.clone()),
+        "stats" => Some(format!(
+            "Files: {}\nLines: {}\nSize: {} bytes\nEstimated tokens: {}",
+            report.files.len(),
+            report.total_lines(),
+            report.total_bytes(),
+            report.total_tokens()
+        )),
+        "tree" => Some(text::tree(&report.root, glyphs).trim_end().to_string()),
+        "instructions" => Some(instructions.to_string()),
+        _ => None,
+    })
+}
+
+/// Replaces each `{{name}}` in `template` for which `value` has a value, in
+/// a single pass over the template.
+fn substitute(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after
+            .find("}}")
+            .and_then(|end| Some((end, value(&after[..end])?)))
+        {
+            Some((end, value)) => {
+                output.push_str(&value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(template: &str) -> String {
+        substitute(template, |name| match name {
+            "project" => Some("{{tree}}".to_string()),
+            "tree" => Some("src/".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn known_placeholders_are_replaced() {
+        assert_eq!(fill("Tree:\n{{tree}}\nDone"), "Tree:\nsrc/\nDone");
+        assert_eq!(fill("{{tree}}{{tree}}"), "src/src/");
+    }
+
+    #[test]
+    fn values_are_not_expanded_again() {
+        assert_eq!(fill("Project {{project}}"), "Project {{tree}}");
+    }
+
+    #[test]
+    fn other_braces_are_kept() {
+        assert_eq!(fill("{{unknown}} {{tree"), "{{unknown}} {{tree");
+        assert_eq!(fill("{{{{tree}}}}"), "{{src/}}");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::comments::LineCounts;
+use crate::report::{wasted_bytes, FileContent, Report};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE statistics (
+    project TEXT NOT NULL,
+    files INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    code INTEGER NOT NULL,
+    comments INTEGER NOT NULL,
+    blanks INTEGER NOT NULL,
+    tokens INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    binary_files INTEGER NOT NULL,
+    duplicate_wasted_bytes INTEGER NOT NULL,
+    partial INTEGER NOT NULL
+);
+CREATE TABLE files (
+    path TEXT PRIMARY KEY,
+    extension TEXT NOT NULL,
+    language TEXT,
+    category TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    code INTEGER NOT NULL,
+    comments INTEGER NOT NULL,
+    blanks INTEGER NOT NULL,
+    tokens INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    redactions INTEGER NOT NULL,
+    sha256 TEXT,
+    copies INTEGER NOT NULL
+);
+CREATE TABLE extensions (
+    extension TEXT PRIMARY KEY,
+    files INTEGER NOT NULL,
+    lines INTEGER NOT NULL,
+    code INTEGER NOT NULL,
+    comments INTEGER NOT NULL,
+    blanks INTEGER NOT NULL,
+    tokens INTEGER NOT NULL,
+    size INTEGER NOT NULL
+);
+CREATE TABLE excluded (
+    path TEXT NOT NULL,
+    rule TEXT NOT NULL
+);
+CREATE TABLE authors (
+    path TEXT NOT NULL,
+    author TEXT NOT NULL,
+    lines INTEGER NOT NULL
+);
+CREATE TABLE bus_factors (
+    directory TEXT PRIMARY KEY,
+    lines INTEGER NOT NULL,
+    bus_factor INTEGER NOT NULL
+);
+";
+
+/// Writes the report to a new SQLite database at `path`, replacing any
+/// existing file, with one table each for the overall statistics, the
+/// included files, the totals per extension, the excluded paths, the lines
+/// of each file per author and the bus factor of each top-level directory.
+/// The last two are empty unless authors were collected.
+pub fn write(report: &Report, path: &Path) -> io::Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    insert(report, path).map_err(io::Error::other)
+}
+
+fn insert(report: &Report, path: &Path) -> rusqlite::Result<()> {
+    let mut db = Connection::open(path)?;
+    let tx = db.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    let mut counts = LineCounts::default();
+    let copies = report.identical_copies();
+    {
+        let mut insert_file = tx.prepare(
+            "INSERT INTO files VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        )?;
+        for file in &report.files {
+            let status = match &file.content {
+                FileContent::Text(text) if text.too_large => "too_large",
+                FileContent::Text(_) => "ok",
+                FileContent::Binary => "binary",
+                FileContent::Unreadable => "unreadable",
+                FileContent::Missing => "missing",
+            };
+            let line_counts = file.line_counts();
+            counts.add(line_counts);
+            insert_file.execute(params![
+                file.path.to_string_lossy(),
+                file.path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default(),
+                file.language(),
+                file.category().to_string(),
+                file.size,
+                file.line_count(),
+                line_counts.code,
+                line_counts.comments,
+                line_counts.blanks,
+                file.token_count(),
+                status,
+                file.redactions(),
+                file.sha256(),
+                copies.get(file.path.as_path()).copied().unwrap_or(1),
+            ])?;
+        }
+
+        let mut insert_extension =
+            tx.prepare("INSERT INTO extensions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+        for (extension, totals) in report.extension_totals() {
+            insert_extension.execute(params![
+                extension,
+                totals.files,
+                totals.lines,
+                totals.counts.code,
+                totals.counts.comments,
+                totals.counts.blanks,
+                totals.tokens,
+                totals.bytes,
+            ])?;
+        }
+
+        let mut insert_excluded = tx.prepare("INSERT INTO excluded VALUES (?1, ?2)")?;
+        for excluded in &report.excluded {
+            insert_excluded.execute(params![excluded.path.to_string_lossy(), excluded.rule])?;
+        }
+
+        if let Some(authorship) = &report.authors {
+            let mut insert_author = tx.prepare("INSERT INTO authors VALUES (?1, ?2, ?3)")?;
+            for file in &report.files {
+                for author in authorship.get(&file.path) {
+                    insert_author.execute(params![
+                        file.path.to_string_lossy(),
+                        author.name,
+                        author.lines
+                    ])?;
+                }
+            }
+        }
+        let mut insert_bus_factor = tx.prepare("INSERT INTO bus_factors VALUES (?1, ?2, ?3)")?;
+        for directory in report.ownership() {
+            insert_bus_factor.execute(params![
+                directory.directory.to_string_lossy(),
+                directory.lines,
+                directory.bus_factor
+            ])?;
+        }
+    }
+
+    tx.execute(
+        "INSERT INTO statistics VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            report.project_name,
+            report.files.len(),
+            report.total_lines(),
+            counts.code,
+            counts.comments,
+            counts.blanks,
+            report.total_tokens(),
+            report.total_bytes(),
+            report.binary_files(),
+            wasted_bytes(&report.duplicate_groups()),
+            report.truncated.is_some(),
+        ],
+    )?;
+    tx.commit()
+}
@@ -0,0 +1,202 @@
+use super::escape_html;
+use crate::report::{Report, TreeNode};
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::fmt::Write;
+use std::path::Path;
+
+const SIZE: f64 = 900.0;
+const LEGEND_WIDTH: f64 = 300.0;
+const CENTER: f64 = SIZE / 2.0;
+const RADIUS: f64 = SIZE / 2.0 - 10.0;
+/// Deeper levels are folded into their ancestors to keep rings readable.
+const MAX_RINGS: usize = 8;
+/// Arcs narrower than this (in radians) are not drawn.
+const MIN_ANGLE: f64 = 0.002;
+
+/// Renders the directory hierarchy as a sunburst: the project is the
+/// center, each ring is one level deeper, and every arc's angle is
+/// proportional to the lines of code below it.
+pub fn render(report: &Report) -> String {
+    let lines: HashMap<&Path, usize> = report
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file.line_count()))
+        .collect();
+    let copies = report.identical_copies();
+    let total = node_lines(&report.root, &lines);
+    let rings = depth(&report.root).clamp(1, MAX_RINGS);
+    let ring_width = RADIUS / (rings as f64 + 1.0);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         font-family=\"Arial, Helvetica, sans-serif\" font-size=\"12\">\n\
+         <title>Codetree sunburst: {name} ({total} lines, about {tokens} tokens)</title>\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#ffffff\"/>\n\
+         <circle cx=\"{c}\" cy=\"{c}\" r=\"{r:.1}\" fill=\"#e5e7eb\"><title>{name} ({total} lines)</title></circle>\n\
+         <text x=\"{c}\" y=\"{c}\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-weight=\"bold\">{name}</text>\n",
+        w = SIZE + LEGEND_WIDTH,
+        h = SIZE,
+        c = CENTER,
+        r = ring_width,
+        name = escape_html(&report.project_name),
+        total = total,
+        tokens = report.total_tokens()
+    );
+
+    let mut legend = Vec::new();
+    if total > 0 {
+        let mut start = 0.0;
+        let children = weighted_children(&report.root, &lines);
+        let count = children.len().max(1);
+        for (i, (child, child_lines)) in children.into_iter().enumerate() {
+            let sweep = child_lines as f64 / total as f64 * TAU;
+            let hue = i as f64 * 360.0 / count as f64;
+            legend.push((child, child_lines, hue));
+            draw(
+                child,
+                child_lines,
+                start,
+                sweep,
+                1,
+                rings,
+                ring_width,
+                hue,
+                &lines,
+                &copies,
+                &mut svg,
+            );
+            start += sweep;
+        }
+    }
+
+    for (i, (node, node_lines, hue)) in legend.iter().take(40).enumerate() {
+        let y = 30.0 + i as f64 * 20.0;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"hsl({:.0}, 65%, 55%)\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{}{} ({} lines)</text>",
+            SIZE + 10.0,
+            y - 10.0,
+            hue,
+            SIZE + 28.0,
+            y,
+            escape_html(&node.name),
+            if node.is_dir { "/" } else { "" },
+            node_lines
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw(
+    node: &TreeNode,
+    node_lines: usize,
+    start: f64,
+    sweep: f64,
+    ring: usize,
+    rings: usize,
+    ring_width: f64,
+    hue: f64,
+    lines: &HashMap<&Path, usize>,
+    copies: &HashMap<&Path, usize>,
+    svg: &mut String,
+) {
+    if sweep < MIN_ANGLE {
+        return;
+    }
+    let inner = ring as f64 * ring_width;
+    let lightness = (45.0 + ring as f64 * 6.0).min(85.0);
+    let _ = writeln!(
+        svg,
+        "<path d=\"{}\" fill=\"hsl({:.0}, 65%, {:.0}%)\" stroke=\"#ffffff\" stroke-width=\"1\"><title>{}{} ({} lines{})</title></path>",
+        sector(inner, inner + ring_width, start, sweep),
+        hue,
+        lightness,
+        escape_html(&node.path.display().to_string()),
+        if node.is_dir { "/" } else { "" },
+        node_lines,
+        match copies.get(node.path.as_path()) {
+            Some(count) => format!(", one of {} identical copies", count),
+            None => String::new(),
+        }
+    );
+
+    if ring >= rings || node_lines == 0 {
+        return;
+    }
+    let mut child_start = start;
+    for (child, child_lines) in weighted_children(node, lines) {
+        let child_sweep = child_lines as f64 / node_lines as f64 * sweep;
+        draw(
+            child,
+            child_lines,
+            child_start,
+            child_sweep,
+            ring + 1,
+            rings,
+            ring_width,
+            hue,
+            lines,
+            copies,
+            svg,
+        );
+        child_start += child_sweep;
+    }
+}
+
+/// SVG path for the ring segment between radii `inner` and `outer`,
+/// starting at angle `start` (0 is 12 o'clock) and spanning `sweep`.
+fn sector(inner: f64, outer: f64, start: f64, sweep: f64) -> String {
+    // A full circle cannot be drawn as a single arc.
+    let sweep = sweep.min(TAU - 0.0001);
+    let end = start + sweep;
+    let large_arc = if sweep > TAU / 2.0 { 1 } else { 0 };
+    let point =
+        |radius: f64, angle: f64| (CENTER + radius * angle.sin(), CENTER - radius * angle.cos());
+    let (x1, y1) = point(outer, start);
+    let (x2, y2) = point(outer, end);
+    let (x3, y3) = point(inner, end);
+    let (x4, y4) = point(inner, start);
+    format!(
+        "M {:.2} {:.2} A {:.2} {:.2} 0 {} 1 {:.2} {:.2} L {:.2} {:.2} A {:.2} {:.2} 0 {} 0 {:.2} {:.2} Z",
+        x1, y1, outer, outer, large_arc, x2, y2, x3, y3, inner, inner, large_arc, x4, y4
+    )
+}
+
+/// Children with at least one line, largest first.
+fn weighted_children<'a>(
+    node: &'a TreeNode,
+    lines: &HashMap<&Path, usize>,
+) -> Vec<(&'a TreeNode, usize)> {
+    let mut children: Vec<_> = node
+        .children
+        .iter()
+        .map(|child| (child, node_lines(child, lines)))
+        .filter(|(_, lines)| *lines > 0)
+        .collect();
+    children.sort_by_key(|(_, lines)| std::cmp::Reverse(*lines));
+    children
+}
+
+fn node_lines(node: &TreeNode, lines: &HashMap<&Path, usize>) -> usize {
+    if node.is_dir {
+        node.children
+            .iter()
+            .map(|child| node_lines(child, lines))
+            .sum()
+    } else {
+        lines.get(node.path.as_path()).copied().unwrap_or(0)
+    }
+}
+
+fn depth(node: &TreeNode) -> usize {
+    node.children
+        .iter()
+        .map(|child| 1 + depth(child))
+        .max()
+        .unwrap_or(0)
+}
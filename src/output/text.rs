@@ -0,0 +1,386 @@
+use super::format_size;
+use crate::churn::RECENT_DAYS;
+use crate::comments::LineCounts;
+use crate::detect;
+use crate::report::{FileContent, Report, TreeNode};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TreeStyle {
+    /// Box-drawing characters (├── └── │)
+    Unicode,
+    /// Plain ASCII (|-- `-- |)
+    Ascii,
+    /// Indentation only, no connecting lines
+    Indent,
+}
+
+/// The strings drawn in front of tree entries: `branch` before an entry
+/// with later siblings, `last` before the final entry of a directory and
+/// `vertical` for each enclosing level.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TreeGlyphs {
+    pub branch: String,
+    pub last: String,
+    pub vertical: String,
+}
+
+impl TreeStyle {
+    pub fn glyphs(self) -> TreeGlyphs {
+        let (branch, last, vertical) = match self {
+            TreeStyle::Unicode => ("├── ", "└── ", "│   "),
+            TreeStyle::Ascii => ("|-- ", "`-- ", "|   "),
+            TreeStyle::Indent => ("", "", "    "),
+        };
+        TreeGlyphs {
+            branch: branch.to_string(),
+            last: last.to_string(),
+            vertical: vertical.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for TreeGlyphs {
+    type Err = String;
+
+    /// Parses `BRANCH,LAST,VERTICAL`, keeping any spaces in each part.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split(',').collect::<Vec<_>>()[..] {
+            [branch, last, vertical] => Ok(TreeGlyphs {
+                branch: branch.to_string(),
+                last: last.to_string(),
+                vertical: vertical.to_string(),
+            }),
+            _ => Err("expected three comma-separated glyphs: BRANCH,LAST,VERTICAL".to_string()),
+        }
+    }
+}
+
+impl TryFrom<String> for TreeGlyphs {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Writes the classic `codetree.txt` layout to `out`: the file tree, the
+/// numbered contents of every file, read back from the spool one at a
+/// time, and summary sections ranking `top` entries, or each section's
+/// default number. Files identical to an earlier one refer to it instead of
+/// repeating its content.
+pub fn write(
+    report: &Report,
+    glyphs: &TreeGlyphs,
+    top: Option<usize>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if let Some(truncation) = report.truncated {
+        writeln!(out, "{}.\n", truncation)?;
+    }
+    write!(out, "Project File Tree:\n\n{}", tree(&report.root, glyphs))?;
+
+    out.write_all(b"\nProject Codes:\n\n")?;
+    let earlier_copies = report.earlier_copies();
+    for (i, file) in report.files.iter().enumerate() {
+        writeln!(out, "{}. {}", i + 1, file.path.display())?;
+
+        if let Some(original) = earlier_copies.get(file.path.as_path()) {
+            writeln!(out, " (Identical to {})\n", original.display())?;
+            continue;
+        }
+        match &file.content {
+            FileContent::Text(text) => match report.read(text)? {
+                Some(content) => {
+                    if text.encoding != "UTF-8" {
+                        writeln!(out, " (Decoded from {})", text.encoding)?;
+                    }
+                    if text.redactions > 0 {
+                        writeln!(out, " (Secrets redacted: {})", text.redactions)?;
+                    }
+                    write!(out, "\n{}\n", content)?
+                }
+                None if text.too_large => {
+                    writeln!(out, " (File too large, {} lines omitted)", text.lines)?
+                }
+                None => out.write_all(b" (Content withheld)\n")?,
+            },
+            FileContent::Binary => out.write_all(b" (Binary file omitted)\n")?,
+            FileContent::Unreadable => out.write_all(b" (Unable to read file content)\n")?,
+            FileContent::Missing => out.write_all(b" (File not found)\n")?,
+        }
+        out.write_all(b"\n")?;
+    }
+
+    out.write_all(summary(report, top).as_bytes())
+}
+
+/// The totals followed by the summary sections of the text report, without
+/// the tree or any file contents, for a quick look at a project's size.
+/// Ranked sections list `top` entries, or their default number.
+pub fn stats(report: &Report, top: Option<usize>) -> String {
+    let mut output = String::new();
+    if let Some(truncation) = report.truncated {
+        output.push_str(&format!("{}.\n\n", truncation));
+    }
+    let mut counts = LineCounts::default();
+    for file in &report.files {
+        counts.add(file.line_counts());
+    }
+    output.push_str(&format!(
+        "Project Statistics: {}\n\nFiles: {} ({} binary)\nLines: {} ({} code, {} comments, {} blank)\nSize: {}\n\n",
+        report.project_name,
+        report.files.len(),
+        report.binary_files(),
+        report.total_lines(),
+        counts.code,
+        counts.comments,
+        counts.blanks,
+        format_size(report.total_bytes())
+    ));
+    output.push_str(&summary(report, top));
+    output
+}
+
+/// Default number of files ranked in the "Hotspots" section.
+const HOTSPOTS: usize = 10;
+
+/// Default number of authors ranked in the "Authors" section.
+const AUTHORS: usize = 10;
+
+/// Default number of authors named per directory in the "Ownership"
+/// section.
+const DIRECTORY_AUTHORS: usize = 3;
+
+/// Identical files, detected projects, hotspots and authors, ranking `top`
+/// entries or each section's default, composition and estimated tokens, in
+/// total and per extension.
+fn summary(report: &Report, top: Option<usize>) -> String {
+    let mut output = String::new();
+
+    let duplicates = report.duplicate_groups();
+    if !duplicates.is_empty() {
+        output.push_str("Identical Files:\n\n");
+        for (i, group) in duplicates.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. {} copies of {} ({} wasted)\n",
+                i + 1,
+                group.paths.len(),
+                format_size(group.size),
+                format_size(group.wasted_bytes())
+            ));
+            for path in &group.paths {
+                output.push_str(&format!("   - {}\n", path.display()));
+            }
+        }
+        output.push('\n');
+    }
+
+    if !report.ecosystems.is_empty() {
+        output.push_str("Detected Projects:\n\n");
+        for (category, detections) in detect::by_category(&report.ecosystems) {
+            let names: Vec<_> = detections.iter().map(ToString::to_string).collect();
+            output.push_str(&format!("{}: {}\n", category, names.join(", ")));
+        }
+        output.push('\n');
+    }
+
+    let projects = report.project_totals();
+    if !projects.is_empty() {
+        output.push_str("Projects:\n\n");
+        let names: Vec<_> = projects
+            .iter()
+            .map(|(project, _)| project.path.display().to_string())
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+        let (lines, bytes) = (report.total_lines().max(1), report.total_bytes().max(1));
+        for (name, (project, totals)) in names.iter().zip(&projects) {
+            output.push_str(&format!(
+                "{:<width$}{:>8} files {:>8} lines ({:>5.1}%) {:>10} ({:>5.1}%)",
+                name,
+                totals.files,
+                totals.lines,
+                totals.lines as f64 / lines as f64 * 100.0,
+                format_size(totals.bytes),
+                totals.bytes as f64 / bytes as f64 * 100.0,
+                width = width
+            ));
+            if !project.ecosystems.is_empty() {
+                let detected: Vec<_> = project.ecosystems.iter().map(ToString::to_string).collect();
+                output.push_str(&format!("  {}", detected.join(", ")));
+            }
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+
+    let hotspots = report.hotspots(top.unwrap_or(HOTSPOTS));
+    if !hotspots.is_empty() {
+        output.push_str("Hotspots:\n\n");
+        for (i, (file, churn, _)) in hotspots.iter().enumerate() {
+            output.push_str(&format!(
+                "{}. {} ({} commits, {} in the last {} days, {} lines)\n",
+                i + 1,
+                file.path.display(),
+                churn.commits,
+                churn.recent_commits,
+                RECENT_DAYS,
+                file.line_count()
+            ));
+        }
+        output.push('\n');
+    }
+
+    let authors = report.author_totals();
+    if !authors.is_empty() {
+        let (shown, per_directory) = (top.unwrap_or(AUTHORS), top.unwrap_or(DIRECTORY_AUTHORS));
+        output.push_str("Authors:\n\n");
+        let lines: usize = authors.iter().map(|author| author.lines).sum();
+        let width = authors
+            .iter()
+            .take(shown)
+            .map(|author| author.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for author in authors.iter().take(shown) {
+            output.push_str(&format!(
+                "{:<width$}{:>8} lines ({:>5.1}%) {:>6} files\n",
+                author.name,
+                author.lines,
+                author.lines as f64 / lines.max(1) as f64 * 100.0,
+                author.files,
+                width = width
+            ));
+        }
+        if authors.len() > shown {
+            output.push_str(&format!("...and {} more\n", authors.len() - shown));
+        }
+        output.push_str("\nOwnership:\n\n");
+        let ownership = report.ownership();
+        let names: Vec<_> = ownership
+            .iter()
+            .map(|directory| format!("{}/", directory.directory.display()))
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+        for (name, directory) in names.iter().zip(&ownership) {
+            let shares: Vec<_> = directory
+                .authors
+                .iter()
+                .take(per_directory)
+                .map(|(author, lines)| {
+                    format!(
+                        "{} {:.0}%",
+                        author,
+                        *lines as f64 / directory.lines.max(1) as f64 * 100.0
+                    )
+                })
+                .collect();
+            output.push_str(&format!(
+                "{:<width$}  bus factor {:>2} {:>8} lines  {}\n",
+                name,
+                directory.bus_factor,
+                directory.lines,
+                shares.join(", "),
+                width = width
+            ));
+        }
+        output.push('\n');
+    }
+
+    output.push_str("Composition:\n\n");
+    let (lines, bytes) = (report.total_lines().max(1), report.total_bytes().max(1));
+    for (category, totals) in report.category_totals() {
+        output.push_str(&format!(
+            "{:<8}{:>6} files {:>8} lines ({:>5.1}%) {:>10} ({:>5.1}%)\n",
+            category.to_string(),
+            totals.files,
+            totals.lines,
+            totals.lines as f64 / lines as f64 * 100.0,
+            format_size(totals.bytes),
+            totals.bytes as f64 / bytes as f64 * 100.0
+        ));
+    }
+
+    // Every extension in alphabetical order, or with `top` only as many,
+    // most lines first.
+    output.push_str("\nExtensions:\n\n");
+    let mut extensions: Vec<_> = report.extension_totals().into_iter().collect();
+    let hidden = match top {
+        Some(top) if extensions.len() > top => {
+            extensions.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.lines));
+            extensions.len() - top
+        }
+        _ => 0,
+    };
+    extensions.truncate(extensions.len() - hidden);
+    let names: Vec<_> = extensions
+        .iter()
+        .map(|(extension, _)| match extension.as_str() {
+            "" => "(none)".to_string(),
+            extension => format!(".{}", extension),
+        })
+        .collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    for (name, (_, totals)) in names.iter().zip(&extensions) {
+        output.push_str(&format!(
+            "{:<width$}{:>6} files {:>8} lines {:>10} tokens\n",
+            name,
+            totals.files,
+            totals.lines,
+            totals.tokens,
+            width = width
+        ));
+    }
+    if hidden > 0 {
+        output.push_str(&format!("...and {} more\n", hidden));
+    }
+    output.push_str(&format!(
+        "\nEstimated Tokens: {}\n\n",
+        report.total_tokens()
+    ));
+
+    output
+}
+
+/// Renders the entries below `root` as a tree drawn with `glyphs`.
+pub fn tree(root: &TreeNode, glyphs: &TreeGlyphs) -> String {
+    let mut output = String::new();
+    render_tree(root, "", glyphs, &mut output);
+    output
+}
+
+/// Writes the children of `dir`, each preceded by `prefix`: one `vertical`
+/// glyph per enclosing directory that has more entries below, or blank
+/// space of the same width once that directory's last entry is reached.
+fn render_tree(dir: &TreeNode, prefix: &str, glyphs: &TreeGlyphs, output: &mut String) {
+    for (i, node) in dir.children.iter().enumerate() {
+        let is_last = i == dir.children.len() - 1;
+        output.push_str(&format!(
+            "{}{}{}{}",
+            prefix,
+            if is_last {
+                &glyphs.last
+            } else {
+                &glyphs.branch
+            },
+            node.name,
+            if node.is_dir { "/" } else { "" }
+        ));
+        match &node.link {
+            Some(target) => output.push_str(&format!(" -> {}\n", target.display())),
+            None => output.push('\n'),
+        }
+        if node.is_dir {
+            let continuation = if is_last {
+                " ".repeat(glyphs.vertical.chars().count())
+            } else {
+                glyphs.vertical.clone()
+            };
+            render_tree(node, &format!("{}{}", prefix, continuation), glyphs, output);
+        }
+    }
+}
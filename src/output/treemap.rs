@@ -0,0 +1,217 @@
+use super::{escape_html, format_size};
+use crate::language::language;
+use crate::report::{Report, TreeNode};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+const WIDTH: f64 = 1200.0;
+const HEIGHT: f64 = 800.0;
+/// Height reserved for a directory's name above its contents.
+const HEADER: f64 = 16.0;
+const PADDING: f64 = 2.0;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Renders a squarified treemap of file sizes as a standalone SVG. Files are
+/// colored by language and nested inside labelled directory frames; hovering
+/// shows the full path and size. Files with identical copies elsewhere get a
+/// dashed outline.
+pub fn render(report: &Report) -> String {
+    let copies = report.identical_copies();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         font-family=\"Arial, Helvetica, sans-serif\" font-size=\"11\">\n\
+         <title>Codetree treemap: {name} ({size}, about {tokens} tokens)</title>\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#1f2937\"/>\n",
+        w = WIDTH,
+        h = HEIGHT,
+        name = escape_html(&report.project_name),
+        size = format_size(report.root.size),
+        tokens = report.total_tokens()
+    );
+    layout_children(
+        &report.root,
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            w: WIDTH,
+            h: HEIGHT,
+        },
+        &copies,
+        &mut svg,
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn layout_children(node: &TreeNode, area: Rect, copies: &HashMap<&Path, usize>, svg: &mut String) {
+    let mut children: Vec<_> = node.children.iter().filter(|c| c.size > 0).collect();
+    children.sort_by_key(|child| std::cmp::Reverse(child.size));
+    let sizes: Vec<f64> = children.iter().map(|child| child.size as f64).collect();
+
+    for (child, rect) in children.into_iter().zip(squarify(&sizes, area)) {
+        if rect.w < 1.0 || rect.h < 1.0 {
+            continue;
+        }
+        if child.is_dir {
+            draw_directory(child, rect, copies, svg);
+        } else {
+            draw_file(child, rect, copies, svg);
+        }
+    }
+}
+
+fn draw_directory(node: &TreeNode, rect: Rect, copies: &HashMap<&Path, usize>, svg: &mut String) {
+    let _ = writeln!(
+        svg,
+        "<g><title>{}/ ({})</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#374151\" stroke=\"#111827\"/></g>",
+        escape_html(&node.path.display().to_string()),
+        format_size(node.size),
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h
+    );
+
+    let has_header = rect.h > HEADER * 2.0 && rect.w > 30.0;
+    if has_header {
+        label(svg, &format!("{}/", node.name), rect, "#e5e7eb");
+    }
+    let top = if has_header { HEADER } else { PADDING };
+    let inner = Rect {
+        x: rect.x + PADDING,
+        y: rect.y + top,
+        w: rect.w - PADDING * 2.0,
+        h: rect.h - top - PADDING,
+    };
+    if inner.w > 1.0 && inner.h > 1.0 {
+        layout_children(node, inner, copies, svg);
+    }
+}
+
+fn draw_file(node: &TreeNode, rect: Rect, copies: &HashMap<&Path, usize>, svg: &mut String) {
+    let (note, stroke) = match copies.get(node.path.as_path()) {
+        Some(count) => (
+            format!(", one of {} identical copies", count),
+            "stroke=\"#f9fafb\" stroke-width=\"1.5\" stroke-dasharray=\"4 2\"",
+        ),
+        None => (String::new(), "stroke=\"#111827\" stroke-width=\"0.5\""),
+    };
+    let _ = writeln!(
+        svg,
+        "<g><title>{} ({}{})</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" {}/></g>",
+        escape_html(&node.path.display().to_string()),
+        format_size(node.size),
+        note,
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        color(language(&node.path).unwrap_or("other")),
+        stroke
+    );
+    if rect.h > 14.0 {
+        label(svg, &node.name, rect, "#111827");
+    }
+}
+
+/// Writes `text` in the top-left corner of `rect` if it fits.
+fn label(svg: &mut String, text: &str, rect: Rect, fill: &str) {
+    if text.chars().count() as f64 * 6.5 + 6.0 > rect.w {
+        return;
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{:.1}\" y=\"{:.1}\" fill=\"{}\" pointer-events=\"none\">{}</text>",
+        rect.x + 3.0,
+        rect.y + 12.0,
+        fill,
+        escape_html(text)
+    );
+}
+
+/// A stable pastel color per language.
+fn color(language: &str) -> String {
+    let hash = language.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    format!("hsl({}, 60%, 65%)", hash % 360)
+}
+
+/// Splits `area` into rectangles whose areas are proportional to `values`
+/// (sorted largest first), keeping aspect ratios close to 1 using the
+/// squarified treemap algorithm of Bruls, Huizing and van Wijk.
+fn squarify(values: &[f64], area: Rect) -> Vec<Rect> {
+    let total: f64 = values.iter().sum();
+    if total <= 0.0 || area.w <= 0.0 || area.h <= 0.0 {
+        return Vec::new();
+    }
+    let scale = area.w * area.h / total;
+    let areas: Vec<f64> = values.iter().map(|value| value * scale).collect();
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut free = area;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if free.w >= free.h {
+            let width = row_area / free.h;
+            let mut y = free.y;
+            for area in row {
+                let h = area / width;
+                rects.push(Rect {
+                    x: free.x,
+                    y,
+                    w: width,
+                    h,
+                });
+                y += h;
+            }
+            free.x += width;
+            free.w -= width;
+        } else {
+            let height = row_area / free.w;
+            let mut x = free.x;
+            for area in row {
+                let w = area / height;
+                rects.push(Rect {
+                    x,
+                    y: free.y,
+                    w,
+                    h: height,
+                });
+                x += w;
+            }
+            free.y += height;
+            free.h -= height;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// The worst aspect ratio in `row` when laid out along a side of length `side`.
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side2 = side * side;
+    let sum2 = sum * sum;
+    (side2 * max / sum2).max(sum2 / (side2 * min))
+}
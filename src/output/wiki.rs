@@ -0,0 +1,215 @@
+use super::{code_fence, format_size};
+use crate::report::{wasted_bytes, FileContent, Report, TreeNode};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Writes the report as a set of GitHub wiki pages into `dir`: `Home.md`
+/// with the tree and the root-level files, plus one page per directory.
+///
+/// Existing pages are overwritten but nothing else in `dir` is touched, so it
+/// can be a clone of the wiki repository.
+pub fn write(report: &Report, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut home = format!("# {}\n\n", report.project_name);
+    if let Some(truncation) = report.truncated {
+        home.push_str(&format!("> **Warning:** {}.\n\n", truncation));
+    }
+    home.push_str(&format!(
+        "{} files, {} lines, {}, about {} tokens.\n\n",
+        report.files.len(),
+        report.total_lines(),
+        format_size(report.total_bytes()),
+        report.total_tokens()
+    ));
+    home.push_str("## File Tree\n\n");
+    render_tree(&report.root, 0, &mut home);
+    home.push('\n');
+    render_duplicates(report, &mut home);
+    render_authors(report, &mut home);
+    render_files(report, &report.root, &mut home)?;
+    fs::write(dir.join("Home.md"), home)?;
+
+    write_directory_pages(report, &report.root, dir)
+}
+
+fn write_directory_pages(report: &Report, node: &TreeNode, dir: &Path) -> io::Result<()> {
+    for child in node.children.iter().filter(|child| child.is_dir) {
+        let mut page = format!("# {}/\n\n", child.path.display());
+        page.push_str(&breadcrumb(child));
+        page.push_str("\n\n");
+
+        let subdirs: Vec<_> = child.children.iter().filter(|c| c.is_dir).collect();
+        if !subdirs.is_empty() {
+            page.push_str("## Directories\n\n");
+            for subdir in subdirs {
+                page.push_str(&format!("- {}\n", wiki_link(subdir)));
+            }
+            page.push('\n');
+        }
+        render_files(report, child, &mut page)?;
+
+        fs::write(dir.join(format!("{}.md", page_name(&child.path))), page)?;
+        write_directory_pages(report, child, dir)?;
+    }
+    Ok(())
+}
+
+fn render_tree(node: &TreeNode, depth: usize, page: &mut String) {
+    for child in &node.children {
+        let indent = "  ".repeat(depth);
+        if child.is_dir {
+            page.push_str(&format!("{}- {}\n", indent, wiki_link(child)));
+            render_tree(child, depth + 1, page);
+        } else {
+            match &child.link {
+                Some(target) => page.push_str(&format!(
+                    "{}- {} -> {}\n",
+                    indent,
+                    child.name,
+                    target.display()
+                )),
+                None => page.push_str(&format!("{}- {}\n", indent, child.name)),
+            }
+        }
+    }
+}
+
+fn render_duplicates(report: &Report, page: &mut String) {
+    let groups = report.duplicate_groups();
+    if groups.is_empty() {
+        return;
+    }
+    page.push_str(&format!(
+        "## Identical Files\n\n{} wasted by redundant copies.\n\n",
+        format_size(wasted_bytes(&groups))
+    ));
+    for group in groups {
+        let paths: Vec<_> = group
+            .paths
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+        page.push_str(&format!(
+            "- {} ({})\n",
+            paths.join(", "),
+            format_size(group.size)
+        ));
+    }
+    page.push('\n');
+}
+
+fn render_authors(report: &Report, page: &mut String) {
+    let authors = report.author_totals();
+    if authors.is_empty() {
+        return;
+    }
+    let total = authors
+        .iter()
+        .map(|author| author.lines)
+        .sum::<usize>()
+        .max(1) as f64;
+    page.push_str("## Authors\n\n| Author | Lines | Share | Files |\n|---|---:|---:|---:|\n");
+    for author in &authors {
+        page.push_str(&format!(
+            "| {} | {} | {:.1}% | {} |\n",
+            author.name.replace('|', "\\|"),
+            author.lines,
+            author.lines as f64 / total * 100.0,
+            author.files
+        ));
+    }
+    let ownership = report.ownership();
+    if !ownership.is_empty() {
+        page.push_str("\n| Directory | Bus factor | Lines | Main author |\n|---|---:|---:|---|\n");
+        for directory in &ownership {
+            let main = directory.authors.first().map_or("", |(name, _)| *name);
+            page.push_str(&format!(
+                "| `{}/` | {} | {} | {} |\n",
+                directory.directory.display(),
+                directory.bus_factor,
+                directory.lines,
+                main.replace('|', "\\|")
+            ));
+        }
+    }
+    page.push('\n');
+}
+
+fn render_files(report: &Report, dir: &TreeNode, page: &mut String) -> io::Result<()> {
+    let files: Vec<_> = report
+        .files
+        .iter()
+        .filter(|file| file.path.parent() == Some(dir.path.as_path()))
+        .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    page.push_str("## Files\n\n");
+    let earlier_copies = report.earlier_copies();
+    for file in files {
+        let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+        page.push_str(&format!("### {}\n\n", name));
+        if let Some(original) = earlier_copies.get(file.path.as_path()) {
+            page.push_str(&format!("*Identical to `{}`.*\n\n", original.display()));
+            continue;
+        }
+        match &file.content {
+            FileContent::Text(text) => {
+                let Some(content) = report.read(text)? else {
+                    if text.too_large {
+                        page.push_str(&format!(
+                            "*File too large, {} lines omitted.*\n\n",
+                            text.lines
+                        ));
+                    } else {
+                        page.push_str("*Content withheld.*\n\n");
+                    }
+                    continue;
+                };
+                let fence = code_fence(&content);
+                page.push_str(&format!(
+                    "{}{}\n{}\n{}\n\n",
+                    fence,
+                    file.language().unwrap_or(""),
+                    content.trim_end_matches('\n'),
+                    fence
+                ));
+            }
+            FileContent::Binary => page.push_str("*Binary file omitted.*\n\n"),
+            FileContent::Unreadable => page.push_str("*Unable to read file content.*\n\n"),
+            FileContent::Missing => page.push_str("*File not found.*\n\n"),
+        }
+    }
+    Ok(())
+}
+
+fn breadcrumb(node: &TreeNode) -> String {
+    let mut crumbs = vec!["[[Home]]".to_string()];
+    let mut path = std::path::PathBuf::new();
+    let components: Vec<_> = node.path.components().collect();
+    for (i, component) in components.iter().enumerate() {
+        path.push(component);
+        let name = component.as_os_str().to_string_lossy();
+        if i == components.len() - 1 {
+            crumbs.push(name.to_string());
+        } else {
+            crumbs.push(format!("[[{}|{}]]", name, page_name(&path)));
+        }
+    }
+    crumbs.join(" / ")
+}
+
+fn wiki_link(node: &TreeNode) -> String {
+    format!("[[{}/|{}]]", node.name, page_name(&node.path))
+}
+
+/// Wiki page names are flat, so directory separators become dashes.
+fn page_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-")
+}
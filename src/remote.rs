@@ -0,0 +1,71 @@
+//! Scanning repositories given by URL, by cloning them into a temporary
+//! directory first.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// URL schemes that name a repository rather than a local path.
+const SCHEMES: [&str; 5] = ["https://", "http://", "ssh://", "git://", "file://"];
+
+/// Whether `path` is the URL of a git repository, such as
+/// `https://github.com/org/repo` or `git@github.com:org/repo.git`, rather
+/// than a local directory.
+pub fn is_url(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    SCHEMES.iter().any(|scheme| text.starts_with(scheme))
+        || text
+            .split_once(':')
+            .is_some_and(|(host, _)| host.contains('@') && !host.contains('/'))
+}
+
+/// A clone of a remote repository, deleted when dropped.
+pub struct Checkout {
+    dir: TempDir,
+    name: String,
+}
+
+impl Checkout {
+    /// Clones the repository at `url` into a new temporary directory,
+    /// checking out `reference`, a branch or tag, or else the default
+    /// branch. A `shallow` clone only fetches the commit checked out, which
+    /// is all a scan needs unless it reads the history.
+    pub fn clone(url: &str, reference: Option<&str>, shallow: bool) -> io::Result<Self> {
+        let dir = tempfile::Builder::new().prefix("codetree-").tempdir()?;
+        let name = repository_name(url);
+        let mut git = Command::new("git");
+        git.args(["clone", "--quiet", "--no-tags"]);
+        if shallow {
+            git.args(["--depth", "1"]);
+        }
+        if let Some(reference) = reference {
+            git.args(["--branch", reference]);
+        }
+        let output = git.args(["--", url]).arg(dir.path().join(&name)).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git clone of {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(Checkout { dir, name })
+    }
+
+    /// The directory of the cloned work tree, named after the repository.
+    pub fn path(&self) -> PathBuf {
+        self.dir.path().join(&self.name)
+    }
+}
+
+/// The last component of `url` without a `.git` suffix, as `git clone`
+/// names the directory it clones into.
+fn repository_name(url: &str) -> String {
+    let path = url.trim_end_matches('/');
+    let last = path.rsplit(['/', ':']).next().unwrap_or_default();
+    match last.strip_suffix(".git").unwrap_or(last) {
+        "" => "repository".to_string(),
+        name => name.to_string(),
+    }
+}
@@ -0,0 +1,695 @@
+use crate::authors::Authorship;
+use crate::cache::Measurements;
+use crate::category::{self, Category};
+use crate::churn::{Churn, FileChurn};
+use crate::comments::{self, LineCounts};
+use crate::detect::Detection;
+use crate::language::{self, language};
+use crate::sfc::Section;
+use crate::spool::{Spool, Spooled};
+use crate::{doc_blocks, sfc, tokens};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Everything collected from one pass over a project, independent of the
+/// output format it is rendered into.
+pub struct Report {
+    /// Name of the analyzed directory.
+    pub project_name: String,
+    pub root: TreeNode,
+    /// Included files, in tree order.
+    pub files: Vec<FileEntry>,
+    /// The text of every readable file, read back with [`Report::read`].
+    pub spool: Spool,
+    /// Project ecosystems detected in the root, such as Android or Flutter.
+    pub ecosystems: Vec<Detection>,
+    /// How often files changed in the git history, when `--hotspots` asked
+    /// for it.
+    pub churn: Option<Churn>,
+    /// Who last changed the lines of each file, when `--authors` asked for
+    /// it.
+    pub authors: Option<Authorship>,
+    /// Why the scan stopped early, if it did; the tree and files then only
+    /// cover part of the project.
+    pub truncated: Option<Truncation>,
+    /// Paths left out of the scan, in the order they were skipped. Paths
+    /// inside a skipped directory are not listed.
+    pub excluded: Vec<Excluded>,
+    /// The projects inside the report: those scanned together into a
+    /// combined report, and the members of a detected workspace. Empty for
+    /// a single project that is not a workspace.
+    pub projects: Vec<Project>,
+}
+
+/// A project or package with its own directory inside a report.
+pub struct Project {
+    /// Directory of the project, relative to the report's root.
+    pub path: PathBuf,
+    /// Ecosystems detected in the project's directory.
+    pub ecosystems: Vec<Detection>,
+}
+
+/// A path left out of the scan, and why.
+pub struct Excluded {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    /// The rule that excluded it, as shown by `--show-ignores`.
+    pub rule: String,
+}
+
+/// Why a scan stopped before covering the whole project.
+#[derive(Clone, Copy)]
+pub enum Truncation {
+    /// The `--timeout` elapsed.
+    Timeout(Duration),
+    /// Only this many files were read, as limited by `--max-files`.
+    MaxFiles(usize),
+}
+
+impl fmt::Display for Truncation {
+    /// A notice to show at the top of a partial report.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Truncation::Timeout(timeout) => write!(
+                f,
+                "Partial report: the scan stopped after the {} s timeout",
+                timeout.as_secs()
+            ),
+            Truncation::MaxFiles(files) => {
+                write!(f, "Analysis truncated after {} files", files)
+            }
+        }
+    }
+}
+
+impl FromStr for Truncation {
+    type Err = ();
+
+    /// Parses the notice written by [`Truncation`]'s `Display`, as stored
+    /// in JSON reports.
+    fn from_str(notice: &str) -> Result<Self, ()> {
+        if let Some(seconds) = notice
+            .strip_prefix("Partial report: the scan stopped after the ")
+            .and_then(|rest| rest.strip_suffix(" s timeout"))
+        {
+            return Ok(Truncation::Timeout(Duration::from_secs(
+                seconds.parse().map_err(|_| ())?,
+            )));
+        }
+        notice
+            .strip_prefix("Analysis truncated after ")
+            .and_then(|rest| rest.strip_suffix(" files"))
+            .and_then(|files| files.parse().ok())
+            .map(Truncation::MaxFiles)
+            .ok_or(())
+    }
+}
+
+/// A directory or file in the project tree.
+pub struct TreeNode {
+    pub name: String,
+    /// Path relative to the project root; empty for the root itself.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Size in bytes; for directories, the total of all included files below.
+    pub size: u64,
+    pub children: Vec<TreeNode>,
+    /// Target of a symlink that was listed rather than followed, as written
+    /// in the link.
+    pub link: Option<PathBuf>,
+}
+
+pub struct FileEntry {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    /// Size on disk in bytes.
+    pub size: u64,
+    pub content: FileContent,
+}
+
+pub enum FileContent {
+    Text(Text),
+    /// Binary data such as an image or an archive, which is counted by size
+    /// but never read as text.
+    Binary,
+    Unreadable,
+    Missing,
+}
+
+/// A readable file: what was measured while its text was in memory, and
+/// where that text was spooled.
+pub struct Text {
+    pub lines: usize,
+    /// The lines split into code, comments and blanks.
+    pub counts: LineCounts,
+    /// Estimated tokens of the content, see [`tokens::estimate`].
+    pub tokens: usize,
+    /// Language named by the `#!` line of a script whose path does not
+    /// tell its language.
+    pub script: Option<&'static str>,
+    /// Name of the encoding the file was decoded from, such as `UTF-8`,
+    /// `UTF-16LE` or `windows-1252`.
+    pub encoding: &'static str,
+    /// Secrets replaced by `[REDACTED:kind]` markers, see
+    /// [`crate::secrets::redact`].
+    pub redactions: usize,
+    /// Hex-encoded SHA-256 of the content.
+    pub sha256: String,
+    /// Lines embedded in other languages, such as the blocks of a Vue
+    /// component, to be split off in [`Report::language_totals`].
+    pub sections: Vec<Section>,
+    /// `None` once the content has been withheld, as with `--anonymize`.
+    pub spooled: Option<Spooled>,
+    /// Whether the content was left out for exceeding `--max-file-size`,
+    /// in which case it is not spooled either.
+    pub too_large: bool,
+}
+
+/// A file's text and what was measured from it, before it is spooled.
+pub struct Measured {
+    content: String,
+    lines: usize,
+    counts: LineCounts,
+    tokens: usize,
+    script: Option<&'static str>,
+    encoding: &'static str,
+    redactions: usize,
+    sha256: String,
+    sections: Vec<Section>,
+}
+
+impl Measured {
+    /// Measures `content`. With `count_doc_blocks`, code blocks in Markdown
+    /// and HTML become sections of their own languages, as Vue and Svelte
+    /// blocks always do.
+    pub fn new(path: &Path, content: String, count_doc_blocks: bool) -> Self {
+        let by_path = language(path);
+        let script = by_path
+            .is_none()
+            .then(|| content.lines().next().and_then(language::by_shebang))
+            .flatten();
+        let language = by_path.or(script).unwrap_or("other");
+        let sections = match sfc::sections(language, &content) {
+            Some(sections) => Some(sections),
+            None if count_doc_blocks => doc_blocks::sections(language, &content),
+            None => None,
+        };
+        Measured {
+            lines: content.lines().count(),
+            counts: comments::count(language, &content),
+            tokens: tokens::estimate(&content),
+            script,
+            encoding: "UTF-8",
+            redactions: 0,
+            sha256: format!("{:x}", Sha256::digest(&content)),
+            sections: sections.unwrap_or_default(),
+            content,
+        }
+    }
+
+    /// `content` as measured by an earlier scan.
+    pub fn cached(content: String, measurements: Measurements) -> Self {
+        Measured {
+            content,
+            lines: measurements.lines,
+            counts: measurements.counts,
+            tokens: measurements.tokens,
+            script: measurements.script,
+            encoding: measurements.encoding,
+            redactions: measurements.redactions,
+            sha256: measurements.sha256,
+            sections: measurements.sections,
+        }
+    }
+
+    /// Records that `content` was decoded from `encoding` rather than read
+    /// as UTF-8.
+    pub fn encoding(mut self, encoding: &'static str) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Records that `redactions` secrets were redacted from `content`.
+    pub fn redactions(mut self, redactions: usize) -> Self {
+        self.redactions = redactions;
+        self
+    }
+
+    /// Moves the text into `spool`, keeping only the measurements.
+    pub fn spool(self, spool: &mut Spool) -> io::Result<Text> {
+        Ok(Text {
+            lines: self.lines,
+            counts: self.counts,
+            tokens: self.tokens,
+            script: self.script,
+            encoding: self.encoding,
+            redactions: self.redactions,
+            sha256: self.sha256,
+            sections: self.sections,
+            spooled: Some(spool.append(&self.content)?),
+            too_large: false,
+        })
+    }
+
+    /// Drops the text of a file too large to include, keeping only the
+    /// measurements.
+    pub fn omit(self) -> Text {
+        Text {
+            lines: self.lines,
+            counts: self.counts,
+            tokens: self.tokens,
+            script: self.script,
+            encoding: self.encoding,
+            redactions: self.redactions,
+            sha256: self.sha256,
+            sections: self.sections,
+            spooled: None,
+            too_large: true,
+        }
+    }
+}
+
+impl Report {
+    /// Reads a file's text back from the spool, unless it was withheld.
+    pub fn read(&self, text: &Text) -> io::Result<Option<String>> {
+        text.spooled
+            .map(|spooled| self.spool.read(spooled))
+            .transpose()
+    }
+
+    /// Keeps only the files whose paths `keep` accepts, removing the others
+    /// from the tree along with the directories they leave empty. Their
+    /// text stays in the spool, unused.
+    pub fn retain(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.files.retain(|file| keep(&file.path));
+        retain_nodes(&mut self.root, &keep);
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.files.iter().map(FileEntry::line_count).sum()
+    }
+
+    /// Number of included files holding binary data.
+    pub fn binary_files(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.content, FileContent::Binary))
+            .count()
+    }
+
+    /// Estimated tokens across all included files.
+    pub fn total_tokens(&self) -> usize {
+        self.files.iter().map(FileEntry::token_count).sum()
+    }
+
+    /// Secrets redacted across all included files.
+    pub fn total_redactions(&self) -> usize {
+        self.files.iter().map(FileEntry::redactions).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    /// Per-language totals, largest by line count first. Files without a
+    /// known language are grouped under `other`. The lines and bytes of
+    /// Vue and Svelte components (and, if enabled, of code blocks in
+    /// documents) are split between the languages of their blocks, while
+    /// the file itself counts towards its own language.
+    pub fn language_totals(&self) -> Vec<(&'static str, Totals)> {
+        let mut totals: HashMap<&'static str, Totals> = HashMap::new();
+        for file in &self.files {
+            let file_language = file.language().unwrap_or("other");
+            let (mut lines, mut bytes) = (file.line_count(), file.size);
+            if let FileContent::Text(text) = &file.content {
+                for section in &text.sections {
+                    let entry = totals.entry(section.language).or_default();
+                    entry.lines += section.lines;
+                    entry.bytes += section.bytes;
+                    lines -= section.lines;
+                    bytes = bytes.saturating_sub(section.bytes);
+                }
+            }
+            let entry = totals.entry(file_language).or_default();
+            entry.files += 1;
+            entry.lines += lines;
+            entry.bytes += bytes;
+        }
+        let mut totals: Vec<_> = totals.into_iter().collect();
+        totals.sort_by_key(|(language, totals)| (Reverse(totals.lines), *language));
+        totals
+    }
+
+    /// Totals for every project of [`Report::projects`], in its order.
+    pub fn project_totals(&self) -> Vec<(&Project, Totals)> {
+        self.projects
+            .iter()
+            .map(|project| {
+                let mut totals = Totals::default();
+                for file in &self.files {
+                    if file.path.starts_with(&project.path) {
+                        totals.files += 1;
+                        totals.lines += file.line_count();
+                        totals.bytes += file.size;
+                    }
+                }
+                (project, totals)
+            })
+            .collect()
+    }
+
+    /// Files and lines attributed to each author, most lines first. Empty
+    /// when no authors were collected.
+    pub fn author_totals(&self) -> Vec<AuthorTotals<'_>> {
+        let Some(authorship) = &self.authors else {
+            return Vec::new();
+        };
+        let mut totals: HashMap<&str, AuthorTotals> = HashMap::new();
+        for file in &self.files {
+            for author in authorship.get(&file.path) {
+                let entry = totals.entry(&author.name).or_insert(AuthorTotals {
+                    name: &author.name,
+                    files: 0,
+                    lines: 0,
+                });
+                entry.files += 1;
+                entry.lines += author.lines;
+            }
+        }
+        let mut totals: Vec<_> = totals.into_values().collect();
+        totals.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.name.cmp(b.name)));
+        totals
+    }
+
+    /// Who wrote the attributed lines of each top-level directory, in path
+    /// order. Files in the root itself belong to no directory. Empty when
+    /// no authors were collected.
+    pub fn ownership(&self) -> Vec<Ownership<'_>> {
+        let Some(authorship) = &self.authors else {
+            return Vec::new();
+        };
+        let mut directories: BTreeMap<&Path, HashMap<&str, usize>> = BTreeMap::new();
+        for file in &self.files {
+            let mut components = file.path.iter();
+            let (Some(directory), Some(_)) = (components.next(), components.next()) else {
+                continue;
+            };
+            for author in authorship.get(&file.path) {
+                *directories
+                    .entry(Path::new(directory))
+                    .or_default()
+                    .entry(&author.name)
+                    .or_default() += author.lines;
+            }
+        }
+        directories
+            .into_iter()
+            .map(|(directory, authors)| {
+                let mut authors: Vec<_> = authors.into_iter().collect();
+                authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+                let lines = authors.iter().map(|(_, lines)| lines).sum();
+                let mut covered = 0;
+                let bus_factor = authors
+                    .iter()
+                    .take_while(|(_, author_lines)| {
+                        let below_half = covered * 2 <= lines;
+                        covered += author_lines;
+                        below_half
+                    })
+                    .count();
+                Ownership {
+                    directory,
+                    lines,
+                    authors,
+                    bus_factor,
+                }
+            })
+            .collect()
+    }
+
+    /// Totals for every [`Category`], in the order of [`Category::ALL`].
+    pub fn category_totals(&self) -> Vec<(Category, Totals)> {
+        let mut totals: Vec<_> = Category::ALL
+            .iter()
+            .map(|&category| (category, Totals::default()))
+            .collect();
+        for file in &self.files {
+            let category = file.category();
+            if let Some((_, entry)) = totals.iter_mut().find(|(c, _)| *c == category) {
+                entry.files += 1;
+                entry.lines += file.line_count();
+                entry.bytes += file.size;
+            }
+        }
+        totals
+    }
+
+    /// Totals per lowercased file extension, in alphabetical order. Files
+    /// without an extension are totalled under an empty one.
+    pub fn extension_totals(&self) -> BTreeMap<String, ExtensionTotals> {
+        let mut totals: BTreeMap<String, ExtensionTotals> = BTreeMap::new();
+        for file in &self.files {
+            let extension = file
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let entry = totals.entry(extension).or_default();
+            entry.files += 1;
+            entry.lines += file.line_count();
+            entry.counts.add(file.line_counts());
+            entry.tokens += file.token_count();
+            entry.bytes += file.size;
+        }
+        totals
+    }
+
+    /// The `n` largest files by size.
+    pub fn largest_files(&self, n: usize) -> Vec<&FileEntry> {
+        let mut files: Vec<_> = self.files.iter().collect();
+        files.sort_by_key(|file| Reverse(file.size));
+        files.truncate(n);
+        files
+    }
+
+    /// The `n` files with the most churn weighted by size, with their churn
+    /// and score: commits times lines. Files without history or lines are
+    /// left out, as is everything when no churn was collected.
+    pub fn hotspots(&self, n: usize) -> Vec<(&FileEntry, FileChurn, usize)> {
+        let Some(churn) = &self.churn else {
+            return Vec::new();
+        };
+        let mut hotspots: Vec<_> = self
+            .files
+            .iter()
+            .filter_map(|file| {
+                let changes = churn.get(&file.path)?;
+                let score = changes.commits * file.line_count();
+                (score > 0).then_some((file, changes, score))
+            })
+            .collect();
+        hotspots.sort_by_key(|&(_, _, score)| Reverse(score));
+        hotspots.truncate(n);
+        hotspots
+    }
+
+    /// Groups of non-empty files sharing the same content, most wasted
+    /// bytes first. Paths within a group keep tree order.
+    pub fn duplicate_groups(&self) -> Vec<DuplicateGroup<'_>> {
+        let mut groups: HashMap<String, DuplicateGroup> = HashMap::new();
+        for file in &self.files {
+            let Some(sha256) = file.sha256().filter(|_| file.size > 0) else {
+                continue;
+            };
+            groups
+                .entry(sha256.to_string())
+                .or_insert_with(|| DuplicateGroup {
+                    sha256: sha256.to_string(),
+                    size: file.size,
+                    paths: Vec::new(),
+                })
+                .paths
+                .push(&file.path);
+        }
+        let mut groups: Vec<_> = groups
+            .into_values()
+            .filter(|group| group.paths.len() > 1)
+            .collect();
+        groups.sort_by(|a, b| {
+            b.wasted_bytes()
+                .cmp(&a.wasted_bytes())
+                .then_with(|| a.paths[0].cmp(b.paths[0]))
+        });
+        groups
+    }
+
+    /// For every file that has an identical copy earlier in tree order, the
+    /// first such copy.
+    pub fn earlier_copies(&self) -> HashMap<&Path, &Path> {
+        self.duplicate_groups()
+            .into_iter()
+            .flat_map(|group| {
+                let first = group.paths[0];
+                group
+                    .paths
+                    .into_iter()
+                    .skip(1)
+                    .map(move |path| (path, first))
+            })
+            .collect()
+    }
+
+    /// Number of identical copies for every file that has at least one.
+    pub fn identical_copies(&self) -> HashMap<&Path, usize> {
+        self.duplicate_groups()
+            .into_iter()
+            .flat_map(|group| {
+                let count = group.paths.len();
+                group.paths.into_iter().map(move |path| (path, count))
+            })
+            .collect()
+    }
+}
+
+/// The files and lines attributed to one author across a report.
+pub struct AuthorTotals<'a> {
+    pub name: &'a str,
+    /// Files with at least one line by the author.
+    pub files: usize,
+    pub lines: usize,
+}
+
+/// Who wrote the lines of a top-level directory.
+pub struct Ownership<'a> {
+    pub directory: &'a Path,
+    /// Lines attributed to any author.
+    pub lines: usize,
+    /// Lines per author, most first.
+    pub authors: Vec<(&'a str, usize)>,
+    /// The fewest authors who together wrote more than half of the lines:
+    /// how many people could leave before most of the directory has no
+    /// author left to ask about it.
+    pub bus_factor: usize,
+}
+
+/// Removes the files below `node` that `keep` does not accept, and the
+/// directories that held nothing else, and recomputes directory sizes.
+fn retain_nodes(node: &mut TreeNode, keep: &impl Fn(&Path) -> bool) {
+    node.children.retain_mut(|child| {
+        if !child.is_dir {
+            return keep(&child.path);
+        }
+        let had_children = !child.children.is_empty();
+        retain_nodes(child, keep);
+        !had_children || !child.children.is_empty()
+    });
+    node.size = node.children.iter().map(|child| child.size).sum();
+}
+
+/// Total bytes taken up by redundant copies across `groups`.
+pub fn wasted_bytes(groups: &[DuplicateGroup]) -> u64 {
+    groups.iter().map(DuplicateGroup::wasted_bytes).sum()
+}
+
+/// Files whose contents are byte-for-byte identical.
+pub struct DuplicateGroup<'a> {
+    pub sha256: String,
+    /// Size of each copy in bytes.
+    pub size: u64,
+    pub paths: Vec<&'a Path>,
+}
+
+impl DuplicateGroup<'_> {
+    /// Bytes that would be saved by keeping a single copy.
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+#[derive(Default)]
+pub struct Totals {
+    pub files: usize,
+    pub lines: usize,
+    pub bytes: u64,
+}
+
+#[derive(Default)]
+pub struct ExtensionTotals {
+    pub files: usize,
+    pub lines: usize,
+    /// The lines split into code, comments and blanks.
+    pub counts: LineCounts,
+    pub tokens: usize,
+    pub bytes: u64,
+}
+
+impl FileEntry {
+    /// The file's language, from its path or, for scripts without a telling
+    /// name, from its `#!` line.
+    pub fn language(&self) -> Option<&'static str> {
+        language(&self.path).or(match &self.content {
+            FileContent::Text(text) => text.script,
+            _ => None,
+        })
+    }
+
+    /// The file's category, taking a language recognized from its content
+    /// into account.
+    pub fn category(&self) -> Category {
+        category::classify(&self.path, self.language())
+    }
+
+    /// Hex-encoded SHA-256 of the content, if it could be read.
+    pub fn sha256(&self) -> Option<&str> {
+        match &self.content {
+            FileContent::Text(text) => Some(&text.sha256),
+            _ => None,
+        }
+    }
+
+    /// Name of the encoding the content was decoded from, if it could be
+    /// read.
+    pub fn encoding(&self) -> Option<&'static str> {
+        match &self.content {
+            FileContent::Text(text) => Some(text.encoding),
+            _ => None,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        match &self.content {
+            FileContent::Text(text) => text.lines,
+            _ => 0,
+        }
+    }
+
+    /// Estimated tokens of the content, zero if the file was not read.
+    pub fn token_count(&self) -> usize {
+        match &self.content {
+            FileContent::Text(text) => text.tokens,
+            _ => 0,
+        }
+    }
+
+    /// Secrets redacted from the content, zero if the file was not read.
+    pub fn redactions(&self) -> usize {
+        match &self.content {
+            FileContent::Text(text) => text.redactions,
+            _ => 0,
+        }
+    }
+
+    /// Code, comment and blank lines, all zero if the file was not read.
+    pub fn line_counts(&self) -> LineCounts {
+        match &self.content {
+            FileContent::Text(text) => text.counts,
+            _ => LineCounts::default(),
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! Scanning zip and tar archives as the directories they hold, reading
+//! their entries in memory instead of extracting them.
+
+use super::{exclusion, is_hidden, read_text, redact, skip, Deadline, Hidden, ScanOptions};
+use crate::detect::{self, Detection, Listing};
+use crate::events::Logger;
+use crate::report::{Excluded, FileContent, FileEntry, Measured, Report, TreeNode, Truncation};
+use crate::spool::Spool;
+use flate2::read::GzDecoder;
+use ignore::gitignore::Gitignore;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tracing::info_span;
+use zip::ZipArchive;
+
+/// Name suffixes of the archives that can be scanned, lowercase.
+const SUFFIXES: [&str; 4] = [".tar.gz", ".tgz", ".tar", ".zip"];
+
+/// Whether `path` is an archive file to be scanned in place of a directory.
+pub fn is_archive(path: &Path) -> bool {
+    suffix(path).is_some() && path.is_file()
+}
+
+/// The project ecosystems found in the archive at `path`.
+pub fn detect(path: &Path) -> io::Result<Vec<Detection>> {
+    Ok(detect::detect_listing(&Archive::open(path)?.listing()))
+}
+
+/// Reads the archive at `path` like [`super::scan_project`] walks a
+/// directory. Entries are listed in a first pass, then only the included
+/// files are read, one at a time, in a second.
+pub fn scan(path: &Path, options: &ScanOptions, log: &Logger) -> io::Result<Report> {
+    let deadline = Deadline::after(options.timeout);
+    let (archive, root, file_paths, excluded) = info_span!("walk").in_scope(|| {
+        let archive = Archive::open(path)?;
+        let (root, file_paths, excluded) = archive.walk(&deadline, options, log)?;
+        Ok::<_, io::Error>((archive, root, file_paths, excluded))
+    })?;
+
+    let _collect = info_span!("collect").entered();
+    let mut spool = Spool::new()?;
+    let limit = options
+        .max_files
+        .map_or(file_paths.len(), |max| max.min(file_paths.len()));
+    let files = archive.read(&file_paths[..limit], &deadline, options, log, &mut spool)?;
+    drop(_collect);
+
+    Ok(Report {
+        project_name: root.name.clone(),
+        root,
+        truncated: match options.timeout {
+            Some(timeout) if deadline.expired.load(Ordering::Relaxed) => {
+                Some(Truncation::Timeout(timeout))
+            }
+            _ if limit < file_paths.len() => Some(Truncation::MaxFiles(limit)),
+            _ => None,
+        },
+        files,
+        spool,
+        ecosystems: options.ecosystems.clone(),
+        churn: None,
+        authors: None,
+        projects: Vec::new(),
+        excluded,
+    })
+}
+
+/// Lists the entries of the archive at `path` like
+/// [`super::list_files`], without reading any file.
+pub fn list_files(path: &Path, options: &ScanOptions, log: &Logger) -> io::Result<Vec<PathBuf>> {
+    let deadline = Deadline::after(options.timeout);
+    let (_, mut file_paths, _) = Archive::open(path)?.walk(&deadline, options, log)?;
+    file_paths.truncate(options.max_files.unwrap_or(usize::MAX));
+    Ok(file_paths)
+}
+
+/// What an entry of an archive is.
+enum Kind {
+    Dir,
+    File,
+    /// A symbolic or hard link, with its target.
+    Link(PathBuf),
+}
+
+/// The entries of an archive, as found by a first pass over it.
+struct Archive {
+    path: PathBuf,
+    /// Name of the project: the archive's single top-level directory, or
+    /// else the archive's name without its suffix.
+    name: String,
+    /// The top-level directory left out of every path, if there is one.
+    prefix: PathBuf,
+    /// Every entry with its size, by path relative to the project root,
+    /// including the directories implied by the paths of other entries.
+    entries: BTreeMap<PathBuf, (Kind, u64)>,
+    /// The names of the entries of each directory.
+    children: HashMap<PathBuf, Vec<PathBuf>>,
+    /// Contents of the `.gitignore` files and the files ecosystems are
+    /// detected by.
+    texts: HashMap<PathBuf, String>,
+}
+
+impl Archive {
+    fn open(path: &Path) -> io::Result<Self> {
+        let markers: Vec<_> = detect::marker_files().map(Path::new).collect();
+        let mut entries = BTreeMap::new();
+        let mut texts = HashMap::new();
+        visit(path, |name, kind, size, reader| {
+            // Marker files only count at the root, possibly below the
+            // top-level directory.
+            let wanted = matches!(kind, Kind::File)
+                && (name.ends_with(".gitignore")
+                    || markers.iter().any(|marker| {
+                        name.ends_with(marker)
+                            && name.components().count() <= marker.components().count() + 1
+                    }));
+            if wanted {
+                let mut text = String::new();
+                if reader.read_to_string(&mut text).is_ok() {
+                    texts.insert(name.to_path_buf(), text);
+                }
+            }
+            entries.insert(name.to_path_buf(), (kind, size));
+            Ok(true)
+        })
+        .map_err(|err| io::Error::other(format!("cannot read {}: {}", path.display(), err)))?;
+
+        // An archive of a single directory, as most are, holds the project
+        // in that directory.
+        let first = |path: &Path| path.components().next().map(|c| c.as_os_str().to_owned());
+        let top = entries.keys().next().and_then(|path| first(path));
+        let prefix = match top {
+            Some(top)
+                if entries
+                    .keys()
+                    .all(|path| first(path).as_ref() == Some(&top))
+                    && entries.keys().any(|path| path.components().count() > 1)
+                    && !entries
+                        .get(Path::new(&top))
+                        .is_some_and(|(kind, _)| !matches!(kind, Kind::Dir)) =>
+            {
+                PathBuf::from(top)
+            }
+            _ => PathBuf::new(),
+        };
+        let name = match prefix.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let suffix = suffix(path).unwrap_or_default();
+                name[..name.len() - suffix.len()].to_string()
+            }
+        };
+
+        let mut archive = Archive {
+            path: path.to_path_buf(),
+            name,
+            entries: BTreeMap::new(),
+            children: HashMap::new(),
+            texts: texts
+                .into_iter()
+                .filter_map(|(path, text)| {
+                    Some((path.strip_prefix(&prefix).ok()?.to_owned(), text))
+                })
+                .collect(),
+            prefix,
+        };
+        for (path, entry) in entries {
+            let Ok(path) = path.strip_prefix(&archive.prefix) else {
+                continue;
+            };
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            for dir in path.ancestors().skip(1) {
+                if archive.entries.contains_key(dir) || dir.as_os_str().is_empty() {
+                    break;
+                }
+                archive.entries.insert(dir.to_path_buf(), (Kind::Dir, 0));
+            }
+            archive.entries.insert(path.to_path_buf(), entry);
+        }
+        for path in archive.entries.keys() {
+            let parent = path.parent().unwrap_or(Path::new(""));
+            let children = archive.children.entry(parent.to_path_buf()).or_default();
+            children.push(path.to_path_buf());
+        }
+        Ok(archive)
+    }
+
+    /// The entries as ecosystem detection needs them.
+    fn listing(&self) -> Listing {
+        Listing {
+            paths: self.entries.keys().cloned().collect(),
+            contents: self
+                .texts
+                .iter()
+                .filter(|(path, _)| {
+                    detect::marker_files().any(|marker| path.as_path() == Path::new(marker))
+                })
+                .map(|(path, text)| (path.clone(), text.clone()))
+                .collect(),
+        }
+    }
+
+    /// Builds the tree of the entries left after the exclusion rules, as
+    /// [`super::walk_dir`] does for a directory, and returns it with the
+    /// paths of the files to read and the entries left out.
+    fn walk(
+        &self,
+        deadline: &Deadline,
+        options: &ScanOptions,
+        log: &Logger,
+    ) -> io::Result<(TreeNode, Vec<PathBuf>, Vec<Excluded>)> {
+        let mut root = TreeNode {
+            name: self.name.clone(),
+            path: PathBuf::new(),
+            is_dir: true,
+            size: 0,
+            children: Vec::new(),
+            link: None,
+        };
+        let mut file_paths = Vec::new();
+        let mut excluded = Vec::new();
+        self.walk_dir(
+            &mut root,
+            &mut file_paths,
+            &mut excluded,
+            &mut Vec::new(),
+            deadline,
+            options,
+            log,
+        )?;
+        Ok((root, file_paths, excluded))
+    }
+
+    /// Adds the entries of the directory of `node` to it, recursing into
+    /// subdirectories. Paths are checked against the rules as if the
+    /// archive were the directory of its project.
+    #[allow(clippy::too_many_arguments)]
+    fn walk_dir(
+        &self,
+        node: &mut TreeNode,
+        file_paths: &mut Vec<PathBuf>,
+        excluded: &mut Vec<Excluded>,
+        gitignores: &mut Vec<Gitignore>,
+        deadline: &Deadline,
+        options: &ScanOptions,
+        log: &Logger,
+    ) -> io::Result<()> {
+        log.progress("walk", file_paths.len(), None, &node.path)?;
+        let dir = self.path.join(&node.path);
+        let gitignore = self
+            .texts
+            .get(&node.path.join(".gitignore"))
+            .and_then(|content| options.ignores.parse_gitignore(&dir, content, log));
+        let has_gitignore = gitignore.is_some();
+        gitignores.extend(gitignore);
+
+        let mut entries: Vec<_> = self
+            .children
+            .get(&node.path)
+            .into_iter()
+            .flatten()
+            .filter_map(|path| {
+                let (kind, size) = &self.entries[path];
+                let is_dir = matches!(kind, Kind::Dir);
+                let full = self.path.join(path);
+                match exclusion(&self.path, &full, is_dir, options, gitignores) {
+                    Some(rule) => {
+                        skip(&full, &rule, &self.path, excluded, log);
+                        None
+                    }
+                    None => Some((path, kind, *size)),
+                }
+            })
+            .collect();
+        entries.sort_by_key(|(path, kind, _)| {
+            (
+                !matches!(kind, Kind::Dir),
+                matches!(kind, Kind::Link(_)),
+                path.file_name(),
+            )
+        });
+
+        for (path, kind, size) in entries {
+            if deadline.passed() {
+                break;
+            }
+            let mut child = TreeNode {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                path: path.clone(),
+                is_dir: matches!(kind, Kind::Dir),
+                size: 0,
+                children: Vec::new(),
+                link: None,
+            };
+            match kind {
+                Kind::Link(target) => {
+                    child.link = Some(target.clone());
+                    node.children.push(child);
+                    continue;
+                }
+                Kind::Dir => {
+                    let depth = path.components().count();
+                    if options.max_depth.is_some_and(|max| depth >= max) {
+                        node.children.push(child);
+                        continue;
+                    }
+                    self.walk_dir(
+                        &mut child, file_paths, excluded, gitignores, deadline, options, log,
+                    )?;
+                    if options.globs.has_includes() && child.children.is_empty() {
+                        continue;
+                    }
+                }
+                Kind::File => {
+                    child.size = size;
+                    let listed_only =
+                        options.hidden == Hidden::TreeOnly && path.iter().any(is_hidden);
+                    if !listed_only {
+                        file_paths.push(path.clone());
+                    }
+                }
+            }
+            node.size += child.size;
+            node.children.push(child);
+        }
+
+        if has_gitignore {
+            gitignores.pop();
+        }
+        Ok(())
+    }
+
+    /// Reads and measures the files at `paths`, in a second pass over the
+    /// archive, and spools their text. Reading stops once `deadline` has
+    /// passed, and the files up to the first one left unread are kept.
+    fn read(
+        &self,
+        paths: &[PathBuf],
+        deadline: &Deadline,
+        options: &ScanOptions,
+        log: &Logger,
+        spool: &mut Spool,
+    ) -> io::Result<Vec<FileEntry>> {
+        let wanted: HashMap<_, _> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (self.prefix.join(path), i))
+            .collect();
+        let mut contents: Vec<Option<FileContent>> = paths.iter().map(|_| None).collect();
+        let mut read = 0;
+        visit(&self.path, |name, _, size, reader| {
+            let Some(&i) = wanted.get(name) else {
+                return Ok(true);
+            };
+            if deadline.passed() {
+                return Ok(false);
+            }
+            let path = &paths[i];
+            read += 1;
+            log.progress("collect", read, Some(paths.len()), path)?;
+            contents[i] = Some(match read_text(reader) {
+                Ok(Some((content, encoding))) => {
+                    let (content, redactions) = redact(content, options);
+                    let measured = Measured::new(path, content, options.count_doc_blocks)
+                        .encoding(encoding)
+                        .redactions(redactions);
+                    if options.max_file_size.is_some_and(|max| size > max) {
+                        FileContent::Text(measured.omit())
+                    } else {
+                        FileContent::Text(measured.spool(spool)?)
+                    }
+                }
+                Ok(None) => FileContent::Binary,
+                Err(err) => {
+                    log.decode_error(&self.path.join(path), &err);
+                    FileContent::Unreadable
+                }
+            });
+            Ok(true)
+        })?;
+
+        let mut files = Vec::with_capacity(paths.len());
+        for (path, content) in paths.iter().zip(contents) {
+            let content = match content {
+                Some(content) => content,
+                None if deadline.expired.load(Ordering::Relaxed) => break,
+                None => FileContent::Missing,
+            };
+            files.push(FileEntry {
+                path: path.clone(),
+                size: self.entries.get(path).map_or(0, |(_, size)| *size),
+                content,
+            });
+        }
+        Ok(files)
+    }
+}
+
+/// The archive suffix of the name of `path`, if it has one.
+fn suffix(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    SUFFIXES.into_iter().find(|suffix| name.ends_with(suffix))
+}
+
+/// Calls `visit` with the path, kind, size and contents of every entry of
+/// the archive at `path`, in the order they are stored, until it returns
+/// `false`. Entries whose paths lead outside the archive are skipped, as
+/// are devices and other special files.
+fn visit(
+    path: &Path,
+    mut visit: impl FnMut(&Path, Kind, u64, &mut dyn Read) -> io::Result<bool>,
+) -> io::Result<()> {
+    let file = BufReader::new(File::open(path)?);
+    if suffix(path) == Some(".zip") {
+        let mut zip = ZipArchive::new(file)?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let Some(name) = relative(Path::new(entry.name())) else {
+                continue;
+            };
+            let kind = if entry.is_dir() {
+                Kind::Dir
+            } else if entry.is_symlink() {
+                // Zip archives store the target as the link's contents.
+                let mut target = String::new();
+                entry.read_to_string(&mut target)?;
+                Kind::Link(PathBuf::from(target))
+            } else {
+                Kind::File
+            };
+            let size = entry.size();
+            if !visit(&name, kind, size, &mut entry)? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+
+    let reader: Box<dyn Read> = if matches!(suffix(path), Some(".tar.gz" | ".tgz")) {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        let kind = if kind.is_dir() {
+            Kind::Dir
+        } else if kind.is_symlink() || kind.is_hard_link() {
+            Kind::Link(entry.link_name()?.unwrap_or_default().into_owned())
+        } else if kind.is_file() || kind.is_contiguous() {
+            Kind::File
+        } else {
+            continue;
+        };
+        let Some(name) = relative(&entry.path()?) else {
+            continue;
+        };
+        let size = entry.size();
+        if !visit(&name, kind, size, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `path` without any `.` components, or `None` if it is absolute or goes
+/// up a directory, or is empty.
+fn relative(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a tar archive named `name` in `dir` holding `files`, given as
+    /// path and content pairs.
+    fn tar(dir: &Path, name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.join(name);
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (file, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, file, content.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    fn paths(archive: &Archive) -> Vec<&Path> {
+        archive.entries.keys().map(PathBuf::as_path).collect()
+    }
+
+    #[test]
+    fn single_top_level_directory_is_stripped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tar(
+            dir.path(),
+            "release.tar",
+            &[("project/src/main.rs", ""), ("project/README.md", "")],
+        );
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.name, "project");
+        assert_eq!(archive.prefix, Path::new("project"));
+        assert_eq!(
+            paths(&archive),
+            [
+                Path::new("README.md"),
+                Path::new("src"),
+                Path::new("src/main.rs")
+            ]
+        );
+    }
+
+    #[test]
+    fn several_top_level_entries_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tar(dir.path(), "release.tar", &[("a/x.rs", ""), ("b.rs", "")]);
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.name, "release");
+        assert_eq!(archive.prefix, Path::new(""));
+        assert_eq!(
+            paths(&archive),
+            [Path::new("a"), Path::new("a/x.rs"), Path::new("b.rs")]
+        );
+    }
+
+    #[test]
+    fn single_top_level_file_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tar(dir.path(), "notes.tar", &[("notes.txt", "")]);
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(archive.name, "notes");
+        assert_eq!(paths(&archive), [Path::new("notes.txt")]);
+    }
+
+    #[test]
+    fn gitignore_texts_are_keyed_without_the_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = tar(
+            dir.path(),
+            "release.tar",
+            &[("project/.gitignore", "gen/\n"), ("project/gen/a.js", "")],
+        );
+        let archive = Archive::open(&path).unwrap();
+        assert_eq!(
+            archive
+                .texts
+                .get(Path::new(".gitignore"))
+                .map(String::as_str),
+            Some("gen/\n")
+        );
+    }
+
+    #[test]
+    fn entries_outside_the_archive_are_rejected() {
+        assert_eq!(relative(Path::new("./a/./b")), Some(PathBuf::from("a/b")));
+        assert_eq!(relative(Path::new("../a")), None);
+        assert_eq!(relative(Path::new("a/../../b")), None);
+        assert_eq!(relative(Path::new("/etc/passwd")), None);
+        assert_eq!(relative(Path::new(".")), None);
+    }
+}
@@ -0,0 +1,1074 @@
+//! Walking a project and reading its files into a [`Report`].
+
+use crate::anonymize::report_path;
+use crate::authors::Authorship;
+use crate::cache::{Cache, Measurements};
+use crate::churn::Churn;
+use crate::config::SensitivePolicy;
+use crate::detect::{self, Detection};
+use crate::events::Logger;
+use crate::ignores::{Ignores, PathGlobs, Rule, SensitiveFiles, TrackedFiles};
+use crate::output::OutputFormat;
+use crate::report::{
+    Excluded, FileContent, FileEntry, Measured, Project, Report, Text, TreeNode, Truncation,
+};
+use crate::secrets;
+use crate::spool::Spool;
+use crate::workspace;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use ignore::gitignore::Gitignore;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::iter;
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info_span;
+use walkdir::{DirEntry, WalkDir};
+
+pub mod archive;
+
+const EXCLUDED_DIRS: [&str; 23] = [
+    ".codetree",
+    ".idea",
+    ".git",
+    ".github",
+    ".gitlab",
+    ".next",
+    ".vscode",
+    ".venv",
+    ".target",
+    ".zig-cache",
+    "node_modules",
+    "assets",
+    "asset",
+    "public",
+    "bin",
+    "build",
+    "cache",
+    "dist",
+    "fonts",
+    "obj",
+    "out",
+    "target",
+    "vendor",
+];
+
+/// The entries of [`EXCLUDED_DIRS`] skipped even with
+/// `--no-default-excludes`: codetree's cache and git's internals.
+const ALWAYS_EXCLUDED_DIRS: [&str; 2] = [".codetree", ".git"];
+
+const EXCLUDED_FILES: [&str; 24] = [
+    ".DS_Store",
+    ".eslintrc.json",
+    ".gitignore",
+    ".npmignore",
+    "Cargo.lock",
+    "eslint.config.js",
+    "favicon.ico",
+    "globals.css",
+    "next.config.mjs",
+    "next-env.d.ts",
+    "postcss.config.js",
+    "postcss.config.mjs",
+    "README.md",
+    "package-lock.json",
+    "pnpm-lock.yaml",
+    "tailwind.config.js",
+    "tailwind.config.ts",
+    "tsconfig.app.json",
+    "tsconfig.node.json",
+    "tsconfig.json",
+    "thumbs.db",
+    "tsconfig.json",
+    "vite.config.ts",
+    "yarn.lock",
+];
+
+/// Settings shared by every pass over a project.
+pub struct ScanOptions {
+    /// Name of the running executable, which is never included.
+    pub script_name: String,
+    pub ignores: Ignores,
+    /// `--exclude` and `--include` patterns.
+    pub globs: PathGlobs,
+    /// If set, only these files are included.
+    pub tracked: Option<TrackedFiles>,
+    /// Commit counts from the git history, if hotspots are to be reported.
+    pub churn: Option<Churn>,
+    /// Attribute the lines of every file to their authors with `git blame`.
+    pub authors: bool,
+    /// Attribute code blocks in documents to their own languages.
+    pub count_doc_blocks: bool,
+    /// How long a scan may take before it stops early.
+    pub timeout: Option<Duration>,
+    /// How many files a scan reads at most.
+    pub max_files: Option<usize>,
+    /// How many directory levels below the root a scan descends.
+    pub max_depth: Option<usize>,
+    /// Size in bytes above which a file is measured but its content left
+    /// out of the report.
+    pub max_file_size: Option<u64>,
+    /// Walk into symlinked directories and read symlinked files, instead of
+    /// listing symlinks with their targets.
+    pub follow_symlinks: bool,
+    /// What to do with dotfiles and dot-directories that are not excluded
+    /// by any other rule.
+    pub hidden: Hidden,
+    /// Whether the built-in lists of excluded directories and files apply.
+    pub default_excludes: bool,
+    /// Directory and file names excluded by configuration files.
+    pub excluded_dirs: Vec<String>,
+    pub excluded_files: Vec<String>,
+    /// Files that may hold secrets, which are never included.
+    pub sensitive: SensitiveFiles,
+    /// Replace secrets found in file contents with markers.
+    pub redact: bool,
+    /// File name of the report being written, which is never included.
+    pub output_name: Option<OsString>,
+    /// Project ecosystems found in the root, whose exclusions are part of
+    /// `ignores`.
+    pub ecosystems: Vec<Detection>,
+    /// Where measurements are remembered between scans, if anywhere.
+    pub cache: Option<PathBuf>,
+    /// Largest size in bytes the cache may take, see [`Cache::save`].
+    pub cache_max_size: Option<u64>,
+    /// Whether the report needs the contents of files. Without them, files
+    /// found unchanged in the cache are not opened at all.
+    pub keep_contents: bool,
+}
+
+impl ScanOptions {
+    /// The defaults of the command-line tool for scanning `root`: ecosystems
+    /// are detected, git's ignore files are honored and nothing is limited.
+    pub fn new(root: &Path, log: &Logger) -> io::Result<Self> {
+        let ecosystems = detect(root)?;
+        Ok(ScanOptions {
+            script_name: String::new(),
+            ignores: Ignores::new(root, &[], true, &ecosystems, log)?,
+            globs: PathGlobs::new(&[], &[])?,
+            tracked: None,
+            churn: None,
+            authors: false,
+            count_doc_blocks: false,
+            timeout: None,
+            max_files: None,
+            max_depth: None,
+            max_file_size: None,
+            follow_symlinks: false,
+            hidden: Hidden::Include,
+            default_excludes: true,
+            excluded_dirs: Vec::new(),
+            excluded_files: Vec::new(),
+            sensitive: SensitiveFiles::new(&SensitivePolicy::default())?,
+            redact: true,
+            output_name: None,
+            ecosystems,
+            cache: None,
+            cache_max_size: None,
+            keep_contents: true,
+        })
+    }
+}
+
+/// How a scan treats dotfiles and dot-directories, such as `.eslintrc.js`
+/// or `.husky/`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hidden {
+    /// Analyzed like any other file.
+    Include,
+    /// Listed in the tree, but their contents are not read.
+    TreeOnly,
+    /// Skipped entirely.
+    Exclude,
+}
+
+/// The project ecosystems found in `start_dir`, a directory or an archive.
+pub fn detect(start_dir: &Path) -> io::Result<Vec<Detection>> {
+    if archive::is_archive(start_dir) {
+        archive::detect(start_dir)
+    } else {
+        Ok(detect::detect(start_dir))
+    }
+}
+
+/// Walks `start_dir` and reads the contents of every included file. An
+/// archive given as `start_dir` is scanned as the directory it holds.
+pub fn scan_project(start_dir: &Path, options: &ScanOptions, log: &Logger) -> io::Result<Report> {
+    if archive::is_archive(start_dir) {
+        return archive::scan(start_dir, options, log);
+    }
+    let mut root = TreeNode {
+        name: start_dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| {
+                dir.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| start_dir.display().to_string()),
+        path: PathBuf::new(),
+        is_dir: true,
+        size: 0,
+        children: Vec::new(),
+        link: None,
+    };
+    let mut file_paths = Vec::new();
+    let mut excluded = Vec::new();
+    let deadline = Deadline::after(options.timeout);
+    let mut visited = HashSet::new();
+    if options.follow_symlinks {
+        visited.extend(dir_id(start_dir).ok());
+    }
+
+    info_span!("walk").in_scope(|| {
+        walk_dir(
+            start_dir,
+            start_dir,
+            &mut root,
+            &mut file_paths,
+            &mut excluded,
+            &mut Vec::new(),
+            &mut visited,
+            &deadline,
+            options,
+            log,
+        )
+    })?;
+
+    let _collect = info_span!("collect").entered();
+
+    let mut spool = Spool::new()?;
+    let cached = match &options.cache {
+        Some(path) => Cache::load(path, options.count_doc_blocks, options.redact),
+        None => Cache::new(options.count_doc_blocks, options.redact),
+    };
+    let mut cache = Cache::new(options.count_doc_blocks, options.redact);
+    let limit = options
+        .max_files
+        .map_or(file_paths.len(), |max| max.min(file_paths.len()));
+    let files = read_files(
+        start_dir,
+        &file_paths[..limit],
+        &deadline,
+        options,
+        log,
+        &mut spool,
+        &cached,
+        &mut cache,
+    )?;
+    if let Some(path) = &options.cache {
+        cache.save(path, options.cache_max_size)?;
+    }
+    drop(_collect);
+    let authors = options
+        .authors
+        .then(|| info_span!("blame").in_scope(|| Authorship::blame(start_dir, &files)))
+        .transpose()?;
+
+    Ok(Report {
+        project_name: root.name.clone(),
+        root,
+        files,
+        spool,
+        ecosystems: options.ecosystems.clone(),
+        churn: options.churn.clone(),
+        authors,
+        projects: workspace::members(start_dir)
+            .into_iter()
+            .map(|path| Project {
+                ecosystems: detect::detect(&start_dir.join(&path)),
+                path,
+            })
+            .collect(),
+        truncated: match options.timeout {
+            Some(timeout) if deadline.expired.load(Ordering::Relaxed) => {
+                Some(Truncation::Timeout(timeout))
+            }
+            _ if limit < file_paths.len() => Some(Truncation::MaxFiles(limit)),
+            _ => None,
+        },
+        excluded,
+    })
+}
+
+/// Walks `start_dir` like [`scan_project`] and returns the paths, relative
+/// to `start_dir`, of the files whose contents a scan would read, without
+/// reading any of them.
+pub fn list_files(
+    start_dir: &Path,
+    options: &ScanOptions,
+    log: &Logger,
+) -> io::Result<Vec<PathBuf>> {
+    if archive::is_archive(start_dir) {
+        return archive::list_files(start_dir, options, log);
+    }
+    let mut root = TreeNode {
+        name: String::new(),
+        path: PathBuf::new(),
+        is_dir: true,
+        size: 0,
+        children: Vec::new(),
+        link: None,
+    };
+    let mut file_paths = Vec::new();
+    let mut visited = HashSet::new();
+    if options.follow_symlinks {
+        visited.extend(dir_id(start_dir).ok());
+    }
+    walk_dir(
+        start_dir,
+        start_dir,
+        &mut root,
+        &mut file_paths,
+        &mut Vec::new(),
+        &mut Vec::new(),
+        &mut visited,
+        &Deadline::after(options.timeout),
+        options,
+        log,
+    )?;
+    file_paths.truncate(options.max_files.unwrap_or(usize::MAX));
+    Ok(file_paths
+        .into_iter()
+        .map(|path| path.strip_prefix(start_dir).unwrap_or(&path).to_path_buf())
+        .collect())
+}
+
+/// Whether a path would be part of a scan, as told by [`explain`].
+pub enum Verdict {
+    /// Walked, for a directory, or read and analyzed, for a file.
+    Included,
+    /// Listed in the tree without being read or followed, for the given
+    /// reason.
+    Listed(&'static str),
+    /// Left out, along with everything below it, by the rule that matched
+    /// the path itself or a directory above it.
+    Excluded(Excluded),
+    /// Not found below the scanned directory.
+    NotFound,
+}
+
+/// Tells whether `path`, relative to `start_dir`, would be part of a scan
+/// with `options`. The path and every directory above it are checked
+/// against the same rules, in the same order, as during a scan.
+pub fn explain(
+    start_dir: &Path,
+    path: &Path,
+    options: &ScanOptions,
+    log: &Logger,
+) -> io::Result<Verdict> {
+    let mut gitignores: Vec<_> = options
+        .ignores
+        .load_gitignore(start_dir, log)
+        .into_iter()
+        .collect();
+    let mut current = start_dir.to_path_buf();
+    let depth = path.components().count();
+    for (level, component) in path.components().enumerate() {
+        current.push(component);
+        let Ok(metadata) = fs::symlink_metadata(&current) else {
+            return Ok(Verdict::NotFound);
+        };
+        let symlink = metadata.file_type().is_symlink();
+        let is_dir = metadata.is_dir() || (options.follow_symlinks && symlink && current.is_dir());
+        let excluded = |rule: String| {
+            Verdict::Excluded(Excluded {
+                path: current
+                    .strip_prefix(start_dir)
+                    .unwrap_or(&current)
+                    .to_path_buf(),
+                rule,
+            })
+        };
+        if let Some(rule) = exclusion(start_dir, &current, is_dir, options, &gitignores) {
+            return Ok(excluded(rule.to_string()));
+        }
+        let last = level + 1 == depth;
+        if symlink && !(options.follow_symlinks && current.exists()) {
+            return Ok(if last {
+                Verdict::Listed("symlinks are listed with their targets, not followed")
+            } else {
+                excluded("symlink not followed".to_string())
+            });
+        }
+        if last {
+            break;
+        }
+        // `current` is a directory on the way to `path`.
+        if let Some(max) = options.max_depth.filter(|&max| level + 1 >= max) {
+            return Ok(excluded(format!("--max-depth {}", max)));
+        }
+        gitignores.extend(options.ignores.load_gitignore(&current, log));
+    }
+
+    if options.hidden == Hidden::TreeOnly && !current.is_dir() && path.iter().any(is_hidden) {
+        return Ok(Verdict::Listed(
+            "hidden files are listed without their contents",
+        ));
+    }
+    Ok(Verdict::Included)
+}
+
+/// Brings `report` up to date with changes to `paths`, relative to
+/// `start_dir`, by re-reading only the files whose contents changed. The
+/// new text is appended to the spool, leaving the old text unused. Returns
+/// how many files were re-read, or `None` without touching the report if a
+/// change affects the tree itself, such as a file being created or
+/// removed, in which case the project has to be scanned again.
+pub fn refresh(
+    report: &mut Report,
+    start_dir: &Path,
+    paths: &[PathBuf],
+    options: &ScanOptions,
+    log: &Logger,
+) -> io::Result<Option<usize>> {
+    let mut changed = Vec::new();
+    for path in paths {
+        let exists = start_dir.join(path).exists();
+        match find(&report.root, path) {
+            Some(node) if node.is_dir => {
+                if !exists {
+                    return Ok(None);
+                }
+            }
+            Some(_) => match report.files.iter().position(|file| file.path == *path) {
+                Some(i) if start_dir.join(path).is_file() => changed.push(i),
+                // Removed, replaced by a directory, or listed but never read.
+                _ => return Ok(None),
+            },
+            None => {
+                // Changes below excluded or unexpanded directories do not
+                // show in the report, while new entries next to listed
+                // ones might.
+                let parent = path.parent().unwrap_or(Path::new(""));
+                let expanded = options
+                    .max_depth
+                    .is_none_or(|max| parent.components().count() < max);
+                if exists && expanded && find(&report.root, parent).is_some_and(|p| p.is_dir) {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    for &i in &changed {
+        let file = start_dir.join(&report.files[i].path);
+        let size = fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+        let content = match File::open(&file).and_then(read_text) {
+            Ok(Some((content, encoding))) => {
+                let (content, redactions) = redact(content, options);
+                let measured =
+                    Measured::new(&report.files[i].path, content, options.count_doc_blocks)
+                        .encoding(encoding)
+                        .redactions(redactions);
+                FileContent::Text(match options.max_file_size {
+                    Some(max) if size > max => measured.omit(),
+                    _ => measured.spool(&mut report.spool)?,
+                })
+            }
+            Ok(None) => FileContent::Binary,
+            Err(err) => {
+                log.decode_error(&file, &err);
+                FileContent::Unreadable
+            }
+        };
+        let entry = &mut report.files[i];
+        resize(&mut report.root, &entry.path, entry.size, size);
+        entry.size = size;
+        entry.content = content;
+    }
+    Ok(Some(changed.len()))
+}
+
+/// The node at `path` below `node`, or `node` itself for an empty path.
+fn find<'a>(node: &'a TreeNode, path: &Path) -> Option<&'a TreeNode> {
+    if node.path == path {
+        return Some(node);
+    }
+    node.children
+        .iter()
+        .find(|child| path.starts_with(&child.path))
+        .and_then(|child| find(child, path))
+}
+
+/// Updates the size of the file at `path` from `old` to `new` bytes, along
+/// with the totals of every directory above it.
+fn resize(node: &mut TreeNode, path: &Path, old: u64, new: u64) {
+    node.size = node.size - old + new;
+    if let Some(child) = node
+        .children
+        .iter_mut()
+        .find(|child| path.starts_with(&child.path))
+    {
+        resize(child, path, old, new);
+    }
+}
+
+/// The point at which a scan with a timeout stops early.
+struct Deadline {
+    at: Option<Instant>,
+    /// Set once [`Deadline::passed`] has returned `true`, so that the scan
+    /// is known to have skipped something.
+    expired: AtomicBool,
+}
+
+impl Deadline {
+    fn after(timeout: Option<Duration>) -> Self {
+        Deadline {
+            at: timeout.map(|timeout| Instant::now() + timeout),
+            expired: AtomicBool::new(false),
+        }
+    }
+
+    fn passed(&self) -> bool {
+        if self.at.is_some_and(|at| Instant::now() >= at) {
+            self.expired.store(true, Ordering::Relaxed);
+        }
+        self.expired.load(Ordering::Relaxed)
+    }
+}
+
+/// A file as read and measured by a worker, before its text is spooled.
+enum ReadFile {
+    Text(Measured),
+    /// Unchanged since it was cached, and left unread.
+    Cached(Measurements),
+    Binary,
+    Unreadable(io::Error),
+    Missing,
+}
+
+/// Bytes at the start of a file inspected to tell binary files apart, as
+/// git does.
+const BINARY_PROBE: u64 = 8 * 1024;
+
+/// Reads a file from `reader` as text, along with the name of the encoding
+/// it was decoded from, or returns `None` without reading further if its
+/// first bytes show it is binary.
+///
+/// UTF-16 is recognized by its byte order mark. Files that are not valid
+/// UTF-8 are decoded from the legacy encoding they most likely use, such as
+/// windows-1252 or Shift_JIS, with any undecodable bytes replaced.
+fn read_text(mut file: impl Read) -> io::Result<Option<(String, &'static str)>> {
+    let mut bytes = Vec::new();
+    file.by_ref().take(BINARY_PROBE).read_to_end(&mut bytes)?;
+    let utf16 = Encoding::for_bom(&bytes).filter(|(encoding, _)| *encoding != UTF_8);
+    if utf16.is_none() && is_binary(&bytes) {
+        return Ok(None);
+    }
+    file.read_to_end(&mut bytes)?;
+
+    let encoding = match utf16 {
+        Some((encoding, _)) => encoding,
+        None => match String::from_utf8(bytes) {
+            Ok(content) => return Ok(Some((content, UTF_8.name()))),
+            Err(err) => {
+                bytes = err.into_bytes();
+                let mut detector = EncodingDetector::new();
+                detector.feed(&bytes, true);
+                detector.guess(None, true)
+            }
+        },
+    };
+    let (content, _) = encoding.decode_with_bom_removal(&bytes);
+    Ok(Some((content.into_owned(), encoding.name())))
+}
+
+/// Replaces the secrets in `content` with markers, unless `--no-redact` was
+/// given, returning the text and the number of secrets replaced.
+fn redact(content: String, options: &ScanOptions) -> (String, usize) {
+    if options.redact {
+        secrets::redact(content)
+    } else {
+        (content, 0)
+    }
+}
+
+/// Whether `probe`, the start of a file, looks like binary data: it holds
+/// a NUL byte, or it is not UTF-8 and more than a tenth of it are control
+/// characters, which text in legacy encodings rarely has.
+fn is_binary(probe: &[u8]) -> bool {
+    if probe.contains(&0) {
+        return true;
+    }
+    // A character cut off at the end of the probe is still valid text.
+    let utf8 = std::str::from_utf8(probe).map_or_else(|err| err.error_len().is_none(), |_| true);
+    if utf8 {
+        return false;
+    }
+    let control = probe
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    control * 10 > probe.len()
+}
+
+/// Reads and measures `paths` on a pool of worker threads, then spools
+/// their text in order. Workers stop picking up files once `deadline` has
+/// passed, and the files read up to the first one they skipped are kept.
+/// Measurements are taken from `cached` where the file is unchanged, and
+/// every file's are recorded in `cache`.
+#[allow(clippy::too_many_arguments)]
+fn read_files(
+    start_dir: &Path,
+    paths: &[PathBuf],
+    deadline: &Deadline,
+    options: &ScanOptions,
+    log: &Logger,
+    spool: &mut Spool,
+    cached: &Cache,
+    cache: &mut Cache,
+) -> io::Result<Vec<FileEntry>> {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len().max(1));
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let (sender, next) = (sender.clone(), &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= paths.len() || deadline.passed() {
+                    break;
+                }
+                let file = &paths[i];
+                let path = file.strip_prefix(start_dir).unwrap_or(file);
+                let metadata = fs::metadata(file).ok();
+                let known = metadata.as_ref().and_then(|m| cached.get(path, m));
+                let too_large = metadata
+                    .as_ref()
+                    .is_some_and(|m| options.max_file_size.is_some_and(|max| m.len() > max));
+                let read = if !file.exists() {
+                    ReadFile::Missing
+                } else {
+                    match known {
+                        Some(known) if !options.keep_contents || too_large => {
+                            ReadFile::Cached(known)
+                        }
+                        known => match File::open(file).and_then(read_text) {
+                            Ok(Some((content, encoding))) => {
+                                let (content, redactions) = redact(content, options);
+                                ReadFile::Text(match known {
+                                    Some(known) => Measured::cached(content, known),
+                                    None => Measured::new(path, content, options.count_doc_blocks)
+                                        .encoding(encoding)
+                                        .redactions(redactions),
+                                })
+                            }
+                            Ok(None) => ReadFile::Binary,
+                            Err(err) => ReadFile::Unreadable(err),
+                        },
+                    }
+                };
+                // The receiver is gone if spooling failed; nothing left to do.
+                if sender.send((i, metadata, too_large, read)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Results arrive in any order; keep them until their turn comes.
+        let mut pending = HashMap::new();
+        let mut files = Vec::with_capacity(paths.len());
+        for (i, metadata, too_large, read) in receiver {
+            pending.insert(i, (metadata, too_large, read));
+            while let Some((metadata, too_large, read)) = pending.remove(&files.len()) {
+                let file = &paths[files.len()];
+                let path = file.strip_prefix(start_dir).unwrap_or(file);
+                log.progress("collect", files.len() + 1, Some(paths.len()), path)?;
+
+                let content = match read {
+                    ReadFile::Text(measured) if too_large => FileContent::Text(measured.omit()),
+                    ReadFile::Text(measured) => FileContent::Text(measured.spool(spool)?),
+                    ReadFile::Cached(known) => FileContent::Text(Text {
+                        lines: known.lines,
+                        counts: known.counts,
+                        tokens: known.tokens,
+                        script: known.script,
+                        encoding: known.encoding,
+                        redactions: known.redactions,
+                        sha256: known.sha256,
+                        sections: known.sections,
+                        spooled: None,
+                        too_large,
+                    }),
+                    ReadFile::Binary => FileContent::Binary,
+                    ReadFile::Unreadable(err) => {
+                        log.decode_error(file, &err);
+                        FileContent::Unreadable
+                    }
+                    ReadFile::Missing => FileContent::Missing,
+                };
+                if let (FileContent::Text(text), Some(metadata)) = (&content, &metadata) {
+                    cache.insert(path, metadata, text);
+                }
+                files.push(FileEntry {
+                    path: path.to_path_buf(),
+                    size: metadata.map_or(0, |m| m.len()),
+                    content,
+                });
+            }
+        }
+        Ok(files)
+    })
+}
+
+/// Adds the entries of `dir` to `node`, recursing into subdirectories, and
+/// records every included file in `file_paths` and every skipped path in
+/// `excluded`. Stops adding entries once `deadline` has passed.
+///
+/// Symlinks are listed with their targets, unless `--follow-symlinks` is
+/// given; then every directory walked is added to `visited`, and one
+/// reached again, as through a symlink cycle, is listed without being
+/// expanded a second time.
+#[allow(clippy::too_many_arguments)]
+fn walk_dir(
+    start_dir: &Path,
+    dir: &Path,
+    node: &mut TreeNode,
+    file_paths: &mut Vec<PathBuf>,
+    excluded: &mut Vec<Excluded>,
+    gitignores: &mut Vec<Gitignore>,
+    visited: &mut HashSet<DirId>,
+    deadline: &Deadline,
+    options: &ScanOptions,
+    log: &Logger,
+) -> io::Result<()> {
+    let relative = dir.strip_prefix(start_dir).unwrap_or(dir);
+    log.progress("walk", file_paths.len(), None, relative)?;
+
+    let has_gitignore = match options.ignores.load_gitignore(dir, log) {
+        Some(gitignore) => {
+            gitignores.push(gitignore);
+            true
+        }
+        None => false,
+    };
+
+    let mut entries: Vec<_> = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_entry(|e| {
+            match exclusion(start_dir, e.path(), is_dir(e, options), options, gitignores) {
+                Some(rule) => {
+                    skip(e.path(), &rule, start_dir, excluded, log);
+                    false
+                }
+                None => true,
+            }
+        })
+        .filter_map(|e| e.ok())
+        .collect();
+
+    // Real directories come before symlinked ones, so that a directory
+    // reachable both ways is walked under its own name.
+    entries.sort_by_key(|a| {
+        (
+            !is_dir(a, options),
+            a.path_is_symlink(),
+            a.file_name().to_string_lossy().to_string(),
+        )
+    });
+
+    for entry in &entries {
+        if deadline.passed() {
+            break;
+        }
+        let mut child = TreeNode {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: entry
+                .path()
+                .strip_prefix(start_dir)
+                .unwrap_or(entry.path())
+                .to_path_buf(),
+            is_dir: is_dir(entry, options),
+            size: 0,
+            children: Vec::new(),
+            link: None,
+        };
+        // Broken symlinks are listed even when following them.
+        if entry.path_is_symlink() && !(options.follow_symlinks && entry.path().exists()) {
+            child.link = Some(link_target(entry.path(), start_dir));
+            node.children.push(child);
+            continue;
+        }
+        if child.is_dir {
+            // Directories at the depth limit are listed but not expanded.
+            let depth = child.path.components().count();
+            if options.max_depth.is_some_and(|max| depth >= max) {
+                node.children.push(child);
+                continue;
+            }
+            if options.follow_symlinks && dir_id(entry.path()).is_ok_and(|id| !visited.insert(id)) {
+                skip(entry.path(), &Rule::Revisited, start_dir, excluded, log);
+                if entry.path_is_symlink() {
+                    child.is_dir = false;
+                    child.link = Some(link_target(entry.path(), start_dir));
+                }
+                node.children.push(child);
+                continue;
+            }
+            walk_dir(
+                start_dir,
+                entry.path(),
+                &mut child,
+                file_paths,
+                excluded,
+                gitignores,
+                visited,
+                deadline,
+                options,
+                log,
+            )?;
+            // With --include, directories holding no included file are noise.
+            if options.globs.has_includes() && child.children.is_empty() {
+                continue;
+            }
+        } else {
+            child.size = fs::metadata(entry.path()).map_or(0, |m| m.len());
+            let listed_only =
+                options.hidden == Hidden::TreeOnly && child.path.iter().any(is_hidden);
+            if !listed_only {
+                file_paths.push(entry.path().to_path_buf());
+            }
+        }
+        node.size += child.size;
+        node.children.push(child);
+    }
+
+    if has_gitignore {
+        gitignores.pop();
+    }
+    Ok(())
+}
+
+/// Logs that `path` was skipped because of `rule`, and records it.
+fn skip(path: &Path, rule: &Rule, start_dir: &Path, excluded: &mut Vec<Excluded>, log: &Logger) {
+    log.skipped(path, rule);
+    excluded.push(Excluded {
+        path: path.strip_prefix(start_dir).unwrap_or(path).to_path_buf(),
+        rule: rule.in_report(start_dir),
+    });
+}
+
+/// Target of the symlink at `path`. An absolute target inside the project
+/// is rewritten relative to the link, and one outside it as
+/// [`report_path`] does, so the report does not reveal where the project
+/// was checked out.
+fn link_target(path: &Path, start_dir: &Path) -> PathBuf {
+    let target = fs::read_link(path).unwrap_or_default();
+    let Ok(root) = std::path::absolute(start_dir) else {
+        return target;
+    };
+    let Ok(inside) = target.strip_prefix(&root) else {
+        return report_path(&target, &root);
+    };
+    let depth = path.strip_prefix(start_dir).map_or(0, |relative| {
+        relative.components().count().saturating_sub(1)
+    });
+    let mut relative: PathBuf = iter::repeat_n(Component::ParentDir, depth).collect();
+    relative.extend(inside.components());
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// Whether a file or directory name marks it as hidden.
+fn is_hidden(name: &OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+/// Whether `entry` is a directory, or a symlink to one that is followed.
+fn is_dir(entry: &DirEntry, options: &ScanOptions) -> bool {
+    entry.file_type().is_dir()
+        || (options.follow_symlinks && entry.path_is_symlink() && entry.path().is_dir())
+}
+
+/// Identifies a directory however it is reached: its device and inode
+/// numbers, or its canonical path where those are not available.
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    fs::canonicalize(path)
+}
+
+/// The rule that leaves `path`, found in a scan of `start_dir`, out of the
+/// report along with everything below it, if any. `gitignores` are the
+/// `.gitignore` files of the directories above `path`.
+fn exclusion<'a>(
+    start_dir: &Path,
+    path: &Path,
+    is_dir: bool,
+    options: &'a ScanOptions,
+    gitignores: &'a [Gitignore],
+) -> Option<Rule<'a>> {
+    let name = path.file_name().unwrap_or_default();
+    let builtin_dirs: &[&str] = if options.default_excludes {
+        &EXCLUDED_DIRS
+    } else {
+        &ALWAYS_EXCLUDED_DIRS
+    };
+    if is_dir && builtin_dirs.contains(&name.to_str().unwrap_or("")) {
+        return Some(Rule::BuiltinDir);
+    }
+    if is_dir && is_listed(path, &options.excluded_dirs) {
+        return Some(Rule::Config);
+    }
+    if let Some(rule) = options.ignores.matched(path, is_dir, gitignores) {
+        return Some(rule);
+    }
+    if options.hidden == Hidden::Exclude && is_hidden(name) {
+        return Some(Rule::Hidden);
+    }
+    let relative = path.strip_prefix(start_dir).unwrap_or(path);
+    if let Some(rule) = options.globs.matched(relative, is_dir) {
+        return Some(rule);
+    }
+    if let Some(tracked) = &options.tracked {
+        if !tracked.contains(relative, is_dir) {
+            return Some(Rule::Untracked);
+        }
+    }
+    if name.to_string_lossy() == options.script_name
+        || OutputFormat::is_output_name(&name.to_string_lossy())
+        || options.output_name.as_deref() == Some(name)
+        || (!is_dir && is_cache(path, options))
+    {
+        return Some(Rule::Codetree);
+    }
+    if options.default_excludes && EXCLUDED_FILES.contains(&name.to_str().unwrap_or("")) {
+        return Some(Rule::BuiltinFile);
+    }
+    if !is_dir {
+        if let Some(rule) = options.sensitive.matched(relative) {
+            return Some(rule);
+        }
+    }
+    if !is_dir && is_listed(path, &options.excluded_files) {
+        return Some(Rule::Config);
+    }
+    None
+}
+
+/// Whether `path` is the cache the scan reads and writes, which may be kept
+/// inside the project.
+fn is_cache(path: &Path, options: &ScanOptions) -> bool {
+    let Some(cache) = &options.cache else {
+        return false;
+    };
+    if cache.file_name() != path.file_name() {
+        return false;
+    }
+    match (fs::canonicalize(cache), fs::canonicalize(path)) {
+        (Ok(cache), Ok(path)) => cache == path,
+        _ => false,
+    }
+}
+
+/// Whether the file name of `path` is one of `names`.
+fn is_listed(path: &Path, names: &[String]) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    names.iter().any(|listed| *listed == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A project of `count` files whose `n`th, in walk order, holds `n + 1`
+    /// lines, and sizes that vary enough for workers to finish out of order.
+    fn project(count: usize) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for n in 0..count {
+            let line = "x".repeat(if n % 7 == 0 { 4096 } else { 8 });
+            let content = format!("{}\n", line).repeat(n + 1);
+            fs::write(dir.path().join(format!("f{:03}.txt", n)), content).unwrap();
+        }
+        dir
+    }
+
+    fn lines(report: &Report) -> Vec<usize> {
+        report.files.iter().map(FileEntry::line_count).collect()
+    }
+
+    #[test]
+    fn files_are_reported_in_walk_order() {
+        let dir = project(64);
+        let log = Logger::silent();
+        let options = ScanOptions::new(dir.path(), &log).unwrap();
+        let report = scan_project(dir.path(), &options, &log).unwrap();
+        let paths: Vec<_> = report.files.iter().map(|file| file.path.clone()).collect();
+        assert_eq!(paths, list_files(dir.path(), &options, &log).unwrap());
+        assert_eq!(lines(&report), (1..=64).collect::<Vec<_>>());
+        assert!(report.truncated.is_none());
+    }
+
+    #[test]
+    fn max_files_keeps_the_first_files() {
+        let dir = project(20);
+        let log = Logger::silent();
+        let mut options = ScanOptions::new(dir.path(), &log).unwrap();
+        options.max_files = Some(5);
+        let report = scan_project(dir.path(), &options, &log).unwrap();
+        assert_eq!(lines(&report), [1, 2, 3, 4, 5]);
+        assert!(matches!(report.truncated, Some(Truncation::MaxFiles(5))));
+    }
+
+    #[test]
+    fn expired_deadline_reads_no_files() {
+        let dir = project(20);
+        let log = Logger::silent();
+        let mut options = ScanOptions::new(dir.path(), &log).unwrap();
+        options.timeout = Some(Duration::ZERO);
+        let report = scan_project(dir.path(), &options, &log).unwrap();
+        assert!(report.files.is_empty());
+        assert!(matches!(report.truncated, Some(Truncation::Timeout(_))));
+    }
+
+    #[test]
+    fn files_read_before_the_deadline_are_a_prefix() {
+        let dir = project(200);
+        let log = Logger::silent();
+        let options = ScanOptions::new(dir.path(), &log).unwrap();
+        let paths = list_files(dir.path(), &options, &log).unwrap();
+        let paths: Vec<_> = paths.iter().map(|path| dir.path().join(path)).collect();
+        let deadline = Deadline::after(Some(Duration::from_millis(1)));
+        let cache = Cache::new(false, true);
+        let files = read_files(
+            dir.path(),
+            &paths,
+            &deadline,
+            &options,
+            &log,
+            &mut Spool::new().unwrap(),
+            &cache,
+            &mut Cache::new(false, true),
+        )
+        .unwrap();
+        let lines: Vec<_> = files.iter().map(FileEntry::line_count).collect();
+        assert_eq!(lines, (1..=files.len()).collect::<Vec<_>>());
+    }
+}
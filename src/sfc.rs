@@ -0,0 +1,81 @@
+use crate::language::by_extension;
+
+/// Lines and bytes of a single-file component attributed to one language.
+pub struct Section {
+    pub language: &'static str,
+    pub lines: usize,
+    pub bytes: u64,
+}
+
+/// Splits a Vue or Svelte single-file component into its top-level
+/// `<script>`, `<template>` and `<style>` blocks, each attributed to the
+/// language in its `lang` attribute or to JavaScript, HTML and CSS by
+/// default. Only tags starting at the beginning of a line count as
+/// top-level, which keeps nested `<template>` elements inside their block.
+///
+/// Returns `None` for other languages. Lines outside any block are left out
+/// and stay with the component's own language.
+pub fn sections(language: &str, content: &str) -> Option<Vec<Section>> {
+    if language != "vue" && language != "svelte" {
+        return None;
+    }
+    let mut sections: Vec<Section> = Vec::new();
+    let mut open: Option<(&'static str, &'static str)> = None;
+    for line in content.lines() {
+        if open.is_none() {
+            open = BLOCKS
+                .iter()
+                .find(|(tag, _, _)| opens(line, tag))
+                .map(|&(tag, closing, default)| (closing, block_language(line, tag, default)));
+        }
+        let Some((closing, language)) = open else {
+            continue;
+        };
+        add_line(&mut sections, language, line);
+        if line.contains(closing) {
+            open = None;
+        }
+    }
+    Some(sections)
+}
+
+/// Counts `line` towards the section of `language`, creating it if needed.
+pub fn add_line(sections: &mut Vec<Section>, language: &'static str, line: &str) {
+    match sections.iter_mut().find(|s| s.language == language) {
+        Some(section) => {
+            section.lines += 1;
+            section.bytes += line.len() as u64 + 1;
+        }
+        None => sections.push(Section {
+            language,
+            lines: 1,
+            bytes: line.len() as u64 + 1,
+        }),
+    }
+}
+
+/// Opening tag, closing tag and default language of each block.
+const BLOCKS: [(&str, &str, &str); 3] = [
+    ("<script", "</script>", "javascript"),
+    ("<template", "</template>", "html"),
+    ("<style", "</style>", "css"),
+];
+
+fn opens(line: &str, tag: &str) -> bool {
+    line.strip_prefix(tag)
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|next| next == '>' || next.is_whitespace())
+}
+
+/// The language named by the tag's `lang` attribute, if known.
+fn block_language(line: &str, tag: &str, default: &'static str) -> &'static str {
+    let attributes = &line[tag.len()..];
+    let Some(value) = attributes.split_once("lang=").map(|(_, value)| value) else {
+        return default;
+    };
+    let value = value.trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| c == '"' || c == '\'' || c == '>' || c.is_whitespace())
+        .unwrap_or(value.len());
+    by_extension(&value[..end]).unwrap_or(default)
+}
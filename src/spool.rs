@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// File contents written to an anonymous temporary file as they are
+/// collected, so a report only holds one file's text in memory at a time.
+/// The file is deleted by the operating system once the spool is dropped.
+pub struct Spool {
+    file: File,
+    len: u64,
+}
+
+/// Where one file's text lives in the [`Spool`].
+#[derive(Clone, Copy)]
+pub struct Spooled {
+    offset: u64,
+    len: usize,
+}
+
+impl Spool {
+    pub fn new() -> io::Result<Self> {
+        Ok(Spool {
+            file: tempfile::tempfile()?,
+            len: 0,
+        })
+    }
+
+    pub fn append(&mut self, content: &str) -> io::Result<Spooled> {
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(content.as_bytes())?;
+        let spooled = Spooled {
+            offset: self.len,
+            len: content.len(),
+        };
+        self.len += content.len() as u64;
+        Ok(spooled)
+    }
+
+    pub fn read(&self, spooled: Spooled) -> io::Result<String> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(spooled.offset))?;
+        let mut bytes = vec![0; spooled.len];
+        file.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Wall-clock duration of each closed span, in the order the spans closed.
+pub type PhaseTimings = Arc<Mutex<Vec<(&'static str, Duration)>>>;
+
+/// Tracing layer that records how long each span stayed open.
+pub struct TimingLayer {
+    timings: PhaseTimings,
+}
+
+struct SpanStart(Instant);
+
+impl TimingLayer {
+    pub fn new() -> (Self, PhaseTimings) {
+        let timings = PhaseTimings::default();
+        (
+            TimingLayer {
+                timings: Arc::clone(&timings),
+            },
+            timings,
+        )
+    }
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let elapsed = span
+            .extensions()
+            .get::<SpanStart>()
+            .map(|SpanStart(start)| start.elapsed());
+        if let (Some(elapsed), Ok(mut timings)) = (elapsed, self.timings.lock()) {
+            timings.push((span.name(), elapsed));
+        }
+    }
+}
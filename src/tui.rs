@@ -0,0 +1,504 @@
+//! Browsing a report in the terminal: a tree of the project, statistics and
+//! a preview of the file under the cursor, and a selection of the files to
+//! write the report for.
+
+use crate::output::format_size;
+use crate::report::{FileContent, FileEntry, Report, TreeNode};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Keys shown at the bottom of the screen.
+const HELP: &str = "↑↓ move  → open  ← close  space select  / search  n/N next/previous  \
+                    PgUp/PgDn scroll  w write  q quit";
+
+/// Lines of the preview skipped by PgUp and PgDn.
+const PAGE: usize = 20;
+
+/// Shows `report` until the user quits, returning `None`, or asks for the
+/// report to be written, returning the paths of the files selected for it.
+/// Every file starts out selected.
+pub fn run(report: &Report) -> io::Result<Option<HashSet<PathBuf>>> {
+    let mut browser = Browser::new(report);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// The state of the interface between key presses.
+struct Browser<'a> {
+    report: &'a Report,
+    files: HashMap<&'a Path, &'a FileEntry>,
+    /// Every node of the tree, in the order it is drawn when fully expanded.
+    nodes: Vec<&'a TreeNode>,
+    expanded: HashSet<&'a Path>,
+    /// The nodes currently shown, with their depth.
+    rows: Vec<(&'a TreeNode, usize)>,
+    list: ListState,
+    /// Files and symlinks left out of the selection.
+    deselected: HashSet<&'a Path>,
+    /// Lines of the file under the cursor, read once it is reached.
+    preview: Option<(&'a Path, Vec<String>)>,
+    scroll: usize,
+    /// The search being typed, if any.
+    input: Option<String>,
+    /// The last search, lowercase.
+    query: String,
+    /// A message replacing the help line until the next key press.
+    status: Option<String>,
+}
+
+impl<'a> Browser<'a> {
+    fn new(report: &'a Report) -> Self {
+        let mut nodes = Vec::new();
+        flatten(&report.root, &mut nodes);
+        let mut browser = Browser {
+            report,
+            files: report
+                .files
+                .iter()
+                .map(|file| (file.path.as_path(), file))
+                .collect(),
+            nodes,
+            expanded: HashSet::from([report.root.path.as_path()]),
+            rows: Vec::new(),
+            list: ListState::default().with_selected(Some(0)),
+            deselected: HashSet::new(),
+            preview: None,
+            scroll: 0,
+            input: None,
+            query: String::new(),
+            status: None,
+        };
+        browser.update_rows();
+        browser
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Option<HashSet<PathBuf>>> {
+        loop {
+            self.load_preview()?;
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.status = None;
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(None);
+            }
+            if let Some(input) = &mut self.input {
+                match key.code {
+                    KeyCode::Enter => {
+                        self.query = input.to_lowercase();
+                        self.input = None;
+                        self.find(true)?;
+                    }
+                    KeyCode::Esc => self.input = None,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                KeyCode::Char('w') => {
+                    let selected: HashSet<_> = leaves(&self.report.root)
+                        .into_iter()
+                        .filter(|path| !self.deselected.contains(path))
+                        .map(Path::to_path_buf)
+                        .collect();
+                    if !selected.is_empty() {
+                        return Ok(Some(selected));
+                    }
+                    self.status = Some("Nothing is selected".to_string());
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.select(self.cursor().saturating_sub(1)),
+                KeyCode::Down | KeyCode::Char('j') => self.select(self.cursor() + 1),
+                KeyCode::Home | KeyCode::Char('g') => self.select(0),
+                KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => {
+                    let (node, _) = self.rows[self.cursor()];
+                    if node.is_dir && self.expanded.insert(&node.path) {
+                        self.update_rows();
+                    }
+                }
+                KeyCode::Left | KeyCode::Char('h') => self.close(),
+                KeyCode::Char(' ') => self.toggle(),
+                KeyCode::Char('/') => self.input = Some(String::new()),
+                KeyCode::Char('n') => self.find(true)?,
+                KeyCode::Char('N') => self.find(false)?,
+                KeyCode::PageDown => self.scroll += PAGE,
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+                _ => {}
+            }
+        }
+    }
+
+    fn cursor(&self) -> usize {
+        self.list.selected().unwrap_or(0)
+    }
+
+    /// Moves the cursor to row `row`, or the last row if there are fewer.
+    fn select(&mut self, row: usize) {
+        self.list.select(Some(row.min(self.rows.len() - 1)));
+    }
+
+    /// Collapses the directory under the cursor, or else moves the cursor
+    /// to the directory holding it.
+    fn close(&mut self) {
+        let (node, depth) = self.rows[self.cursor()];
+        if node.is_dir && depth > 0 && self.expanded.remove(node.path.as_path()) {
+            self.update_rows();
+            return;
+        }
+        if let Some(parent) = (0..self.cursor())
+            .rev()
+            .find(|&row| self.rows[row].1 < depth)
+        {
+            self.select(parent);
+        }
+    }
+
+    /// Selects every file below the cursor if any is left out, or else
+    /// leaves them all out.
+    fn toggle(&mut self) {
+        let (node, _) = self.rows[self.cursor()];
+        let paths = leaves(node);
+        if paths.iter().any(|path| self.deselected.contains(path)) {
+            for path in paths {
+                self.deselected.remove(path);
+            }
+        } else {
+            self.deselected.extend(paths);
+        }
+    }
+
+    /// Moves the cursor to the next file, or the previous one, whose path
+    /// or content holds the last search, opening the directories above it.
+    fn find(&mut self, forward: bool) -> io::Result<()> {
+        if self.query.is_empty() {
+            return Ok(());
+        }
+        let (current, _) = self.rows[self.cursor()];
+        let start = self
+            .nodes
+            .iter()
+            .position(|node| std::ptr::eq(*node, current))
+            .unwrap_or(0);
+        let count = self.nodes.len();
+        for step in 1..=count {
+            let i = if forward {
+                (start + step) % count
+            } else {
+                (start + count - step) % count
+            };
+            let node = self.nodes[i];
+            if node.is_dir || !self.matches(node)? {
+                continue;
+            }
+            for dir in node.path.ancestors().skip(1) {
+                self.expanded.insert(dir);
+            }
+            self.update_rows();
+            let row = self
+                .rows
+                .iter()
+                .position(|(row, _)| std::ptr::eq(*row, node))
+                .unwrap_or(0);
+            self.select(row);
+            return Ok(());
+        }
+        self.status = Some(format!("No file matches \"{}\"", self.query));
+        Ok(())
+    }
+
+    fn matches(&self, node: &TreeNode) -> io::Result<bool> {
+        if node
+            .path
+            .to_string_lossy()
+            .to_lowercase()
+            .contains(&self.query)
+        {
+            return Ok(true);
+        }
+        let Some(FileContent::Text(text)) = self.files.get(node.path.as_path()).map(|f| &f.content)
+        else {
+            return Ok(false);
+        };
+        Ok(self
+            .report
+            .read(text)?
+            .is_some_and(|content| content.to_lowercase().contains(&self.query)))
+    }
+
+    /// Lists the nodes below the expanded directories, keeping the cursor
+    /// on the same node.
+    fn update_rows(&mut self) {
+        let current = self.rows.get(self.cursor()).map(|(node, _)| *node);
+        self.rows.clear();
+        visible(&self.report.root, 0, &self.expanded, &mut self.rows);
+        let row = current
+            .and_then(|current| {
+                self.rows
+                    .iter()
+                    .position(|(node, _)| std::ptr::eq(*node, current))
+            })
+            .unwrap_or(0);
+        self.select(row);
+    }
+
+    /// Reads the text of the file under the cursor, if it has changed,
+    /// showing it from the top or from its first match of the last search.
+    fn load_preview(&mut self) -> io::Result<()> {
+        let (node, _) = self.rows[self.cursor()];
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|(path, _)| *path == node.path)
+        {
+            return Ok(());
+        }
+        let lines = match self
+            .files
+            .get(node.path.as_path())
+            .map(|file| &file.content)
+        {
+            Some(FileContent::Text(text)) if text.too_large => {
+                vec!["(larger than --max-file-size; content not included)".to_string()]
+            }
+            Some(FileContent::Text(text)) => match self.report.read(text)? {
+                Some(content) => content.lines().map(str::to_string).collect(),
+                None => vec!["(content withheld)".to_string()],
+            },
+            Some(FileContent::Binary) => vec!["(binary file)".to_string()],
+            Some(FileContent::Unreadable) => vec!["(could not be read)".to_string()],
+            Some(FileContent::Missing) => vec!["(removed during the scan)".to_string()],
+            None => Vec::new(),
+        };
+        self.scroll = match self.query.as_str() {
+            "" => 0,
+            query => lines
+                .iter()
+                .position(|line| line.to_lowercase().contains(query))
+                .unwrap_or(0),
+        };
+        self.preview = Some((&node.path, lines));
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Fill(1)]).areas(main);
+        let [stats, preview] =
+            Layout::vertical([Constraint::Length(10), Constraint::Fill(1)]).areas(right);
+        self.draw_tree(frame, tree);
+        self.draw_stats(frame, stats);
+        self.draw_preview(frame, preview);
+
+        let help_line = match (&self.input, &self.status) {
+            (Some(input), _) => format!("/{}", input),
+            (None, Some(status)) => status.clone(),
+            (None, None) => HELP.to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(help_line).style(Style::new().add_modifier(Modifier::REVERSED)),
+            help,
+        );
+    }
+
+    fn draw_tree(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<_> = self
+            .rows
+            .iter()
+            .map(|(node, depth)| {
+                let paths = leaves(node);
+                let left_out = paths
+                    .iter()
+                    .filter(|path| self.deselected.contains(*path))
+                    .count();
+                let check = match left_out {
+                    0 => "[x]",
+                    n if n == paths.len() => "[ ]",
+                    _ => "[-]",
+                };
+                let marker = match (node.is_dir, self.expanded.contains(node.path.as_path())) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                let mut name = node.name.clone();
+                if let Some(link) = &node.link {
+                    name = format!("{} -> {}", name, link.display());
+                }
+                let style = if left_out == paths.len() && !paths.is_empty() {
+                    Style::new().fg(Color::DarkGray)
+                } else {
+                    Style::new()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} {}{}", check, "  ".repeat(*depth), marker)),
+                    Span::styled(name, style),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Files "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_stats(&self, frame: &mut Frame, area: Rect) {
+        let (node, _) = self.rows[self.cursor()];
+        let mut lines = Vec::new();
+        if node.is_dir {
+            let files: Vec<_> = leaves(node)
+                .into_iter()
+                .filter_map(|path| self.files.get(path))
+                .collect();
+            lines.push(format!("Directory: {}", display(&node.path)));
+            lines.push(format!("Files: {}", files.len()));
+            lines.push(format!(
+                "Lines: {}",
+                files.iter().map(|file| file.line_count()).sum::<usize>()
+            ));
+            lines.push(format!(
+                "Tokens: {}",
+                files.iter().map(|file| file.token_count()).sum::<usize>()
+            ));
+            lines.push(format!("Size: {}", format_size(node.size)));
+        } else if let Some(file) = self.files.get(node.path.as_path()) {
+            let counts = file.line_counts();
+            lines.push(format!("File: {}", display(&node.path)));
+            lines.push(format!(
+                "Language: {}",
+                file.language().unwrap_or("unknown")
+            ));
+            lines.push(format!(
+                "Lines: {} ({} code, {} comments, {} blank)",
+                file.line_count(),
+                counts.code,
+                counts.comments,
+                counts.blanks
+            ));
+            lines.push(format!("Tokens: {}", file.token_count()));
+            lines.push(format!("Size: {}", format_size(file.size)));
+            if let Some(encoding) = file.encoding() {
+                lines.push(format!("Encoding: {}", encoding));
+            }
+        } else {
+            lines.push(format!("File: {}", display(&node.path)));
+            lines.push("Listed in the tree only".to_string());
+        }
+
+        let selected: Vec<_> = self
+            .report
+            .files
+            .iter()
+            .filter(|file| !self.deselected.contains(file.path.as_path()))
+            .collect();
+        lines.push(String::new());
+        lines.push(format!(
+            "Selected: {} of {} files, {} lines, {} tokens",
+            selected.len(),
+            self.report.files.len(),
+            selected.iter().map(|file| file.line_count()).sum::<usize>(),
+            selected
+                .iter()
+                .map(|file| file.token_count())
+                .sum::<usize>()
+        ));
+        let lines: Vec<_> = lines.into_iter().map(Line::from).collect();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Statistics ")),
+            area,
+        );
+    }
+
+    fn draw_preview(&mut self, frame: &mut Frame, area: Rect) {
+        let Some((_, lines)) = &self.preview else {
+            return;
+        };
+        let height = usize::from(area.height.saturating_sub(2));
+        self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+        let width = lines.len().to_string().len();
+        let shown: Vec<_> = lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .map(|(i, line)| {
+                let style = if !self.query.is_empty() && line.to_lowercase().contains(&self.query) {
+                    Style::new().fg(Color::Black).bg(Color::Yellow)
+                } else {
+                    Style::new()
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:>width$} ", i + 1),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                    Span::styled(line.replace('\t', "    "), style),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(shown).block(Block::bordered().title(" Preview ")),
+            area,
+        );
+    }
+}
+
+/// `node` and every node below it, depth first.
+fn flatten<'a>(node: &'a TreeNode, nodes: &mut Vec<&'a TreeNode>) {
+    nodes.push(node);
+    for child in &node.children {
+        flatten(child, nodes);
+    }
+}
+
+/// `node` and the nodes below it that are not inside a collapsed
+/// directory, with their depth below the root.
+fn visible<'a>(
+    node: &'a TreeNode,
+    depth: usize,
+    expanded: &HashSet<&Path>,
+    rows: &mut Vec<(&'a TreeNode, usize)>,
+) {
+    rows.push((node, depth));
+    if expanded.contains(node.path.as_path()) {
+        for child in &node.children {
+            visible(child, depth + 1, expanded, rows);
+        }
+    }
+}
+
+/// The paths of the files and symlinks at or below `node`.
+fn leaves(node: &TreeNode) -> Vec<&Path> {
+    if !node.is_dir {
+        return vec![node.path.as_path()];
+    }
+    node.children.iter().flat_map(leaves).collect()
+}
+
+/// `path` as shown in the statistics, with the root as `.`.
+fn display(path: &Path) -> String {
+    if path.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
@@ -0,0 +1,81 @@
+//! Filesystem notifications for `codetree watch`.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long changes must stop arriving before a batch is handed out, so an
+/// editor saving several files, or one file in several writes, causes a
+/// single regeneration.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Watches a directory and everything below it.
+pub struct Watcher {
+    root: PathBuf,
+    /// Paths whose changes are of no interest, such as the report itself.
+    ignored: Vec<PathBuf>,
+    events: Receiver<notify::Result<Event>>,
+    // Dropping the watcher stops the notifications.
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub fn new(root: &Path) -> io::Result<Self> {
+        let root = root.canonicalize()?;
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(io::Error::other)?;
+        Ok(Watcher {
+            root,
+            ignored: Vec::new(),
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Leaves out changes to `path`, or below it if it is a directory. The
+    /// path must exist.
+    pub fn ignore(&mut self, path: &Path) -> io::Result<()> {
+        self.ignored.push(path.canonicalize()?);
+        Ok(())
+    }
+
+    /// Blocks until something below the root changes, waits for changes to
+    /// settle, and returns the changed paths relative to the root, sorted
+    /// and without duplicates.
+    pub fn changes(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = BTreeSet::new();
+        loop {
+            let event = if paths.is_empty() {
+                self.events.recv().map_err(io::Error::other)?
+            } else {
+                match self.events.recv_timeout(SETTLE) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(err) => return Err(io::Error::other(err)),
+                }
+            };
+            let event = event.map_err(io::Error::other)?;
+            // Reading files, including our own reads, is not a change.
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if self.ignored.iter().any(|ignored| path.starts_with(ignored)) {
+                    continue;
+                }
+                if let Ok(relative) = path.strip_prefix(&self.root) {
+                    if !relative.as_os_str().is_empty() {
+                        paths.insert(relative.to_path_buf());
+                    }
+                }
+            }
+        }
+        Ok(paths.into_iter().collect())
+    }
+}
@@ -0,0 +1,239 @@
+//! Finding the member packages of a monorepo from its workspace manifests,
+//! so statistics can be reported per package.
+
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directories never searched for packages.
+const SKIPPED_DIRS: [&str; 3] = [".git", "node_modules", "target"];
+
+/// How deep below the root Nx `project.json` files are looked for.
+const NX_DEPTH: usize = 4;
+
+/// Returns the member packages declared by the workspace manifests in
+/// `root`, relative to it and in path order: Cargo `[workspace]` members,
+/// pnpm, Yarn and npm workspaces, Lerna packages, Nx projects and `go.work`
+/// modules. Members that do not exist, and the root itself, are left out.
+pub fn members(root: &Path) -> Vec<PathBuf> {
+    let mut patterns = Vec::new();
+    let mut excluded = Vec::new();
+    cargo(root, &mut patterns, &mut excluded);
+    pnpm(root, &mut patterns);
+    package_json(root, &mut patterns);
+    lerna(root, &mut patterns);
+    go_work(root, &mut patterns);
+
+    let excluded: BTreeSet<_> = patterns
+        .iter()
+        .filter_map(|pattern| pattern.strip_prefix('!'))
+        .chain(excluded.iter().map(String::as_str))
+        .flat_map(|pattern| expand(root, pattern))
+        .collect();
+    let mut members: BTreeSet<_> = patterns
+        .iter()
+        .filter(|pattern| !pattern.starts_with('!'))
+        .flat_map(|pattern| expand(root, pattern))
+        .collect();
+    members.extend(nx(root));
+    members
+        .into_iter()
+        .filter(|member| !member.as_os_str().is_empty() && !excluded.contains(member))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CargoManifest {
+    workspace: Option<CargoWorkspace>,
+}
+
+#[derive(Deserialize)]
+struct CargoWorkspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// Members and exclusions of the `[workspace]` table of `Cargo.toml`.
+fn cargo(root: &Path, patterns: &mut Vec<String>, excluded: &mut Vec<String>) {
+    let Ok(text) = fs::read_to_string(root.join("Cargo.toml")) else {
+        return;
+    };
+    if let Ok(CargoManifest {
+        workspace: Some(workspace),
+    }) = toml::from_str(&text)
+    {
+        patterns.extend(workspace.members);
+        excluded.extend(workspace.exclude);
+    }
+}
+
+/// The `packages` list of `pnpm-workspace.yaml`, read line by line as it
+/// is a plain list of quoted or unquoted strings.
+fn pnpm(root: &Path, patterns: &mut Vec<String>) {
+    let Ok(text) = fs::read_to_string(root.join("pnpm-workspace.yaml")) else {
+        return;
+    };
+    let mut in_packages = false;
+    for line in text.lines() {
+        let line = line.split(" #").next().unwrap_or_default().trim_end();
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = line.trim() == "packages:";
+            continue;
+        }
+        if let Some(item) = line.trim().strip_prefix('-').filter(|_| in_packages) {
+            patterns.push(item.trim().trim_matches(['"', '\'']).to_string());
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Workspaces {
+    List(Vec<String>),
+    /// Yarn's `{ "packages": [...], "nohoist": [...] }` form.
+    Object {
+        #[serde(default)]
+        packages: Vec<String>,
+    },
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    workspaces: Option<Workspaces>,
+}
+
+/// The `workspaces` of `package.json`, as used by Yarn and npm.
+fn package_json(root: &Path, patterns: &mut Vec<String>) {
+    let Ok(text) = fs::read_to_string(root.join("package.json")) else {
+        return;
+    };
+    if let Ok(PackageJson {
+        workspaces: Some(Workspaces::List(list) | Workspaces::Object { packages: list }),
+    }) = serde_json::from_str(&text)
+    {
+        patterns.extend(list);
+    }
+}
+
+#[derive(Deserialize)]
+struct LernaJson {
+    #[serde(default)]
+    packages: Vec<String>,
+}
+
+/// The `packages` of `lerna.json`.
+fn lerna(root: &Path, patterns: &mut Vec<String>) {
+    let Ok(text) = fs::read_to_string(root.join("lerna.json")) else {
+        return;
+    };
+    if let Ok(lerna) = serde_json::from_str::<LernaJson>(&text) {
+        patterns.extend(lerna.packages);
+    }
+}
+
+/// The `use` directives of `go.work`, in their single-line and block forms.
+fn go_work(root: &Path, patterns: &mut Vec<String>) {
+    let Ok(text) = fs::read_to_string(root.join("go.work")) else {
+        return;
+    };
+    let mut in_block = false;
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                patterns.push(line.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            match rest.trim() {
+                "(" => in_block = true,
+                path if !path.is_empty() && rest.starts_with([' ', '\t']) => {
+                    patterns.push(path.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Directories holding an Nx `project.json`, which Nx workspaces use
+/// instead of listing their projects.
+fn nx(root: &Path) -> Vec<PathBuf> {
+    if !root.join("nx.json").is_file() {
+        return Vec::new();
+    }
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(NX_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        .flatten()
+        .filter(|entry| entry.file_name() == "project.json")
+        .filter_map(|entry| {
+            let dir = entry.path().parent()?.strip_prefix(root).ok()?;
+            Some(dir.to_path_buf())
+        })
+        .collect()
+}
+
+/// The directories below `root` matching the workspace `pattern`, relative
+/// to `root`. Components may hold `*` and other glob syntax; `**` matches
+/// any number of directories.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::new()];
+    for component in Path::new(pattern.trim_end_matches('/')).components() {
+        let part = match component {
+            Component::Normal(part) => part.to_string_lossy(),
+            Component::CurDir => continue,
+            // Packages outside the root are not part of the report.
+            _ => return Vec::new(),
+        };
+        dirs = if part == "**" {
+            dirs.iter().flat_map(|dir| below(root, dir)).collect()
+        } else if part.contains(['*', '?', '[', '{']) {
+            let Ok(glob) = Glob::new(&part).map(|glob| glob.compile_matcher()) else {
+                return Vec::new();
+            };
+            dirs.iter()
+                .flat_map(|dir| children(root, dir, &glob))
+                .collect()
+        } else {
+            dirs.iter().map(|dir| dir.join(part.as_ref())).collect()
+        };
+    }
+    dirs.retain(|dir| root.join(dir).is_dir());
+    dirs
+}
+
+/// The subdirectories of `dir` whose names match `glob`.
+fn children(root: &Path, dir: &Path, glob: &GlobMatcher) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root.join(dir)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| entry.file_name())
+        .filter(|name| {
+            glob.is_match(name) && !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref())
+        })
+        .map(|name| dir.join(name))
+        .collect()
+}
+
+/// `dir` and every directory below it.
+fn below(root: &Path, dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root.join(dir))
+        .into_iter()
+        .filter_entry(|entry| !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        .flatten()
+        .filter(|entry| entry.file_type().is_dir())
+        .filter_map(|entry| Some(entry.path().strip_prefix(root).ok()?.to_path_buf()))
+        .collect()
+}